@@ -5,6 +5,7 @@ use futures::stream;
 use num_bigint::BigUint;
 use num_traits::identities::One;
 
+#[allow(dead_code)]
 struct Fibonacci {
     one_ago: BigUint,
     two_ago: BigUint,
@@ -0,0 +1,101 @@
+use std::alloc::Allocator;
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::{primitive, size_hint};
+use super::{ArrayAccess, Decoder, FromStream, FromStreamWith, SeqAccess, Visitor};
+
+struct VecVisitor<T, A> {
+    alloc: A,
+    marker: PhantomData<fn() -> T>,
+}
+
+macro_rules! vec_visitor {
+    ($($visit_array:ident($elem:ty)),+) => {
+        #[async_trait]
+        impl<T, A> Visitor for VecVisitor<T, A>
+        where
+            T: FromStream<Context = ()>,
+            A: Allocator + Send,
+        {
+            type Value = Vec<T, A>;
+
+            fn expecting() -> &'static str {
+                "a sequence, to decode as a Vec"
+            }
+
+            async fn visit_seq<S: SeqAccess>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+                let hint = seq.size_hint();
+                let mut values = Vec::with_capacity_in(size_hint::cautious(hint), self.alloc);
+
+                while let Some(value) = seq.next_element(()).await? {
+                    values.push(value);
+                }
+
+                Ok(values)
+            }
+
+            $(
+                async fn $visit_array<S: ArrayAccess<$elem>>(
+                    self,
+                    array: S,
+                ) -> Result<Self::Value, S::Error> {
+                    let mut values = Vec::new_in(self.alloc);
+                    primitive::decode_elements((), array, |value| values.push(value)).await?;
+                    Ok(values)
+                }
+            )+
+        }
+    };
+}
+
+vec_visitor!(
+    visit_array_bool(bool),
+    visit_array_i8(i8),
+    visit_array_i16(i16),
+    visit_array_i32(i32),
+    visit_array_i64(i64),
+    visit_array_u8(u8),
+    visit_array_u16(u16),
+    visit_array_u32(u32),
+    visit_array_u64(u64),
+    visit_array_f32(f32),
+    visit_array_f64(f64)
+);
+
+/// Decode a `Vec` in the allocator given as its context, e.g. a per-request arena.
+#[async_trait]
+impl<T, A> FromStreamWith<A> for Vec<T, A>
+where
+    T: FromStream<Context = ()>,
+    A: Allocator + Clone + Send,
+{
+    async fn from_stream_with<D: Decoder>(
+        alloc: &mut A,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let visitor = VecVisitor {
+            alloc: alloc.clone(),
+            marker: PhantomData,
+        };
+
+        decoder.decode_seq(visitor).await
+    }
+}
+
+/// Decode a `Box` in the allocator given as its context, e.g. a per-request arena.
+#[async_trait]
+impl<T, A> FromStreamWith<A> for Box<T, A>
+where
+    T: FromStream<Context = ()>,
+    A: Allocator + Clone + Send,
+{
+    async fn from_stream_with<D: Decoder>(
+        alloc: &mut A,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let value = T::from_stream((), decoder).await?;
+        Ok(Box::new_in(value, alloc.clone()))
+    }
+}
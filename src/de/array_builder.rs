@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::Error;
+
+/// A helper to assemble a fixed-size array `[T; N]` one element at a time, e.g. in
+/// [`Visitor::visit_seq`](super::Visitor::visit_seq), without `unsafe` code.
+pub struct ArrayBuilder<T, const N: usize> {
+    elements: Vec<T>,
+}
+
+impl<T, const N: usize> ArrayBuilder<T, N> {
+    /// Construct a new, empty [`ArrayBuilder`].
+    pub fn new() -> Self {
+        Self {
+            elements: Vec::with_capacity(N),
+        }
+    }
+
+    /// Return `true` if this builder holds `N` elements.
+    pub fn is_full(&self) -> bool {
+        self.elements.len() == N
+    }
+
+    /// Return the number of elements pushed so far.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Return `true` if no elements have been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Push the next element, or return an "invalid length" error if the array is already full.
+    pub fn push<E: Error>(&mut self, element: T) -> Result<(), E> {
+        if self.is_full() {
+            Err(Error::invalid_length(N + 1, ExpectedLength(N)))
+        } else {
+            self.elements.push(element);
+            Ok(())
+        }
+    }
+
+    /// Return the completed array, or an "invalid length" error if fewer than `N` elements
+    /// were pushed.
+    pub fn finish<E: Error>(self) -> Result<[T; N], E> {
+        let len = self.elements.len();
+
+        self.elements
+            .try_into()
+            .map_err(|_| Error::invalid_length(len, ExpectedLength(N)))
+    }
+}
+
+impl<T, const N: usize> Default for ArrayBuilder<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ExpectedLength(usize);
+
+impl fmt::Display for ExpectedLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an array of length {}", self.0)
+    }
+}
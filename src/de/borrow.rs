@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream};
+
+/// A [`Decoder`] over an input which is already buffered in memory, and so can expose
+/// borrowed views of its string and binary values without copying them.
+///
+/// This is an opt-in extension; the owned decoding path is unaffected.
+#[async_trait]
+pub trait BorrowDecoder<'de>: Decoder {
+    /// Decode a string slice borrowed from the input.
+    async fn decode_borrowed_str(&mut self) -> Result<&'de str, Self::Error>;
+
+    /// Decode a byte slice borrowed from the input.
+    async fn decode_borrowed_bytes(&mut self) -> Result<&'de [u8], Self::Error>;
+}
+
+/// A data structure which can be decoded from a [`BorrowDecoder`], possibly borrowing
+/// from its input.
+///
+/// Every [`FromStream`] type also implements `FromStreamRef`.
+#[async_trait]
+pub trait FromStreamRef<'de>: Send + Sized {
+    /// The decoding context of this type.
+    type Context: Send;
+
+    /// Decode this type from a [`BorrowDecoder`].
+    async fn from_stream_ref<D: BorrowDecoder<'de>>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error>;
+}
+
+#[async_trait]
+impl<'de, T: FromStream> FromStreamRef<'de> for T {
+    type Context = T::Context;
+
+    async fn from_stream_ref<D: BorrowDecoder<'de>>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        T::from_stream(context, decoder).await
+    }
+}
+
+#[async_trait]
+impl<'de> FromStreamRef<'de> for &'de str {
+    type Context = ();
+
+    async fn from_stream_ref<D: BorrowDecoder<'de>>(
+        _context: (),
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_borrowed_str().await
+    }
+}
+
+#[async_trait]
+impl<'de> FromStreamRef<'de> for &'de [u8] {
+    type Context = ();
+
+    async fn from_stream_ref<D: BorrowDecoder<'de>>(
+        _context: (),
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_borrowed_bytes().await
+    }
+}
@@ -0,0 +1,239 @@
+use std::mem::size_of;
+
+use async_trait::async_trait;
+
+use super::with::With;
+use super::{
+    ArrayAccess, Decoder, Error, FromStreamWith, IgnoredAny, MapAccess, SeqAccess, TextAccess,
+    Visitor,
+};
+
+const BUF_SIZE: usize = 256;
+
+/// A limit on how much input may be skipped by decoding an [`IgnoredAny`] with
+/// [`FromStreamWith`], or by calling [`Decoder::skip_value_within`], e.g. to skip the unknown
+/// fields of an untrusted document without spending an unbounded amount of time on them.
+///
+/// A budget limits the depth of nested maps and sequences in a skipped value, and the approximate
+/// total size of the skipped values in bytes, counting at least one byte for each value. The same
+/// budget can be shared between every value skipped while decoding a document. Exceeding the
+/// budget is a decoding error.
+///
+/// Example:
+/// ```
+/// # use std::fmt;
+/// # use destream::de::{self, Decoder, Replay, SkipBudget, Trace};
+/// # #[derive(Debug)]
+/// # struct Error(String);
+/// # impl fmt::Display for Error {
+/// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// #         f.write_str(&self.0)
+/// #     }
+/// # }
+/// # impl std::error::Error for Error {}
+/// # impl de::Error for Error {
+/// #     fn custom<T: fmt::Display>(msg: T) -> Self {
+/// #         Self(msg.to_string())
+/// #     }
+/// # }
+/// // an option nested more deeply than the budget allows
+/// let mut value = Trace::Unit;
+/// for _ in 0..64 {
+///     value = Trace::Some(Box::new(value));
+/// }
+///
+/// let mut decoder = Replay::<Error>::new(value);
+/// let mut budget = SkipBudget::new(32, 4_096);
+/// let skipped = futures::executor::block_on(decoder.skip_value_within(&mut budget));
+/// assert!(skipped.unwrap_err().to_string().contains("maximum depth of 32"));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SkipBudget {
+    max_depth: usize,
+    max_bytes: usize,
+    depth: usize,
+    bytes: usize,
+}
+
+impl SkipBudget {
+    /// Construct a new [`SkipBudget`] which allows values nested up to `max_depth` levels deep
+    /// and up to `max_bytes` bytes in total to be skipped.
+    pub fn new(max_depth: usize, max_bytes: usize) -> Self {
+        Self {
+            max_depth,
+            max_bytes,
+            depth: 0,
+            bytes: 0,
+        }
+    }
+
+    /// The approximate number of bytes skipped so far.
+    pub fn spent(&self) -> usize {
+        self.bytes
+    }
+
+    /// The approximate number of bytes which can still be skipped.
+    pub fn remaining(&self) -> usize {
+        self.max_bytes - self.bytes
+    }
+
+    fn spend<E: Error>(&mut self, bytes: usize) -> Result<(), E> {
+        let bytes = Ord::max(bytes, 1);
+
+        if bytes > self.remaining() {
+            self.bytes = self.max_bytes;
+            Err(E::custom(format_args!(
+                "skipped values exceed the limit of {} bytes",
+                self.max_bytes
+            )))
+        } else {
+            self.bytes += bytes;
+            Ok(())
+        }
+    }
+
+    fn enter<E: Error>(&mut self) -> Result<(), E> {
+        if self.depth < self.max_depth {
+            self.depth += 1;
+            self.spend(1)
+        } else {
+            Err(E::custom(format_args!(
+                "a skipped value exceeds the maximum depth of {}",
+                self.max_depth
+            )))
+        }
+    }
+
+    fn exit(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+struct BudgetVisitor<'a> {
+    budget: &'a mut SkipBudget,
+}
+
+async fn skip_array<T, A>(budget: &mut SkipBudget, mut array: A) -> Result<IgnoredAny, A::Error>
+where
+    T: Copy + Default + Send,
+    A: ArrayAccess<T>,
+{
+    budget.spend(1)?;
+
+    let mut buffer = [T::default(); BUF_SIZE];
+    loop {
+        let len = array.buffer(&mut buffer).await?;
+        if len == 0 {
+            break Ok(IgnoredAny);
+        }
+
+        budget.spend(len * size_of::<T>())?;
+    }
+}
+
+macro_rules! budget_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array_kind:ident),+
+    ) => {
+        #[async_trait]
+        impl<'a> Visitor for BudgetVisitor<'a> {
+            type Value = IgnoredAny;
+
+            fn expecting() -> &'static str {
+                "anything at all"
+            }
+
+            $(
+                fn $visit<E: Error>(self, _v: $ty) -> Result<Self::Value, E> {
+                    self.budget.spend(size_of::<$ty>()).map(|()| IgnoredAny)
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    skip_array(self.budget, array).await
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                self.budget.spend(v.len()).map(|()| IgnoredAny)
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                mut text: A,
+            ) -> Result<Self::Value, A::Error> {
+                self.budget.spend(1)?;
+
+                let mut buffer = [0u8; BUF_SIZE];
+                loop {
+                    let len = text.buffer(&mut buffer).await?;
+                    if len == 0 {
+                        break Ok(IgnoredAny);
+                    }
+
+                    self.budget.spend(len)?;
+                }
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                self.budget.spend(1).map(|()| IgnoredAny)
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                self.budget.spend(1).map(|()| IgnoredAny)
+            }
+
+            async fn visit_some<D: Decoder>(
+                self,
+                decoder: &mut D,
+            ) -> Result<Self::Value, D::Error> {
+                self.budget.enter()?;
+
+                let budget = &mut *self.budget;
+                let IgnoredAny = decoder.decode_ignored_any(BudgetVisitor { budget }).await?;
+
+                self.budget.exit();
+                Ok(IgnoredAny)
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                self.budget.enter()?;
+
+                while let Some(With { value: IgnoredAny, .. }) = map.next_key(&mut *self.budget).await? {
+                    let IgnoredAny = map.next_value_with(self.budget).await?;
+                }
+
+                self.budget.exit();
+                Ok(IgnoredAny)
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                self.budget.enter()?;
+
+                while let Some(IgnoredAny) = seq.next_element_with(self.budget).await? {
+                    // Gobble
+                }
+
+                self.budget.exit();
+                Ok(IgnoredAny)
+            }
+        }
+    };
+}
+
+visit_methods!(budget_visitor);
+
+#[async_trait]
+impl FromStreamWith<SkipBudget> for IgnoredAny {
+    async fn from_stream_with<D: Decoder>(
+        budget: &mut SkipBudget,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_ignored_any(BudgetVisitor { budget }).await
+    }
+}
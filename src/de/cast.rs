@@ -0,0 +1,29 @@
+/// A lossy numeric conversion, equivalent to an `as` cast, used by
+/// [`ArrayAccess::buffer_lossy`](super::ArrayAccess::buffer_lossy).
+pub trait CastFrom<T> {
+    /// Convert the given `value` as if with `value as Self`.
+    fn cast_from(value: T) -> Self;
+}
+
+macro_rules! cast_from {
+    ($from:ty => $($to:ty),+) => {
+        $(
+            impl CastFrom<$from> for $to {
+                #[inline]
+                fn cast_from(value: $from) -> Self {
+                    value as $to
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! cast {
+    ($($from:ty),+) => {
+        $(
+            cast_from!($from => i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+        )+
+    };
+}
+
+cast!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
@@ -0,0 +1,278 @@
+use async_trait::async_trait;
+use futures::future::TryFutureExt;
+
+use super::{
+    ArrayAccess, Decoder, Error, FromStream, KeyHint, MapAccess, SeqAccess, TextAccess, Visitor,
+};
+
+/// Panic with the given message if this is a debug build.
+fn violation(msg: std::fmt::Arguments) {
+    if cfg!(debug_assertions) {
+        panic!("decoder contract violated: {msg}");
+    }
+}
+
+/// A [`Decoder`] which checks, in debug builds, that the wrapped decoder keeps the contract of
+/// the decoding traits, recursively, e.g. to catch bugs in a new format during development.
+///
+/// The checks are:
+///  - a `size_hint` of a map or sequence must be exact, i.e. the number of entries or elements
+///    remaining, and consistent with the first `size_hint` it reported
+///  - `ArrayAccess::buffer` and `TextAccess::buffer` must not report more values than fit in the
+///    given buffer, and a chunk of text must be valid UTF-8 on its own
+///
+/// An inexact size hint is returned as an error, since a format may be able to recover from it.
+/// Any other violation panics.
+///
+/// A [`Visitor`] is consumed when it's called, so there is no need to check that a decoder
+/// calls it at most once.
+///
+/// In release builds, a [`DebugChecked`] decoder only forwards each call to the wrapped decoder.
+pub struct DebugChecked<'a, D> {
+    decoder: &'a mut D,
+}
+
+impl<'a, D> DebugChecked<'a, D> {
+    /// Wrap the given `decoder` so that it's checked in debug builds.
+    pub fn new(decoder: &'a mut D) -> Self {
+        Self { decoder }
+    }
+}
+
+forward_decoder!(
+    [<'a, D: Decoder>] DebugChecked<'a, D>, D::Error;
+    |this, visitor, _len| this.decoder => CheckedVisitor { visitor };
+
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.decoder.skip_value().await
+    }
+);
+
+struct CheckedVisitor<V> {
+    visitor: V,
+}
+
+forward_visitor!(
+    [<V: Visitor>] CheckedVisitor<V> => V;
+    |this| this.visitor;
+    |array| CheckedArray { array };
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visitor.visit_string(v)
+    }
+
+    async fn visit_text_stream<A: TextAccess>(self, text: A) -> Result<Self::Value, A::Error> {
+        self.visitor.visit_text_stream(CheckedText { text }).await
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        let mut decoder = DebugChecked::new(decoder);
+        self.visitor.visit_some(&mut decoder).await
+    }
+
+    async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.visitor.visit_map(CheckedMap {
+                map,
+                remaining: Remaining::new("map"),
+            }).await
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.visitor.visit_seq(CheckedSeq {
+                seq,
+                remaining: Remaining::new("sequence"),
+            }).await
+    }
+);
+
+/// A value decoded with a [`DebugChecked`] decoder.
+struct Checked<T>(T);
+
+#[async_trait]
+impl<T: FromStream> FromStream for Checked<T> {
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let mut decoder = DebugChecked::new(decoder);
+        T::from_stream(context, &mut decoder).map_ok(Self).await
+    }
+}
+
+/// Tracks the items read from a map or sequence, to check that its `size_hint` is exact.
+struct Remaining {
+    kind: &'static str,
+    read: usize,
+    expected: Option<usize>,
+}
+
+impl Remaining {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            read: 0,
+            expected: None,
+        }
+    }
+
+    /// Check the `hint` reported before reading the next item against the first hint reported,
+    /// less the number of items read since then, and whether there was a next item.
+    fn check<E: Error>(&mut self, hint: Option<usize>, more: bool) -> Result<(), E> {
+        if !cfg!(debug_assertions) {
+            return Ok(());
+        }
+
+        let Self { kind, read, .. } = *self;
+
+        if let (Some(expected), Some(hint)) = (self.expected, hint) {
+            if hint != expected {
+                return Err(Error::custom(format_args!(
+                    "a {kind} reported {hint} items remaining, not {expected}, after {read} items"
+                )));
+            }
+        }
+
+        match self.expected.or(hint) {
+            Some(0) if more => {
+                return Err(Error::custom(format_args!(
+                    "a {kind} reported no items remaining but yielded item {read}"
+                )))
+            }
+            Some(remaining) if !more && remaining > 0 => {
+                return Err(Error::custom(format_args!(
+                    "a {kind} reported {remaining} items remaining but ended after {read} items"
+                )))
+            }
+            _ => {}
+        }
+
+        self.read += 1;
+        self.expected = self
+            .expected
+            .or(hint)
+            .map(|remaining| remaining.saturating_sub(1));
+
+        Ok(())
+    }
+}
+
+struct CheckedMap<A> {
+    map: A,
+    remaining: Remaining,
+}
+
+#[async_trait]
+impl<A: MapAccess> MapAccess for CheckedMap<A> {
+    type Error = A::Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.next_key_as(KeyHint::Any, context).await
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        let size_hint = self.map.size_hint();
+        let key: Option<Checked<K>> = self.map.next_key_as(hint, context).await?;
+        self.remaining.check(size_hint, key.is_some())?;
+        Ok(key.map(|Checked(key)| key))
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        let Checked(value) = self.map.next_value(context).await?;
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct CheckedSeq<A> {
+    seq: A,
+    remaining: Remaining,
+}
+
+#[async_trait]
+impl<A: SeqAccess> SeqAccess for CheckedSeq<A> {
+    type Error = A::Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        let size_hint = self.seq.size_hint();
+        let element: Option<Checked<T>> = self.seq.next_element(context).await?;
+        self.remaining.check(size_hint, element.is_some())?;
+        Ok(element.map(|Checked(element)| element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+struct CheckedArray<A> {
+    array: A,
+}
+
+#[async_trait]
+impl<T: Send, A: ArrayAccess<T>> ArrayAccess<T> for CheckedArray<A> {
+    type Error = A::Error;
+
+    async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, Self::Error> {
+        let len = self.array.buffer(buffer).await?;
+
+        if len > buffer.len() {
+            violation(format_args!(
+                "an array wrote {len} values into a buffer of length {}",
+                buffer.len()
+            ));
+        }
+
+        Ok(len)
+    }
+}
+
+struct CheckedText<A> {
+    text: A,
+}
+
+#[async_trait]
+impl<A: TextAccess> TextAccess for CheckedText<A> {
+    type Error = A::Error;
+
+    async fn buffer(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = self.text.buffer(buffer).await?;
+
+        if len > buffer.len() {
+            violation(format_args!(
+                "a string wrote {len} bytes into a buffer of length {}",
+                buffer.len()
+            ));
+        } else if cfg!(debug_assertions) {
+            if let Err(cause) = std::str::from_utf8(&buffer[..len]) {
+                violation(format_args!(
+                    "a chunk of a string is not valid UTF-8: {cause}"
+                ));
+            }
+        }
+
+        Ok(len)
+    }
+}
@@ -0,0 +1,141 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::{ArrayAccess, Error, FieldKey, FromStream, KeyHint, MapAccess, SeqAccess, TextAccess};
+
+/// Convert a decoding error of one type into another, preserving its message.
+///
+/// This is useful when composing visitors, since each `visit_*` method is generic over its own
+/// error type, independently of the error types of the access traits passed to the others.
+pub fn convert_error<E: Error, F: Error>(error: E) -> F {
+    F::custom(error)
+}
+
+/// An access adapter which converts every error returned by the wrapped
+/// [`MapAccess`], [`SeqAccess`], [`ArrayAccess`], or [`TextAccess`] into the error type `E`.
+///
+/// This allows a visitor combinator to pass the access it receives on to an inner
+/// [`Visitor`](super::Visitor) whose result must have the combinator's own error type.
+///
+/// Example:
+/// ```
+/// use destream::de::{self, WithError};
+///
+/// /// Decode a map, but report any error as a `MyError`.
+/// async fn visit_map<V, A, E>(visitor: V, access: A) -> Result<V::Value, E>
+/// where
+///     V: de::Visitor,
+///     A: de::MapAccess,
+///     E: de::Error,
+/// {
+///     visitor.visit_map(WithError::<A, E>::new(access)).await
+/// }
+/// ```
+pub struct WithError<A, E> {
+    access: A,
+    error: PhantomData<fn() -> E>,
+}
+
+impl<A, E> WithError<A, E> {
+    /// Wrap the given `access` so that its errors are converted into `E`.
+    pub fn new(access: A) -> Self {
+        Self {
+            access,
+            error: PhantomData,
+        }
+    }
+
+    /// Return the wrapped access.
+    pub fn into_inner(self) -> A {
+        self.access
+    }
+}
+
+#[async_trait]
+impl<A: MapAccess, E: Error> MapAccess for WithError<A, E> {
+    type Error = E;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.access.next_key(context).await.map_err(convert_error)
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        self.access.next_value(context).await.map_err(convert_error)
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.access
+            .next_key_as(hint, context)
+            .await
+            .map_err(convert_error)
+    }
+
+    async fn match_key(
+        &mut self,
+        fields: &'static [&'static str],
+    ) -> Result<Option<FieldKey>, Self::Error> {
+        self.access.match_key(fields).await.map_err(convert_error)
+    }
+
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.access.skip_value().await.map_err(convert_error)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.access.size_hint()
+    }
+}
+
+#[async_trait]
+impl<A: SeqAccess, E: Error> SeqAccess for WithError<A, E> {
+    type Error = E;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        self.access
+            .next_element(context)
+            .await
+            .map_err(convert_error)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.access.size_hint()
+    }
+}
+
+#[async_trait]
+impl<T: Send, A: ArrayAccess<T>, E: Error> ArrayAccess<T> for WithError<A, E> {
+    type Error = E;
+
+    async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, Self::Error> {
+        self.access.buffer(buffer).await.map_err(convert_error)
+    }
+
+    async fn fold<B, F>(&mut self, init: B, f: F) -> Result<B, Self::Error>
+    where
+        T: Copy + Default + Send,
+        B: Send,
+        F: FnMut(B, T) -> B + Send,
+    {
+        self.access.fold(init, f).await.map_err(convert_error)
+    }
+}
+
+#[async_trait]
+impl<A: TextAccess, E: Error> TextAccess for WithError<A, E> {
+    type Error = E;
+
+    async fn buffer(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        self.access.buffer(buffer).await.map_err(convert_error)
+    }
+}
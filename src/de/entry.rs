@@ -0,0 +1,70 @@
+use std::any::type_name;
+use std::fmt;
+
+use super::{Decoder, FromStream};
+
+/// Decode a `T` from the given `decoder`, using the given `context`.
+///
+/// This is equivalent to `T::from_stream(context, decoder)`, but gives application code
+/// a format-agnostic call shape.
+pub async fn decode_from<T, D>(context: T::Context, decoder: &mut D) -> Result<T, D::Error>
+where
+    T: FromStream,
+    D: Decoder,
+{
+    T::from_stream(context, decoder).await
+}
+
+/// Decode a `T` from the given `decoder`, using the given `context`, and on failure return
+/// a [`DecodeError`] which records the name of the type which could not be decoded.
+pub async fn try_decode<T, D>(
+    context: T::Context,
+    decoder: &mut D,
+) -> Result<T, DecodeError<D::Error>>
+where
+    T: FromStream,
+    D: Decoder,
+{
+    T::from_stream(context, decoder)
+        .await
+        .map_err(|source| DecodeError {
+            type_name: type_name::<T>(),
+            source,
+        })
+}
+
+/// An error returned by [`try_decode`].
+#[derive(Debug)]
+pub struct DecodeError<E> {
+    type_name: &'static str,
+    source: E,
+}
+
+impl<E> DecodeError<E> {
+    /// The name of the type which could not be decoded.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Borrow the [`Decoder`] error which caused this error.
+    pub fn as_inner(&self) -> &E {
+        &self.source
+    }
+
+    /// Return the [`Decoder`] error which caused this error.
+    pub fn into_inner(self) -> E {
+        self.source
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to decode {}: {}", self.type_name, self.source)
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DecodeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use super::{Decoder, Error, FromStream, Visitor};
+
+/// A map key matched against a list of expected field names by
+/// [`MapAccess::match_key`](super::MapAccess::match_key).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FieldKey {
+    /// The key is the field name at this index in the list.
+    Known(usize),
+    /// The key is not in the list. The caller must still consume its value.
+    Unknown,
+}
+
+/// The type of map key which a visitor prefers, passed to
+/// [`MapAccess::next_key_as`](super::MapAccess::next_key_as).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KeyHint {
+    /// The visitor has no preference.
+    #[default]
+    Any,
+    /// The visitor prefers a string key, e.g. a field name.
+    String,
+    /// The visitor prefers an integer key, e.g. a compact field identifier.
+    Integer,
+}
+
+/// A map key decoded as a [`FieldKey`].
+pub(super) struct FieldIndex(pub FieldKey);
+
+struct FieldVisitor {
+    fields: &'static [&'static str],
+}
+
+impl FieldVisitor {
+    fn find(&self, key: &str) -> FieldKey {
+        match self.fields.iter().position(|field| *field == key) {
+            Some(i) => FieldKey::Known(i),
+            None => FieldKey::Unknown,
+        }
+    }
+}
+
+impl Visitor for FieldVisitor {
+    type Value = FieldIndex;
+
+    fn expecting() -> &'static str {
+        "a field name"
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(FieldIndex(self.find(&v)))
+    }
+}
+
+#[async_trait]
+impl FromStream for FieldIndex {
+    type Context = &'static [&'static str];
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder
+            .decode_string(FieldVisitor { fields: context })
+            .await
+    }
+}
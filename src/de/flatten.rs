@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+
+use super::{
+    ArrayAccess, Decoder, Error, FromStream, MapAccess, Replay, SeqAccess, TextAccess, Trace,
+    Visitor,
+};
+
+/// The entries of a map which a visitor did not recognize, buffered so that they can be decoded
+/// afterward, e.g. as the fields of a nested struct which is flattened into its parent map.
+///
+/// Example:
+/// ```
+/// # use destream::de::{self, Decoder, FromStream, MapAccess, Unmatched, Visitor};
+/// # use std::collections::HashMap;
+/// /// A user with any number of extra attributes, encoded in the same map as its name.
+/// struct User {
+///     name: String,
+///     extra: HashMap<String, String>,
+/// }
+///
+/// struct UserVisitor;
+///
+/// #[async_trait::async_trait]
+/// impl Visitor for UserVisitor {
+///     type Value = User;
+///
+///     fn expecting() -> &'static str {
+///         "a user"
+///     }
+///
+///     async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<User, A::Error> {
+///         let mut name = None;
+///         let mut unmatched = Unmatched::new();
+///
+///         while let Some(key) = map.next_key::<String>(()).await? {
+///             match key.as_str() {
+///                 "name" => name = Some(map.next_value(()).await?),
+///                 _ => unmatched.push(key, &mut map).await?,
+///             }
+///         }
+///
+///         let name = name.ok_or_else(|| de::Error::missing_field("name"))?;
+///         let extra = unmatched.decode::<_, A::Error>(()).await?;
+///         Ok(User { name, extra })
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Unmatched {
+    entries: Vec<(Trace, Trace)>,
+}
+
+impl Unmatched {
+    /// Construct a new, empty buffer of [`Unmatched`] map entries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of entries buffered.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return `true` if no entries have been buffered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Buffer the value of the current entry in the given `map`, whose `key` was just decoded.
+    pub async fn push<K, A>(&mut self, key: K, map: &mut A) -> Result<(), A::Error>
+    where
+        K: Into<String>,
+        A: MapAccess,
+    {
+        let Captured(value) = map.next_value(()).await?;
+        self.entries.push((Trace::String(key.into()), value));
+        Ok(())
+    }
+
+    /// Decode the buffered entries as a map, e.g. a flattened struct or a map of extra fields.
+    pub async fn decode<T: FromStream, E: Error>(self, context: T::Context) -> Result<T, E> {
+        let mut decoder = Replay::new(Trace::Map(self.entries));
+        T::from_stream(context, &mut decoder).await
+    }
+}
+
+/// Any value, captured as a [`Trace`] so that it can be replayed later.
+pub(super) struct Captured(pub(super) Trace);
+
+#[async_trait]
+impl FromStream for Captured {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_any(CaptureVisitor).await.map(Self)
+    }
+}
+
+struct CaptureVisitor;
+
+macro_rules! capture_visitor {
+    (
+        $($visit:ident($ty:ty) => $variant:ident),+;
+        $($visit_array:ident($elem:ty) => $array_variant:ident),+
+    ) => {
+        #[async_trait]
+        impl Visitor for CaptureVisitor {
+            type Value = Trace;
+
+            fn expecting() -> &'static str {
+                "any value"
+            }
+
+            $(
+                fn $visit<E: Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    Ok(Trace::$variant(v))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    mut array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let values = array
+                        .fold(Vec::new(), |mut values, value| {
+                            values.push(value);
+                            values
+                        })
+                        .await?;
+
+                    Ok(Trace::$array_variant(values))
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Trace::String(v))
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                text: A,
+            ) -> Result<Self::Value, A::Error> {
+                super::collect_text(text).await.map(Trace::String)
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(Trace::Unit)
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(Trace::None)
+            }
+
+            async fn visit_some<D: Decoder>(
+                self,
+                decoder: &mut D,
+            ) -> Result<Self::Value, D::Error> {
+                let Captured(value) = Captured::from_stream((), decoder).await?;
+                Ok(Trace::Some(Box::new(value)))
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+
+                while let Some(Captured(key)) = map.next_key(()).await? {
+                    let Captured(value) = map.next_value(()).await?;
+                    entries.push((key, value));
+                }
+
+                Ok(Trace::Map(entries))
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut elements = Vec::new();
+
+                while let Some(Captured(element)) = seq.next_element(()).await? {
+                    elements.push(element);
+                }
+
+                Ok(Trace::Seq(elements))
+            }
+        }
+    };
+}
+
+visit_methods!(capture_visitor);
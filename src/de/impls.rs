@@ -1,17 +1,27 @@
+use std::cmp::{Ordering, Reverse};
 use std::collections::*;
 use std::convert::TryInto;
 use std::hash::{BuildHasher, Hash};
 use std::marker::PhantomData;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::future::TryFutureExt;
+#[cfg(feature = "once_cell")]
+use once_cell::sync::OnceCell;
 use uuid::Uuid;
 
+use crate::types::Timestamp;
 use crate::IgnoredAny;
 
+use super::primitive;
 use super::size_hint;
-use super::{ArrayAccess, Decoder, Error, FromStream, MapAccess, SeqAccess, Visitor};
+use super::{
+    ArrayAccess, ArrayBuilder, Decoder, Error, FromStream, MapAccess, SeqAccess, TextAccess,
+    Visitor,
+};
 
 macro_rules! autodecode {
     ($ty:ident, $visit_method:ident, $decode_method:ident) => {
@@ -29,7 +39,7 @@ macro_rules! autodecode {
                     type Value = $ty;
 
                     fn expecting() -> &'static str {
-                        stringify!($ty)
+                        concat!("a value of type ", stringify!($ty))
                     }
 
                     #[inline]
@@ -89,7 +99,7 @@ impl<T: FromStream> Visitor for OptionVisitor<T> {
     type Value = Option<T>;
 
     fn expecting() -> &'static str {
-        stringify!("optional {}", std::any::type_name::<T>())
+        "an optional value, or none"
     }
 
     #[inline]
@@ -130,17 +140,43 @@ impl<T: FromStream> FromStream for Option<T> {
     }
 }
 
+macro_rules! decode_cell {
+    ($($(#[$attr:meta])* $ty:ident),+) => {
+        $(
+            $(#[$attr])*
+            #[async_trait]
+            impl<T: FromStream> FromStream for $ty<T> {
+                type Context = T::Context;
+
+                async fn from_stream<D: Decoder>(
+                    context: Self::Context,
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    let value = Option::<T>::from_stream(context, decoder).await?;
+                    Ok(value.map($ty::from).unwrap_or_default())
+                }
+            }
+        )+
+    };
+}
+
+decode_cell!(
+    OnceLock,
+    #[cfg(feature = "once_cell")]
+    OnceCell
+);
+
 ////////////////////////////////////////////////////////////////////////////////
 
 struct PhantomDataVisitor<T: ?Sized> {
-    marker: PhantomData<T>,
+    marker: PhantomData<fn() -> PhantomData<T>>,
 }
 
-impl<T: Send + ?Sized> Visitor for PhantomDataVisitor<T> {
+impl<T: ?Sized> Visitor for PhantomDataVisitor<T> {
     type Value = PhantomData<T>;
 
     fn expecting() -> &'static str {
-        "unit"
+        "a unit value (), for PhantomData"
     }
 
     #[inline]
@@ -149,6 +185,8 @@ impl<T: Send + ?Sized> Visitor for PhantomDataVisitor<T> {
     }
 }
 
+// `FromStream` requires `Send`, and `PhantomData<T>` is only `Send` if `T` is.
+// To decode a type with a marker parameter which is not `Send`, use `PhantomData<fn() -> T>`.
 #[async_trait]
 impl<T: Send + ?Sized> FromStream for PhantomData<T> {
     type Context = ();
@@ -164,6 +202,109 @@ impl<T: Send + ?Sized> FromStream for PhantomData<T> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+#[async_trait]
+impl<T: FromStream> FromStream for Reverse<T> {
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        T::from_stream(context, decoder).map_ok(Reverse).await
+    }
+}
+
+struct OrderingVisitor;
+
+impl Visitor for OrderingVisitor {
+    type Value = Ordering;
+
+    fn expecting() -> &'static str {
+        "an Ordering (-1, 0, 1, \"Less\", \"Equal\", or \"Greater\")"
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        match v {
+            -1 => Ok(Ordering::Less),
+            0 => Ok(Ordering::Equal),
+            1 => Ok(Ordering::Greater),
+            _ => Err(Error::invalid_value(v, Self::expecting())),
+        }
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        match v {
+            0 => Ok(Ordering::Equal),
+            1 => Ok(Ordering::Greater),
+            _ => Err(Error::invalid_value(v, Self::expecting())),
+        }
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        match v.as_str() {
+            "Less" => Ok(Ordering::Less),
+            "Equal" => Ok(Ordering::Equal),
+            "Greater" => Ok(Ordering::Greater),
+            _ => Err(Error::invalid_value(v, Self::expecting())),
+        }
+    }
+}
+
+#[async_trait]
+impl FromStream for Ordering {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_i8(OrderingVisitor).await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Implement `Visitor` for the `SeqVisitor` of a collection decoded by `decode_seq!`, with the
+// `visit_array_*` methods generated by `visit_methods!`.
+macro_rules! seq_visitor {
+    (
+        [$($generics:tt)*] $visitor:ty => $value:ty, where [$($bounds:tt)*];
+        $expecting:expr, $access:ident, $with_capacity:expr, $insert:expr;
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array:ident),+
+    ) => {
+        #[async_trait]
+        impl $($generics)* Visitor for $visitor
+        where
+            $($bounds)*
+        {
+            type Value = $value;
+
+            fn expecting() -> &'static str {
+                $expecting
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut $access: A) -> Result<Self::Value, A::Error> {
+                let mut values = $with_capacity;
+
+                while let Some(value) = $access.next_element(self.context).await? {
+                    $insert(&mut values, value);
+                }
+
+                Ok(values)
+            }
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(self, array: A) -> Result<Self::Value, A::Error> {
+                    let mut values = Self::Value::default();
+                    primitive::decode_elements(self.context, array, |value| {
+                        $insert(&mut values, value);
+                    }).await?;
+
+                    Ok(values)
+                }
+            )+
+        }
+    };
+}
+
 macro_rules! decode_seq {
     (
         $ty:ident < T $(: $tbound1:ident $(+ $tbound2:ident)*)* $(, $typaram:ident : $bound1:ident $(+ $bound2:ident)*)* >,
@@ -188,29 +329,15 @@ macro_rules! decode_seq {
                     marker: PhantomData<$ty<T $(, $typaram)*>>,
                 }
 
-                #[async_trait]
-                impl<T $(, $typaram)*> Visitor for SeqVisitor<T::Context, T $(, $typaram)*>
-                where
-                    T: FromStream $(+ $tbound1 $(+ $tbound2)*)*,
-                    $($typaram: $bound1 $(+ $bound2)*,)*
-                    T::Context: Copy
-                {
-                    type Value = $ty<T $(, $typaram)*>;
-
-                    fn expecting() -> &'static str {
-                        "a sequence"
-                    }
-
-                    async fn visit_seq<A: SeqAccess>(self, mut $access: A) -> Result<Self::Value, A::Error> {
-                        let mut values = $with_capacity;
-
-                        while let Some(value) = $access.next_element(self.context).await? {
-                            $insert(&mut values, value);
-                        }
-
-                        Ok(values)
-                    }
-                }
+                $crate::de::visit_methods!(seq_visitor {
+                    [<T $(, $typaram)*>] SeqVisitor<T::Context, T $(, $typaram)*> => $ty<T $(, $typaram)*>,
+                    where [
+                        T: FromStream $(+ $tbound1 $(+ $tbound2)*)*,
+                        $($typaram: $bound1 $(+ $bound2)*,)*
+                        T::Context: Copy
+                    ];
+                    concat!("a sequence, to decode as a ", stringify!($ty)), $access, $with_capacity, $insert;
+                });
 
                 let visitor = SeqVisitor { context, marker: PhantomData };
                 decoder.decode_seq(visitor).await
@@ -300,15 +427,12 @@ where
             type Value = smallvec::SmallVec<[T; N]>;
 
             fn expecting() -> &'static str {
-                "a stack-allocated sequence"
+                "a sequence, to decode as a SmallVec"
             }
 
             async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
-                let mut items = if let Some(size_hint) = seq.size_hint() {
-                    smallvec::SmallVec::with_capacity(size_hint)
-                } else {
-                    smallvec::SmallVec::new()
-                };
+                let hint = seq.size_hint();
+                let mut items = smallvec::SmallVec::with_capacity(size_hint::cautious(hint));
 
                 while let Some(item) = seq.next_element(self.context).await? {
                     items.push(item);
@@ -375,7 +499,7 @@ impl<T: FromStream> FromStream for [T; 0] {
 }
 
 macro_rules! decode_array {
-    ($($len:expr => ($($n:tt)+))+) => {
+    ($($len:literal)+) => {
         $(
             #[async_trait]
             impl<T: FromStream> Visitor for ArrayVisitor<T::Context, [T; $len]>
@@ -391,12 +515,16 @@ macro_rules! decode_array {
                     self,
                     mut seq: A
                 ) -> Result<Self::Value, A::Error> {
-                    Ok([$(
+                    let mut array = ArrayBuilder::<T, $len>::new();
+
+                    while !array.is_full() {
                         match seq.next_element(self.context).await? {
-                            Some(val) => val,
-                            None => return Err(Error::invalid_length($n, Self::expecting())),
+                            Some(element) => array.push(element)?,
+                            None => break,
                         }
-                    ),+])
+                    }
+
+                    array.finish()
                 }
             }
 
@@ -418,38 +546,7 @@ macro_rules! decode_array {
 }
 
 decode_array! {
-    1 => (0)
-    2 => (0 1)
-    3 => (0 1 2)
-    4 => (0 1 2 3)
-    5 => (0 1 2 3 4)
-    6 => (0 1 2 3 4 5)
-    7 => (0 1 2 3 4 5 6)
-    8 => (0 1 2 3 4 5 6 7)
-    9 => (0 1 2 3 4 5 6 7 8)
-    10 => (0 1 2 3 4 5 6 7 8 9)
-    11 => (0 1 2 3 4 5 6 7 8 9 10)
-    12 => (0 1 2 3 4 5 6 7 8 9 10 11)
-    13 => (0 1 2 3 4 5 6 7 8 9 10 11 12)
-    14 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13)
-    15 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14)
-    16 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
-    17 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16)
-    18 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17)
-    19 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18)
-    20 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19)
-    21 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20)
-    22 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21)
-    23 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22)
-    24 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23)
-    25 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24)
-    26 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
-    27 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26)
-    28 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27)
-    29 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28)
-    30 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29)
-    31 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30)
-    32 => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31)
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -548,7 +645,7 @@ macro_rules! decode_map {
                     type Value = $ty<K, V $(, $typaram)*>;
 
                     fn expecting() -> &'static str {
-                        "a map"
+                        concat!("a map, to decode as a ", stringify!($ty))
                     }
 
                     async fn visit_map<A: MapAccess>(
@@ -619,7 +716,7 @@ impl Visitor for BytesVisitor {
     type Value = Bytes;
 
     fn expecting() -> &'static str {
-        "bytes"
+        "a byte string"
     }
 
     async fn visit_array_u8<A: ArrayAccess<u8>>(
@@ -727,6 +824,110 @@ impl FromStream for Uuid {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+struct DurationVisitor;
+
+#[async_trait]
+impl Visitor for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting() -> &'static str {
+        "a Duration of the form (seconds, nanoseconds)"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let secs = seq.expect_next::<u64>(()).await?;
+        let nanos = seq.expect_next::<u32>(()).await?;
+
+        if nanos < NANOS_PER_SEC {
+            Ok(Duration::new(secs, nanos))
+        } else {
+            Err(Error::invalid_value(nanos, "fewer than 10^9 nanoseconds"))
+        }
+    }
+}
+
+#[async_trait]
+impl FromStream for Duration {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_tuple(2, DurationVisitor).await
+    }
+}
+
+struct SystemTimeVisitor;
+
+#[async_trait]
+impl Visitor for SystemTimeVisitor {
+    type Value = SystemTime;
+
+    fn expecting() -> &'static str {
+        "a SystemTime of the form (seconds since the Unix epoch, nanoseconds)"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let UnixSecs(secs) = seq.expect_next(()).await?;
+        let nanos = seq.expect_next::<u32>(()).await?;
+
+        if nanos >= NANOS_PER_SEC {
+            return Err(Error::invalid_value(nanos, "fewer than 10^9 nanoseconds"));
+        }
+
+        SystemTime::from_unix(secs, nanos).ok_or_else(|| {
+            Error::invalid_value(
+                format_args!("{secs}.{nanos:09} seconds since the Unix epoch"),
+                "a representable SystemTime",
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl FromStream for SystemTime {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_tuple(2, SystemTimeVisitor).await
+    }
+}
+
+/// A number of seconds since the Unix epoch, which is negative before the epoch.
+struct UnixSecs(i64);
+
+struct UnixSecsVisitor;
+
+impl Visitor for UnixSecsVisitor {
+    type Value = UnixSecs;
+
+    fn expecting() -> &'static str {
+        "a number of seconds since the Unix epoch"
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(UnixSecs(v))
+    }
+
+    // a time after the epoch may have been encoded as a `Duration`
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v)
+            .map(UnixSecs)
+            .map_err(|_| Error::invalid_value(v, "a representable SystemTime"))
+    }
+}
+
+#[async_trait]
+impl FromStream for UnixSecs {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_i64(UnixSecsVisitor).await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 #[async_trait]
 impl Visitor for IgnoredAny {
     type Value = IgnoredAny;
@@ -806,6 +1007,62 @@ impl Visitor for IgnoredAny {
         Ok(IgnoredAny)
     }
 
+    async fn visit_array_bool<A: ArrayAccess<bool>>(
+        self,
+        array: A,
+    ) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_i8<A: ArrayAccess<i8>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_i16<A: ArrayAccess<i16>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_i32<A: ArrayAccess<i32>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_i64<A: ArrayAccess<i64>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_u8<A: ArrayAccess<u8>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_u16<A: ArrayAccess<u16>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_u32<A: ArrayAccess<u32>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_u64<A: ArrayAccess<u64>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_f32<A: ArrayAccess<f32>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_array_f64<A: ArrayAccess<f64>>(self, array: A) -> Result<Self::Value, A::Error> {
+        ignore_array(array).await
+    }
+
+    async fn visit_text_stream<A: TextAccess>(self, mut text: A) -> Result<Self::Value, A::Error> {
+        let mut buffer = [0u8; IGNORE_BUF_SIZE];
+        while text.buffer(&mut buffer).await? > 0 {
+            // Gobble
+        }
+
+        Ok(IgnoredAny)
+    }
+
     #[inline]
     async fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
@@ -847,11 +1104,26 @@ impl Visitor for IgnoredAny {
     // }
 }
 
+const IGNORE_BUF_SIZE: usize = 256;
+
+async fn ignore_array<T, A>(mut array: A) -> Result<IgnoredAny, A::Error>
+where
+    T: Copy + Default + Send,
+    A: ArrayAccess<T>,
+{
+    let mut buffer = [T::default(); IGNORE_BUF_SIZE];
+    while array.buffer(&mut buffer).await? > 0 {
+        // Gobble
+    }
+
+    Ok(IgnoredAny)
+}
+
 #[async_trait]
 impl FromStream for IgnoredAny {
     type Context = ();
 
     async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
-        decoder.decode_ignored_any(IgnoredAny).await
+        decoder.skip_value().await.map(|()| IgnoredAny)
     }
 }
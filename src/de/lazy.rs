@@ -0,0 +1,53 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream};
+
+/// A value of type `T` whose decoding context is created on demand.
+///
+/// The context of a `Lazy<T, F>` is a factory `F` which is only called if a `T` is actually
+/// decoded, e.g. if a map key is present or a sequence has a next element. This is useful when
+/// creating the context of `T` is expensive, like opening a temporary file or a transaction,
+/// e.g. `seq.next_element::<Lazy<Blob, _>>(|| open_temp_file())`.
+pub struct Lazy<T, F> {
+    value: T,
+    factory: PhantomData<fn() -> F>,
+}
+
+impl<T, F> Lazy<T, F> {
+    /// Return the decoded value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, F> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[async_trait]
+impl<T, F, Fut> FromStream for Lazy<T, F>
+where
+    T: FromStream,
+    F: FnOnce() -> Fut + Send,
+    Fut: Future<Output = T::Context> + Send,
+{
+    type Context = F;
+
+    async fn from_stream<D: Decoder>(factory: F, decoder: &mut D) -> Result<Self, D::Error> {
+        let context = factory().await;
+        let value = T::from_stream(context, decoder).await?;
+
+        Ok(Self {
+            value,
+            factory: PhantomData,
+        })
+    }
+}
@@ -3,7 +3,7 @@
 //! The two most important traits in this module are [`FromStream`] and [`Decoder`].
 //!
 //!  - **A type that implements [`FromStream`] is a data structure** that can be decoded from any
-//!  stream encoding supported by `destream`, and conversely
+//!    stream encoding supported by `destream`, and conversely
 //!  - **A type that implements `Decoder` is a data format** that can decode any supported stream.
 //!
 //! # The FromStream trait
@@ -25,7 +25,12 @@
 //!  - **Common standard library types**:
 //!    - String
 //!    - Option\<T\>
+//!    - OnceLock\<T\>
 //!    - PhantomData\<T\>
+//!    - Duration
+//!    - SystemTime
+//!    - Ordering
+//!    - Reverse\<T\>
 //!  - **Other common types**:
 //!    - Bytes
 //!    - Uuid
@@ -39,15 +44,316 @@
 //!    - VecDeque\<T\>
 //!    - Vec\<T\>
 //!
-//! Enable support for `SmallVec` using the `smallvec` feature flag.
+//! Enable support for `SmallVec` using the `smallvec` feature flag, and for
+//! `once_cell::sync::OnceCell` using the `once_cell` feature flag.
+//!
+//! The `unicode-normalization` feature flag enables [`Normalization::nfc`], to convert every
+//! string decoded by a [`Normalize`] decoder to Unicode Normalization Form C.
+//!
+//! On nightly Rust, the `allocator_api` feature flag implements [`FromStreamWith<A>`] for
+//! `Vec<T, A>` and `Box<T, A>`, to decode a collection in the allocator `A` given as its context,
+//! such as a per-request arena.
 
 use std::fmt;
 
 use async_trait::async_trait;
 
-mod impls;
+/// Invoke the given macro with any leading `args`, followed by the name of every method of
+/// [`Decoder`] which takes only a [`Visitor`], other than `decode_ignored_any`.
+macro_rules! decode_methods {
+    ($callback:ident $({ $($args:tt)* })?) => {
+        $callback! {
+            $($($args)*)?
+            decode_any,
+            decode_bool,
+            decode_bytes,
+            decode_i8,
+            decode_i16,
+            decode_i32,
+            decode_i64,
+            decode_u8,
+            decode_u16,
+            decode_u32,
+            decode_u64,
+            decode_f32,
+            decode_f64,
+            decode_array_bool,
+            decode_array_i8,
+            decode_array_i16,
+            decode_array_i32,
+            decode_array_i64,
+            decode_array_u8,
+            decode_array_u16,
+            decode_array_u32,
+            decode_array_u64,
+            decode_array_f32,
+            decode_array_f64,
+            decode_map,
+            decode_option,
+            decode_seq,
+            decode_string,
+            decode_unit,
+            decode_uuid
+        }
+    };
+}
+
+pub(crate) use decode_methods;
+
+/// Invoke the given macro with any leading `args`, followed by every method of [`Visitor`]
+/// which visits a primitive value, then every method which visits a typed array, each with the
+/// type it visits and the name of its kind (as in [`Trace`]).
+macro_rules! visit_methods {
+    ($callback:ident $({ $($args:tt)* })?) => {
+        $callback! {
+            $($($args)*)?
+            visit_bool(bool) => Bool,
+            visit_i8(i8) => I8,
+            visit_i16(i16) => I16,
+            visit_i32(i32) => I32,
+            visit_i64(i64) => I64,
+            visit_u8(u8) => U8,
+            visit_u16(u16) => U16,
+            visit_u32(u32) => U32,
+            visit_u64(u64) => U64,
+            visit_f32(f32) => F32,
+            visit_f64(f64) => F64;
+            visit_array_bool(bool) => ArrayBool,
+            visit_array_i8(i8) => ArrayI8,
+            visit_array_i16(i16) => ArrayI16,
+            visit_array_i32(i32) => ArrayI32,
+            visit_array_i64(i64) => ArrayI64,
+            visit_array_u8(u8) => ArrayU8,
+            visit_array_u16(u16) => ArrayU16,
+            visit_array_u32(u32) => ArrayU32,
+            visit_array_u64(u64) => ArrayU64,
+            visit_array_f32(f32) => ArrayF32,
+            visit_array_f64(f64) => ArrayF64
+        }
+    };
+}
+
+pub(crate) use visit_methods;
+
+/// Implement [`Decoder`] for a wrapper by forwarding every method to an inner decoder.
+///
+/// ```text
+/// forward_decoder!(
+///     [<'a, D: Decoder>] Wrapper<'a, D>, D::Error;
+///     |this, visitor, len| this.decoder => WrapperVisitor::new(visitor, len);
+///     // optional: ignored_any: |this, visitor| ...;
+///     // optional: any other methods, e.g. skip_value
+/// );
+/// ```
+///
+/// `this` is the wrapper, and `len` is the length passed to `decode_tuple`, if any. By default,
+/// `decode_ignored_any` forwards a wrapped visitor like every other method, and `skip_value`
+/// uses its default implementation, so that the wrapper sees every value which is skipped.
+macro_rules! forward_decoder {
+    (
+        @impl [$($generics:tt)*] $decoder:ty, $error:ty;
+        |$this:ident, $visitor:ident, $len:ident| $inner:expr => $wrap:expr;
+        { $($extra:tt)* }
+        $($method:ident),+
+    ) => {
+        #[::async_trait::async_trait]
+        impl $($generics)* $crate::de::Decoder for $decoder {
+            type Error = $error;
+
+            $(
+                async fn $method<V: $crate::de::Visitor>(
+                    &mut self,
+                    $visitor: V,
+                ) -> Result<V::Value, Self::Error> {
+                    let $this = self;
+                    let $len: Option<usize> = None;
+                    let wrapped = $wrap;
+                    $inner.$method(wrapped).await
+                }
+            )+
+
+            async fn decode_tuple<V: $crate::de::Visitor>(
+                &mut self,
+                len: usize,
+                $visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                let $this = self;
+                let $len: Option<usize> = Some(len);
+                let wrapped = $wrap;
+                $inner.decode_tuple(len, wrapped).await
+            }
+
+            $($extra)*
+        }
+    };
+    (
+        [$($generics:tt)*] $decoder:ty, $error:ty;
+        |$this:ident, $visitor:ident, $len:ident| $inner:expr => $wrap:expr;
+        ignored_any: |$ignored_this:ident, $ignored_visitor:ident| $ignored:expr;
+        $($extra:tt)*
+    ) => {
+        $crate::de::decode_methods!(forward_decoder {
+            @impl [$($generics)*] $decoder, $error;
+            |$this, $visitor, $len| $inner => $wrap;
+            {
+                async fn decode_ignored_any<V: $crate::de::Visitor>(
+                    &mut self,
+                    $ignored_visitor: V,
+                ) -> Result<V::Value, Self::Error> {
+                    let $ignored_this = self;
+                    $ignored
+                }
+
+                $($extra)*
+            }
+        });
+    };
+    (
+        [$($generics:tt)*] $decoder:ty, $error:ty;
+        |$this:ident, $visitor:ident, $len:ident| $inner:expr => $wrap:expr;
+        $($extra:tt)*
+    ) => {
+        $crate::de::forward_decoder!(
+            [$($generics)*] $decoder, $error;
+            |$this, $visitor, $len| $inner => $wrap;
+            ignored_any: |$this, $visitor| {
+                let $len: Option<usize> = None;
+                let wrapped = $wrap;
+                $inner.decode_ignored_any(wrapped).await
+            };
+            $($extra)*
+        );
+    };
+}
+
+pub(crate) use forward_decoder;
+
+/// Implement [`Visitor`] for a wrapper by forwarding every primitive value and typed array to an
+/// inner visitor.
+///
+/// ```text
+/// forward_visitor!(
+///     [<V: Visitor>] WrapperVisitor<V> => V;
+///     |this| this.visitor;
+///     |array| WrapperArray { array };
+///     // the remaining methods: visit_string, visit_text_stream, visit_unit, visit_none,
+///     // visit_some, visit_map, and visit_seq
+/// );
+/// ```
+///
+/// `this` is the wrapper, and the second expression wraps the [`ArrayAccess`] of a typed array.
+macro_rules! forward_visitor {
+    (
+        @impl [$($generics:tt)*] $visitor:ty => $inner:ty;
+        |$this:ident| $target:expr;
+        |$array:ident| $wrap_array:expr;
+        { $($extra:tt)* }
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array_kind:ident),+
+    ) => {
+        #[::async_trait::async_trait]
+        impl $($generics)* $crate::de::Visitor for $visitor {
+            type Value = <$inner as $crate::de::Visitor>::Value;
+
+            fn expecting() -> &'static str {
+                <$inner as $crate::de::Visitor>::expecting()
+            }
+
+            $(
+                fn $visit<E: $crate::de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    let $this = self;
+                    $target.$visit(v)
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: $crate::de::ArrayAccess<$elem>>(
+                    self,
+                    $array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let $this = self;
+                    $target.$visit_array($wrap_array).await
+                }
+            )+
+
+            $($extra)*
+        }
+    };
+    (
+        [$($generics:tt)*] $visitor:ty => $inner:ty;
+        |$this:ident| $target:expr;
+        |$array:ident| $wrap_array:expr;
+        $($extra:tt)*
+    ) => {
+        $crate::de::visit_methods!(forward_visitor {
+            @impl [$($generics)*] $visitor => $inner;
+            |$this| $target;
+            |$array| $wrap_array;
+            { $($extra)* }
+        });
+    };
+}
 
-mod size_hint {
+#[cfg(feature = "allocator_api")]
+mod allocator;
+mod array_builder;
+mod borrow;
+mod budget;
+mod cast;
+mod checked;
+mod convert;
+mod entry;
+mod field;
+mod flatten;
+mod impls;
+mod lazy;
+mod multi;
+mod normalize;
+mod pair;
+mod partial;
+mod primitive;
+mod record;
+mod registry;
+mod remainder;
+mod rename;
+mod skipped;
+mod stream;
+mod strict;
+mod update;
+mod versioned;
+pub mod visitors;
+mod with;
+mod zip;
+
+pub use array_builder::ArrayBuilder;
+pub use borrow::{BorrowDecoder, FromStreamRef};
+pub use budget::SkipBudget;
+pub use cast::CastFrom;
+pub use checked::DebugChecked;
+pub use convert::{convert_error, WithError};
+pub use entry::{decode_from, try_decode, DecodeError};
+pub use field::{FieldKey, KeyHint};
+pub use flatten::Unmatched;
+pub use lazy::Lazy;
+pub use multi::MultiValueDecoder;
+pub use normalize::{Normalization, Normalize};
+pub use partial::decode_with_errors;
+pub use record::{Recording, Replay, Trace};
+pub use registry::{Polymorphic, Registry};
+pub use remainder::IntoRemainder;
+pub use rename::{Rename, Renaming};
+pub use skipped::{Skipped, SkippedKind};
+pub use stream::{Chunks, DecodedStreamExt, MapErrPath, TakeBytes};
+pub use strict::Strict;
+pub use update::UpdateFromStream;
+pub use versioned::{DecodeVersion, Versioned};
+pub use with::FromStreamWith;
+pub use zip::zip_seqs;
+
+pub(crate) use pair::{Pair, PairVisitor};
+pub(crate) use stream::Driven;
+
+pub(crate) mod size_hint {
     use std::cmp;
 
     #[inline]
@@ -84,6 +390,38 @@ pub trait Error: Send + Sized + std::error::Error {
     fn invalid_length<E: fmt::Display>(len: usize, exp: E) -> Self {
         Error::custom(format_args!("invalid length: {}, expected {}", len, exp))
     }
+
+    /// Raised when decoding a map with a required field and the input data does not contain it.
+    fn missing_field(field: &'static str) -> Self {
+        Error::custom(format_args!("missing field `{}`", field))
+    }
+
+    /// Annotate this error with some context, e.g. the name of the type being decoded.
+    ///
+    /// This formats the `context` and passes it to [`Error::with_context`].
+    fn context<C: fmt::Display>(self, context: C) -> Self {
+        self.with_context(context.to_string())
+    }
+
+    /// Wrap this error in the given `context`.
+    ///
+    /// The default implementation flattens this error to a string: it returns a new error with
+    /// a message of the form `"{context}: {self}"`, so the type and `source` of the original
+    /// error are lost. Implementations which can hold a chain of causes should override this to
+    /// keep the original error, e.g. as the `source` of the returned error.
+    fn with_context(self, context: String) -> Self {
+        Error::custom(format_args!("{}: {}", context, self))
+    }
+
+    /// Annotate this error with the map key whose value failed to decode.
+    fn with_key<K: fmt::Display>(self, key: K) -> Self {
+        self.context(format_args!("at key {}", key))
+    }
+
+    /// Annotate this error with the index of the sequence element which failed to decode.
+    fn with_index(self, index: usize) -> Self {
+        self.context(format_args!("at index {}", index))
+    }
 }
 
 /// A data format that can decode a given well-formatted stream using one or more [`Visitor`]s.
@@ -209,6 +547,35 @@ pub trait Decoder: Send {
     /// Decoders for non-self-describing formats may not support this mode.
     async fn decode_ignored_any<V: Visitor>(&mut self, visitor: V)
         -> Result<V::Value, Self::Error>;
+
+    /// Skip the next value in the stream without decoding it.
+    ///
+    /// [`IgnoredAny`] (and therefore every unknown map value or trailing element skipped by
+    /// a built-in visitor) is decoded with this method. The default implementation visits the
+    /// value with [`decode_ignored_any`]; formats which can skip over the encoded tokens of a
+    /// value without parsing them should override it.
+    ///
+    /// [`decode_ignored_any`]: #tymethod.decode_ignored_any
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.decode_ignored_any(IgnoredAny)
+            .await
+            .map(|IgnoredAny| ())
+    }
+
+    /// Skip the next value in the stream without decoding it, within the given `budget`.
+    ///
+    /// Unlike [`skip_value`], this visits the value, so that it can fail as soon as the value
+    /// exceeds the budget. See [`SkipBudget`].
+    ///
+    /// [`skip_value`]: #method.skip_value
+    async fn skip_value_within(&mut self, budget: &mut SkipBudget) -> Result<(), Self::Error>
+    where
+        Self: Sized,
+    {
+        IgnoredAny::from_stream_with(budget, self)
+            .await
+            .map(|IgnoredAny| ())
+    }
 }
 
 /// This trait describes a value which can be decoded from a stream.
@@ -229,6 +596,8 @@ pub trait FromStream: Send + Sized {
     ) -> Result<Self, D::Error>;
 }
 
+const CAST_BUF_SIZE: usize = 256;
+
 /// Provides a [`Visitor`] with access to an array of type `T`.
 ///
 /// This is a trait that a [`Decoder`] passes to a `Visitor` implementation.
@@ -240,6 +609,130 @@ pub trait ArrayAccess<T>: Send {
     ///
     /// Returns the number of values written (this will be in the range `0..buffer.len()`).
     async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, Self::Error>;
+
+    /// Write array values from the stream being decoded into the given `buffer`, converting
+    /// each one with [`From`], e.g. to widen `u8` samples into `u16`s.
+    ///
+    /// Returns the number of values written, like [`buffer`]. The default implementation reads
+    /// at most 256 values at a time into an intermediate buffer on the stack.
+    ///
+    /// [`buffer`]: #tymethod.buffer
+    async fn buffer_cast<U>(&mut self, buffer: &mut [U]) -> Result<usize, Self::Error>
+    where
+        T: Copy + Default + Send,
+        U: From<T> + Send,
+    {
+        let mut source = [T::default(); CAST_BUF_SIZE];
+        let len = Ord::min(buffer.len(), CAST_BUF_SIZE);
+        let len = self.buffer(&mut source[..len]).await?;
+
+        for (value, target) in source[..len].iter().zip(buffer.iter_mut()) {
+            *target = U::from(*value);
+        }
+
+        Ok(len)
+    }
+
+    /// Write array values from the stream being decoded into the given `buffer`, converting
+    /// each one with [`TryFrom`], and return an error if any value is out of range.
+    ///
+    /// See [`buffer_cast`](#method.buffer_cast).
+    async fn buffer_try_cast<U>(&mut self, buffer: &mut [U]) -> Result<usize, Self::Error>
+    where
+        T: Copy + Default + Send + fmt::Display,
+        U: TryFrom<T> + Send,
+    {
+        let mut source = [T::default(); CAST_BUF_SIZE];
+        let len = Ord::min(buffer.len(), CAST_BUF_SIZE);
+        let len = self.buffer(&mut source[..len]).await?;
+
+        for (value, target) in source[..len].iter().zip(buffer.iter_mut()) {
+            *target = U::try_from(*value)
+                .map_err(|_| Error::invalid_value(value, std::any::type_name::<U>()))?;
+        }
+
+        Ok(len)
+    }
+
+    /// Write array values from the stream being decoded into the given `buffer`, converting
+    /// each one as if with an `as` cast, e.g. to convert `i32` samples into `f32`s.
+    ///
+    /// See [`buffer_cast`](#method.buffer_cast).
+    async fn buffer_lossy<U>(&mut self, buffer: &mut [U]) -> Result<usize, Self::Error>
+    where
+        T: Copy + Default + Send,
+        U: CastFrom<T> + Send,
+    {
+        let mut source = [T::default(); CAST_BUF_SIZE];
+        let len = Ord::min(buffer.len(), CAST_BUF_SIZE);
+        let len = self.buffer(&mut source[..len]).await?;
+
+        for (value, target) in source[..len].iter().zip(buffer.iter_mut()) {
+            *target = U::cast_from(*value);
+        }
+
+        Ok(len)
+    }
+
+    /// Consume every remaining value in the array, combining each one with an accumulator
+    /// which starts as `init`, e.g. to compute the sum or maximum of a large numeric array
+    /// without holding it in memory.
+    ///
+    /// The default implementation reads at most 256 values at a time into an intermediate buffer
+    /// on the stack. A format can override it to fold over its own buffer instead.
+    async fn fold<B, F>(&mut self, init: B, mut f: F) -> Result<B, Self::Error>
+    where
+        T: Copy + Default + Send,
+        B: Send,
+        F: FnMut(B, T) -> B + Send,
+    {
+        let mut acc = init;
+        let mut source = [T::default(); CAST_BUF_SIZE];
+
+        loop {
+            let len = self.buffer(&mut source).await?;
+            if len == 0 {
+                break Ok(acc);
+            }
+
+            for value in &source[..len] {
+                acc = f(acc, *value);
+            }
+        }
+    }
+}
+
+/// Provides a [`Visitor`] with access to a string value in chunks, so that a very large string
+/// does not need to be held in memory all at once.
+///
+/// This is a trait that a [`Decoder`] passes to a `Visitor` implementation.
+#[async_trait]
+pub trait TextAccess: Send {
+    type Error: Error;
+
+    /// Write the UTF-8 bytes of the next chunk of the string being decoded into the given `buffer`.
+    ///
+    /// Returns the number of bytes written (this will be in the range `0..buffer.len()`).
+    /// The bytes written are always valid UTF-8 on their own, i.e. a chunk never ends in the middle
+    /// of a multi-byte character. The `buffer` must be at least four bytes long.
+    async fn buffer(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+/// Read the entire contents of the given [`TextAccess`] into a `String`.
+async fn collect_text<A: TextAccess>(mut text: A) -> Result<String, A::Error> {
+    const BUF_SIZE: usize = 4_096;
+
+    let mut string = String::new();
+    let mut buf = [0u8; BUF_SIZE];
+    loop {
+        let len = text.buffer(&mut buf).await?;
+        if len == 0 {
+            break Ok(string);
+        }
+
+        let chunk = std::str::from_utf8(&buf[..len]).map_err(Error::custom)?;
+        string.push_str(chunk);
+    }
 }
 
 /// Provides a [`Visitor`] with access to each entry of a map in the input.
@@ -273,6 +766,64 @@ pub trait MapAccess: Send {
     /// panic or return bogus results.
     async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error>;
 
+    /// This returns `Ok(Some(key))` for the next key in the map, or `Ok(None)` if there are no
+    /// more remaining entries, given a `hint` of the type of key which `K` prefers.
+    ///
+    /// The default implementation ignores the hint and calls `next_key`. A format whose keys may
+    /// be either strings or integers should override it to decode a key as the preferred type
+    /// where possible, rather than decoding it twice or into the wrong type.
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        let _ = hint;
+        self.next_key(context).await
+    }
+
+    /// This returns `Ok(Some(key))` for the next key in the map, matched against the given list
+    /// of `fields`, or `Ok(None)` if there are no more remaining entries.
+    ///
+    /// This is intended for visitors of structs with a known set of field names.
+    /// The default implementation decodes the key as a `String`; formats which can compare
+    /// a key in place should override it to avoid allocating.
+    async fn match_key(
+        &mut self,
+        fields: &'static [&'static str],
+    ) -> Result<Option<FieldKey>, Self::Error> {
+        let key: Option<field::FieldIndex> = self.next_key(fields).await?;
+        Ok(key.map(|field::FieldIndex(key)| key))
+    }
+
+    /// This returns `Ok(value)` for the next value in the map, decoded using a mutable
+    /// reference to a `context` which can be shared between values.
+    ///
+    /// # Panics
+    ///
+    /// Calling `next_value_with` before `next_key` is incorrect and is allowed to
+    /// panic or return bogus results.
+    async fn next_value_with<V, C>(&mut self, context: &mut C) -> Result<V, Self::Error>
+    where
+        V: FromStreamWith<C>,
+        C: Send,
+    {
+        let value: with::With<V, C> = self.next_value(context).await?;
+        Ok(value.value)
+    }
+
+    /// Skip the next value in the map without decoding it.
+    ///
+    /// The default implementation decodes an [`IgnoredAny`], which calls
+    /// [`Decoder::skip_value`].
+    ///
+    /// # Panics
+    ///
+    /// Calling `skip_value` before `next_key` is incorrect and is allowed to
+    /// panic or return bogus results.
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.next_value(()).await.map(|IgnoredAny| ())
+    }
+
     /// Returns the number of entries remaining in the map, if known.
     #[inline]
     fn size_hint(&self) -> Option<usize> {
@@ -314,6 +865,18 @@ pub trait SeqAccess: Send {
         }
     }
 
+    /// Returns `Ok(Some(value))` for the next value in the sequence, decoded using a mutable
+    /// reference to a `context` which can be shared between elements,
+    /// or `Ok(None)` if there is no next item.
+    async fn next_element_with<T, C>(&mut self, context: &mut C) -> Result<Option<T>, Self::Error>
+    where
+        T: FromStreamWith<C>,
+        C: Send,
+    {
+        let element: Option<with::With<T, C>> = self.next_element(context).await?;
+        Ok(element.map(|element| element.value))
+    }
+
     /// Returns the number of elements remaining in the sequence, if known.
     #[inline]
     fn size_hint(&self) -> Option<usize> {
@@ -534,6 +1097,18 @@ pub trait Visitor: Send + Sized {
         Err(Error::invalid_type(v, Self::expecting()))
     }
 
+    /// The input contains a string which is made available in chunks, so that it does not need
+    /// to be held in memory all at once.
+    ///
+    /// A [`Decoder`] may call this method in place of [`visit_string`] for any string value.
+    /// The default implementation collects the chunks and forwards to [`visit_string`].
+    ///
+    /// [`visit_string`]: #method.visit_string
+    async fn visit_text_stream<A: TextAccess>(self, text: A) -> Result<Self::Value, A::Error> {
+        let string = collect_text(text).await?;
+        self.visit_string(string)
+    }
+
     /// The input contains a unit `()`.
     ///
     /// The default implementation fails with a type error.
@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream};
+
+/// A [`Decoder`] over a long-lived input, like an RPC connection, which carries
+/// multiple top-level values one after another.
+///
+/// Implementations must decode each value completely without consuming any input which belongs
+/// to the next value, and must skip any framing the format defines between values
+/// (e.g. whitespace or a delimiter) in [`has_next_value`](Self::has_next_value).
+#[async_trait]
+pub trait MultiValueDecoder: Decoder + Sized {
+    /// Skip any framing before the next value, then return `true` if another value follows
+    /// or `false` if the input has ended.
+    async fn has_next_value(&mut self) -> Result<bool, Self::Error>;
+
+    /// Decode the next top-level value of type `T`,
+    /// or return `Ok(None)` if the input has ended.
+    async fn decode_value<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        if self.has_next_value().await? {
+            T::from_stream(context, self).await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
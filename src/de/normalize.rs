@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use futures::future::TryFutureExt;
+
+use super::{Decoder, Error, FromStream, KeyHint, MapAccess, SeqAccess, TextAccess, Visitor};
+
+/// A normalization to apply to every string decoded, e.g. so that identity comparisons on
+/// user-supplied text are consistent without normalizing it in every [`FromStream`] implementation.
+///
+/// Enable Unicode normalization with the `unicode-normalization` feature flag.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Normalization {
+    trim: bool,
+    #[cfg(feature = "unicode-normalization")]
+    nfc: bool,
+}
+
+impl Normalization {
+    /// Construct a new [`Normalization`] which leaves strings unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trim leading and trailing whitespace from every string.
+    pub fn trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    /// Convert every string to Unicode Normalization Form C.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc(mut self) -> Self {
+        self.nfc = true;
+        self
+    }
+
+    /// Apply this normalization to the given string.
+    pub fn normalize(&self, mut string: String) -> String {
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc {
+            use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+            if is_nfc_quick(string.chars()) != IsNormalized::Yes {
+                string = string.nfc().collect();
+            }
+        }
+
+        if self.trim {
+            let trimmed = string.trim();
+            if trimmed.len() < string.len() {
+                string = trimmed.to_string();
+            }
+        }
+
+        string
+    }
+}
+
+/// A [`Decoder`] which applies a [`Normalization`] to every string, recursively, including map
+/// keys, before it's visited.
+///
+/// A streamed string is collected before it's normalized.
+pub struct Normalize<'a, D> {
+    decoder: &'a mut D,
+    normalization: Normalization,
+}
+
+impl<'a, D> Normalize<'a, D> {
+    /// Wrap the given `decoder` so that it applies the given `normalization`.
+    pub fn new(decoder: &'a mut D, normalization: Normalization) -> Self {
+        Self {
+            decoder,
+            normalization,
+        }
+    }
+
+    fn visitor<V>(&self, visitor: V) -> NormalizeVisitor<V> {
+        NormalizeVisitor {
+            visitor,
+            normalization: self.normalization,
+        }
+    }
+}
+
+forward_decoder!(
+    [<'a, D: Decoder>] Normalize<'a, D>, D::Error;
+    |this, visitor, _len| this.decoder => this.visitor(visitor);
+
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.decoder.skip_value().await
+    }
+);
+
+struct NormalizeVisitor<V> {
+    visitor: V,
+    normalization: Normalization,
+}
+
+forward_visitor!(
+    [<V: Visitor>] NormalizeVisitor<V> => V;
+    |this| this.visitor;
+    |array| array;
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visitor.visit_string(self.normalization.normalize(v))
+    }
+
+    async fn visit_text_stream<A: TextAccess>(self, text: A) -> Result<Self::Value, A::Error> {
+        let string = super::collect_text(text).await?;
+        self.visitor.visit_string(self.normalization.normalize(string))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        let mut decoder = Normalize::new(decoder, self.normalization);
+        self.visitor.visit_some(&mut decoder).await
+    }
+
+    async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+        let map = NormalizeMap {
+            map,
+            normalization: self.normalization,
+        };
+
+        self.visitor.visit_map(map).await
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let seq = NormalizeSeq {
+            seq,
+            normalization: self.normalization,
+        };
+
+        self.visitor.visit_seq(seq).await
+    }
+);
+
+/// A value decoded with a [`Normalization`] applied.
+struct Normalized<T>(T);
+
+#[async_trait]
+impl<T: FromStream> FromStream for Normalized<T> {
+    type Context = (T::Context, Normalization);
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let (context, normalization) = context;
+        let mut decoder = Normalize::new(decoder, normalization);
+        T::from_stream(context, &mut decoder).map_ok(Self).await
+    }
+}
+
+struct NormalizeMap<A> {
+    map: A,
+    normalization: Normalization,
+}
+
+#[async_trait]
+impl<A: MapAccess> MapAccess for NormalizeMap<A> {
+    type Error = A::Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.next_key_as(KeyHint::Any, context).await
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        let context = (context, self.normalization);
+        let key: Option<Normalized<K>> = self.map.next_key_as(hint, context).await?;
+        Ok(key.map(|Normalized(key)| key))
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        let Normalized(value) = self.map.next_value((context, self.normalization)).await?;
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct NormalizeSeq<A> {
+    seq: A,
+    normalization: Normalization,
+}
+
+#[async_trait]
+impl<A: SeqAccess> SeqAccess for NormalizeSeq<A> {
+    type Error = A::Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        let context = (context, self.normalization);
+        let element: Option<Normalized<T>> = self.seq.next_element(context).await?;
+        Ok(element.map(|Normalized(element)| element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
@@ -0,0 +1,85 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::{Error, FromStream, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+/// A value encoded either as a map with exactly two keys, in order, or as a tuple of the form
+/// `[<first>, <second>]`, which is decoded with the first element as part of its context.
+///
+/// The first element is always decoded completely before the second, so the second can be
+/// streamed using the given context, or decoded differently depending on the first.
+pub(crate) trait Pair: FromStream<Context = (Self::First, Self::Outer)> {
+    /// The type of the first element.
+    type First: FromStream<Context = ()>;
+
+    /// The context given to [`PairVisitor`], which is passed through to decode this value.
+    type Outer: Send;
+
+    /// The keys of the first and second elements, when encoded as a map.
+    const KEYS: [&'static str; 2];
+
+    /// A description of this value, for use in error messages.
+    fn expecting() -> &'static str;
+}
+
+/// A [`Visitor`] which decodes a [`Pair`].
+pub(crate) struct PairVisitor<P: Pair> {
+    context: P::Outer,
+    marker: PhantomData<fn() -> P>,
+}
+
+impl<P: Pair> PairVisitor<P> {
+    pub fn new(context: P::Outer) -> Self {
+        Self {
+            context,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Pair> Visitor for PairVisitor<P> {
+    type Value = P;
+
+    fn expecting() -> &'static str {
+        P::expecting()
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let [first_key, second_key] = P::KEYS;
+
+        match map.next_key::<String>(()).await? {
+            Some(key) if key == first_key => {}
+            Some(key) => return Err(Error::invalid_value(key, first_key)),
+            None => return Err(Error::invalid_length(0, Self::expecting())),
+        }
+
+        let first = map.next_value::<P::First>(()).await?;
+
+        match map.next_key::<String>(()).await? {
+            Some(key) if key == second_key => {}
+            Some(key) => return Err(Error::invalid_value(key, second_key)),
+            None => return Err(Error::invalid_length(1, Self::expecting())),
+        }
+
+        let value = map.next_value((first, self.context)).await?;
+
+        if map.next_key::<IgnoredAny>(()).await?.is_some() {
+            return Err(Error::invalid_length(3, Self::expecting()));
+        }
+
+        Ok(value)
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let first = seq.expect_next::<P::First>(()).await?;
+        let value = seq.expect_next((first, self.context)).await?;
+
+        if seq.next_element::<IgnoredAny>(()).await?.is_some() {
+            return Err(Error::invalid_length(3, Self::expecting()));
+        }
+
+        Ok(value)
+    }
+}
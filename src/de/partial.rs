@@ -0,0 +1,61 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::flatten::Captured;
+use super::{Decoder, Error, FromStream, Replay, SeqAccess, Visitor};
+
+/// Decode a sequence of `T`, continuing past any element which fails to decode.
+///
+/// Returns the result of decoding each element, in order, so that bad elements can be
+/// quarantined without failing the whole sequence.
+///
+/// Each element is buffered as a [`Trace`](super::Trace) with [`Decoder::decode_any`] as it's
+/// read, then decoded from the buffer before the next element is read, so the input must be
+/// self-describing. If the input itself is malformed, the whole sequence returns an error.
+pub async fn decode_with_errors<T, D>(
+    context: T::Context,
+    decoder: &mut D,
+) -> Result<Vec<Result<T, D::Error>>, D::Error>
+where
+    T: FromStream,
+    T::Context: Copy,
+    D: Decoder,
+{
+    decoder
+        .decode_seq(PartialVisitor {
+            context,
+            marker: PhantomData,
+        })
+        .await
+}
+
+struct PartialVisitor<T: FromStream, E> {
+    context: T::Context,
+    marker: PhantomData<(T, E)>,
+}
+
+#[async_trait]
+impl<T, E> Visitor for PartialVisitor<T, E>
+where
+    T: FromStream,
+    T::Context: Copy,
+    E: Error,
+{
+    type Value = Vec<Result<T, E>>;
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut elements = Vec::with_capacity(super::size_hint::cautious(seq.size_hint()));
+
+        while let Some(Captured(trace)) = seq.next_element(()).await? {
+            let mut replay = Replay::<E>::new(trace);
+            elements.push(T::from_stream(self.context, &mut replay).await);
+        }
+
+        Ok(elements)
+    }
+}
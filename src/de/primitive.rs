@@ -0,0 +1,145 @@
+//! A [`Decoder`] over a single in-memory primitive value, used to decode the elements of a typed
+//! array into an arbitrary [`FromStream`] type.
+
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::{ArrayAccess, Decoder, Error, FromStream, Visitor};
+
+const BUF_SIZE: usize = 4_096;
+
+/// A primitive value which can be passed directly to a [`Visitor`].
+pub trait Visit: Copy + Default + Send {
+    fn visit<V: Visitor, E: Error>(self, visitor: V) -> Result<V::Value, E>;
+}
+
+macro_rules! visit {
+    ($($ty:ty => $method:ident),+) => {
+        $(
+            impl Visit for $ty {
+                #[inline]
+                fn visit<V: Visitor, E: Error>(self, visitor: V) -> Result<V::Value, E> {
+                    visitor.$method(self)
+                }
+            }
+        )+
+    };
+}
+
+visit!(
+    bool => visit_bool,
+    i8 => visit_i8,
+    i16 => visit_i16,
+    i32 => visit_i32,
+    i64 => visit_i64,
+    u8 => visit_u8,
+    u16 => visit_u16,
+    u32 => visit_u32,
+    u64 => visit_u64,
+    f32 => visit_f32,
+    f64 => visit_f64
+);
+
+/// A [`Decoder`] which visits a single primitive value, regardless of the type hint given.
+pub struct Primitive<P, E> {
+    value: P,
+    error: PhantomData<E>,
+}
+
+impl<P, E> Primitive<P, E> {
+    pub fn new(value: P) -> Self {
+        Self {
+            value,
+            error: PhantomData,
+        }
+    }
+}
+
+macro_rules! decode_primitive {
+    ($($method:ident),+) => {
+        #[async_trait]
+        impl<P: Visit, E: Error> Decoder for Primitive<P, E> {
+            type Error = E;
+
+            $(
+                async fn $method<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, E> {
+                    self.value.visit(visitor)
+                }
+            )+
+
+            async fn decode_option<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, E> {
+                visitor.visit_some(self).await
+            }
+
+            async fn decode_tuple<V: Visitor>(
+                &mut self,
+                _len: usize,
+                visitor: V,
+            ) -> Result<V::Value, E> {
+                self.value.visit(visitor)
+            }
+        }
+    };
+}
+
+decode_primitive!(
+    decode_any,
+    decode_bool,
+    decode_bytes,
+    decode_i8,
+    decode_i16,
+    decode_i32,
+    decode_i64,
+    decode_u8,
+    decode_u16,
+    decode_u32,
+    decode_u64,
+    decode_f32,
+    decode_f64,
+    decode_array_bool,
+    decode_array_i8,
+    decode_array_i16,
+    decode_array_i32,
+    decode_array_i64,
+    decode_array_u8,
+    decode_array_u16,
+    decode_array_u32,
+    decode_array_u64,
+    decode_array_f32,
+    decode_array_f64,
+    decode_map,
+    decode_seq,
+    decode_string,
+    decode_unit,
+    decode_uuid,
+    decode_ignored_any
+);
+
+/// Decode each element of the given typed `array` as a `T`, and pass it to `insert`.
+pub async fn decode_elements<P, T, A, F>(
+    context: T::Context,
+    mut array: A,
+    mut insert: F,
+) -> Result<(), A::Error>
+where
+    P: Visit,
+    T: FromStream,
+    T::Context: Copy,
+    A: ArrayAccess<P>,
+    F: FnMut(T) + Send,
+{
+    let mut buffer = vec![P::default(); BUF_SIZE];
+
+    loop {
+        let len = array.buffer(&mut buffer).await?;
+        if len == 0 {
+            break Ok(());
+        }
+
+        for value in &buffer[..len] {
+            let mut decoder = Primitive::new(*value);
+            insert(T::from_stream(context, &mut decoder).await?);
+        }
+    }
+}
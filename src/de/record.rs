@@ -0,0 +1,633 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::en::{Encoder, IntoStream};
+
+use super::{
+    ArrayAccess, Decoder, Error, FromStream, KeyHint, MapAccess, SeqAccess, TextAccess, Visitor,
+};
+
+/// A trace of the values which a [`Decoder`] passed to a [`Visitor`], captured by a
+/// [`Recording`] and re-driven by a [`Replay`].
+///
+/// A string which was visited as a text stream is recorded as a single string. A [`Trace`] is
+/// encoded as a tuple `(kind, value)`, so it can be saved (e.g. as a golden test fixture) and
+/// decoded again later.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trace {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    ArrayBool(Vec<bool>),
+    ArrayI8(Vec<i8>),
+    ArrayI16(Vec<i16>),
+    ArrayI32(Vec<i32>),
+    ArrayI64(Vec<i64>),
+    ArrayU8(Vec<u8>),
+    ArrayU16(Vec<u16>),
+    ArrayU32(Vec<u32>),
+    ArrayU64(Vec<u64>),
+    ArrayF32(Vec<f32>),
+    ArrayF64(Vec<f64>),
+    String(String),
+    Unit,
+    None,
+    Some(Box<Trace>),
+    Map(Vec<(Trace, Trace)>),
+    Seq(Vec<Trace>),
+}
+
+type Shared<T> = Arc<Mutex<T>>;
+
+fn shared<T: Default>() -> Shared<T> {
+    Arc::new(Mutex::new(T::default()))
+}
+
+fn take<T: Default>(shared: &Shared<T>) -> T {
+    match shared.lock() {
+        Ok(mut value) => mem::take(&mut *value),
+        Err(poisoned) => mem::take(&mut *poisoned.into_inner()),
+    }
+}
+
+fn update<T, F: FnOnce(&mut T)>(shared: &Shared<T>, f: F) {
+    match shared.lock() {
+        Ok(mut value) => f(&mut value),
+        Err(poisoned) => f(&mut poisoned.into_inner()),
+    }
+}
+
+/// A [`Decoder`] which records a [`Trace`] of each value decoded by the underlying decoder.
+///
+/// Only the parts of the input which a [`Visitor`] actually reads are recorded, so e.g. the
+/// trailing elements of a sequence which a visitor stops reading early are not recorded.
+pub struct Recording<'a, D> {
+    decoder: &'a mut D,
+    traces: Shared<Vec<Trace>>,
+}
+
+impl<'a, D> Recording<'a, D> {
+    /// Wrap the given `decoder` to record a [`Trace`] of each value decoded.
+    pub fn new(decoder: &'a mut D) -> Self {
+        Self {
+            decoder,
+            traces: shared(),
+        }
+    }
+
+    /// Return the [`Trace`]s recorded so far, one for each top-level value decoded.
+    pub fn into_traces(self) -> Vec<Trace> {
+        take(&self.traces)
+    }
+
+    fn into_trace(self) -> Trace {
+        // a value which didn't visit anything has nothing to replay
+        take(&self.traces).pop().unwrap_or(Trace::Unit)
+    }
+}
+
+forward_decoder!(
+    [<'a, D: Decoder>] Recording<'a, D>, D::Error;
+    |this, visitor, _len| this.decoder => RecordVisitor {
+        visitor,
+        traces: this.traces.clone(),
+    };
+);
+
+/// A value decoded by a [`Recording`], with its [`Trace`].
+struct Recorded<T> {
+    value: T,
+    trace: Trace,
+}
+
+#[async_trait]
+impl<T: FromStream> FromStream for Recorded<T> {
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let mut recording = Recording::new(decoder);
+        let value = T::from_stream(context, &mut recording).await?;
+        let trace = recording.into_trace();
+        Ok(Self { value, trace })
+    }
+}
+
+struct RecordVisitor<V> {
+    visitor: V,
+    traces: Shared<Vec<Trace>>,
+}
+
+fn record(traces: &Shared<Vec<Trace>>, trace: Trace) {
+    update(traces, |traces| traces.push(trace))
+}
+
+macro_rules! record_visitor {
+    (
+        $($visit:ident($ty:ty) => $variant:ident),+;
+        $($visit_array:ident($elem:ty) => $array_variant:ident),+
+    ) => {
+        #[async_trait]
+        impl<V: Visitor> Visitor for RecordVisitor<V> {
+            type Value = V::Value;
+
+            fn expecting() -> &'static str {
+                V::expecting()
+            }
+
+            $(
+                fn $visit<E: Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    record(&self.traces, Trace::$variant(v));
+                    self.visitor.$visit(v)
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let Self { visitor, traces } = self;
+                    let values = shared();
+                    let array = RecordArray { array, values: values.clone() };
+                    let value = visitor.$visit_array(array).await?;
+                    record(&traces, Trace::$array_variant(take(&values)));
+                    Ok(value)
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                record(&self.traces, Trace::String(v.clone()));
+                self.visitor.visit_string(v)
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                text: A,
+            ) -> Result<Self::Value, A::Error> {
+                let Self { visitor, traces } = self;
+                let bytes = shared();
+                let text = RecordText { text, bytes: bytes.clone() };
+                let value = visitor.visit_text_stream(text).await?;
+                let string = String::from_utf8_lossy(&take(&bytes)).into_owned();
+                record(&traces, Trace::String(string));
+                Ok(value)
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                record(&self.traces, Trace::Unit);
+                self.visitor.visit_unit()
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                record(&self.traces, Trace::None);
+                self.visitor.visit_none()
+            }
+
+            async fn visit_some<D: Decoder>(
+                self,
+                decoder: &mut D,
+            ) -> Result<Self::Value, D::Error> {
+                let Self { visitor, traces } = self;
+                let mut recording = Recording::new(decoder);
+                let value = visitor.visit_some(&mut recording).await?;
+                record(&traces, Trace::Some(Box::new(recording.into_trace())));
+                Ok(value)
+            }
+
+            async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+                let Self { visitor, traces } = self;
+                let keys = shared();
+                let values = shared();
+                let map = RecordMap {
+                    map,
+                    keys: keys.clone(),
+                    values: values.clone(),
+                };
+
+                let value = visitor.visit_map(map).await?;
+                let entries = take(&keys).into_iter().zip(take(&values)).collect();
+                record(&traces, Trace::Map(entries));
+                Ok(value)
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+                let Self { visitor, traces } = self;
+                let elements = shared();
+                let seq = RecordSeq {
+                    seq,
+                    elements: elements.clone(),
+                };
+
+                let value = visitor.visit_seq(seq).await?;
+                record(&traces, Trace::Seq(take(&elements)));
+                Ok(value)
+            }
+        }
+    };
+}
+
+visit_methods!(record_visitor);
+
+struct RecordArray<A, T> {
+    array: A,
+    values: Shared<Vec<T>>,
+}
+
+#[async_trait]
+impl<T: Copy + Send, A: ArrayAccess<T>> ArrayAccess<T> for RecordArray<A, T> {
+    type Error = A::Error;
+
+    async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, Self::Error> {
+        let len = self.array.buffer(buffer).await?;
+        update(&self.values, |values| {
+            values.extend_from_slice(&buffer[..len])
+        });
+        Ok(len)
+    }
+}
+
+struct RecordText<A> {
+    text: A,
+    bytes: Shared<Vec<u8>>,
+}
+
+#[async_trait]
+impl<A: TextAccess> TextAccess for RecordText<A> {
+    type Error = A::Error;
+
+    async fn buffer(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = self.text.buffer(buffer).await?;
+        update(&self.bytes, |bytes| bytes.extend_from_slice(&buffer[..len]));
+        Ok(len)
+    }
+}
+
+struct RecordMap<A> {
+    map: A,
+    keys: Shared<Vec<Trace>>,
+    values: Shared<Vec<Trace>>,
+}
+
+#[async_trait]
+impl<A: MapAccess> MapAccess for RecordMap<A> {
+    type Error = A::Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.next_key_as(KeyHint::Any, context).await
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        match self.map.next_key_as::<Recorded<K>>(hint, context).await? {
+            Some(Recorded { value, trace }) => {
+                update(&self.keys, |keys| keys.push(trace));
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        let Recorded { value, trace } = self.map.next_value(context).await?;
+        update(&self.values, |values| values.push(trace));
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct RecordSeq<A> {
+    seq: A,
+    elements: Shared<Vec<Trace>>,
+}
+
+#[async_trait]
+impl<A: SeqAccess> SeqAccess for RecordSeq<A> {
+    type Error = A::Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        match self.seq.next_element::<Recorded<T>>(context).await? {
+            Some(Recorded { value, trace }) => {
+                update(&self.elements, |elements| elements.push(trace));
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A [`Decoder`] which re-drives a [`Visitor`] with a recorded [`Trace`],
+/// regardless of the type hint given.
+pub struct Replay<E> {
+    trace: Option<Trace>,
+    error: PhantomData<E>,
+}
+
+impl<E> Replay<E> {
+    /// Construct a new [`Replay`] of the given `trace`.
+    pub fn new(trace: Trace) -> Self {
+        Self {
+            trace: Some(trace),
+            error: PhantomData,
+        }
+    }
+}
+
+impl<E: Error> Replay<E> {
+    fn take(&mut self) -> Result<Trace, E> {
+        self.trace
+            .take()
+            .ok_or_else(|| Error::custom("this trace has already been replayed"))
+    }
+}
+
+macro_rules! replay {
+    (
+        $trace:expr, $visitor:expr;
+        $($variant:ident => $visit:ident),+;
+        $($array_variant:ident => $visit_array:ident),+
+    ) => {
+        match $trace {
+            $(Trace::$variant(v) => $visitor.$visit(v),)+
+            $(Trace::$array_variant(v) => $visitor.$visit_array(ReplayArray::new(v)).await,)+
+            Trace::String(v) => $visitor.visit_string(v),
+            Trace::Unit => $visitor.visit_unit(),
+            Trace::None => $visitor.visit_none(),
+            Trace::Some(trace) => $visitor.visit_some(&mut Replay::<E>::new(*trace)).await,
+            Trace::Map(entries) => $visitor.visit_map(ReplayMap::new(entries)).await,
+            Trace::Seq(elements) => $visitor.visit_seq(ReplaySeq::new(elements)).await,
+        }
+    };
+}
+
+async fn replay<V: Visitor, E: Error>(trace: Trace, visitor: V) -> Result<V::Value, E> {
+    replay!(
+        trace, visitor;
+        Bool => visit_bool,
+        I8 => visit_i8,
+        I16 => visit_i16,
+        I32 => visit_i32,
+        I64 => visit_i64,
+        U8 => visit_u8,
+        U16 => visit_u16,
+        U32 => visit_u32,
+        U64 => visit_u64,
+        F32 => visit_f32,
+        F64 => visit_f64;
+        ArrayBool => visit_array_bool,
+        ArrayI8 => visit_array_i8,
+        ArrayI16 => visit_array_i16,
+        ArrayI32 => visit_array_i32,
+        ArrayI64 => visit_array_i64,
+        ArrayU8 => visit_array_u8,
+        ArrayU16 => visit_array_u16,
+        ArrayU32 => visit_array_u32,
+        ArrayU64 => visit_array_u64,
+        ArrayF32 => visit_array_f32,
+        ArrayF64 => visit_array_f64
+    )
+}
+
+macro_rules! replay_decoder {
+    ($($method:ident),+) => {
+        #[async_trait]
+        impl<E: Error> Decoder for Replay<E> {
+            type Error = E;
+
+            $(
+                async fn $method<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, E> {
+                    replay(self.take()?, visitor).await
+                }
+            )+
+
+            async fn decode_tuple<V: Visitor>(
+                &mut self,
+                _len: usize,
+                visitor: V,
+            ) -> Result<V::Value, E> {
+                replay(self.take()?, visitor).await
+            }
+
+            async fn decode_ignored_any<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, E> {
+                replay(self.take()?, visitor).await
+            }
+        }
+    };
+}
+
+decode_methods!(replay_decoder);
+
+struct ReplayArray<T, E> {
+    values: Vec<T>,
+    offset: usize,
+    error: PhantomData<E>,
+}
+
+impl<T, E> ReplayArray<T, E> {
+    fn new(values: Vec<T>) -> Self {
+        Self {
+            values,
+            offset: 0,
+            error: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Copy + Send, E: Error> ArrayAccess<T> for ReplayArray<T, E> {
+    type Error = E;
+
+    async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, E> {
+        let remaining = &self.values[self.offset..];
+        let len = Ord::min(remaining.len(), buffer.len());
+        buffer[..len].copy_from_slice(&remaining[..len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+struct ReplayMap<E> {
+    entries: std::vec::IntoIter<(Trace, Trace)>,
+    value: Option<Trace>,
+    error: PhantomData<E>,
+}
+
+impl<E> ReplayMap<E> {
+    fn new(entries: Vec<(Trace, Trace)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+            error: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Error> MapAccess for ReplayMap<E> {
+    type Error = E;
+
+    async fn next_key<K: FromStream>(&mut self, context: K::Context) -> Result<Option<K>, E> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                K::from_stream(context, &mut Replay::new(key))
+                    .await
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, E> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::custom("called next_value before next_key"))?;
+
+        V::from_stream(context, &mut Replay::new(value)).await
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct ReplaySeq<E> {
+    elements: std::vec::IntoIter<Trace>,
+    error: PhantomData<E>,
+}
+
+impl<E> ReplaySeq<E> {
+    fn new(elements: Vec<Trace>) -> Self {
+        Self {
+            elements: elements.into_iter(),
+            error: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Error> SeqAccess for ReplaySeq<E> {
+    type Error = E;
+
+    async fn next_element<T: FromStream>(&mut self, context: T::Context) -> Result<Option<T>, E> {
+        match self.elements.next() {
+            Some(element) => T::from_stream(context, &mut Replay::new(element))
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! trace_codec {
+    ($($variant:ident => $kind:literal),+) => {
+        struct TraceVisitor;
+
+        #[async_trait]
+        impl Visitor for TraceVisitor {
+            type Value = Trace;
+
+            fn expecting() -> &'static str {
+                "a recorded trace of the form (kind, value)"
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let kind: String = seq.expect_next(()).await?;
+
+                match kind.as_str() {
+                    $($kind => seq.expect_next(()).await.map(Trace::$variant),)+
+                    "unit" => seq.expect_next::<()>(()).await.map(|()| Trace::Unit),
+                    "none" => seq.expect_next::<()>(()).await.map(|()| Trace::None),
+                    "some" => seq.expect_next(()).await.map(|trace| Trace::Some(Box::new(trace))),
+                    _ => Err(Error::invalid_value(kind, "a recorded trace kind")),
+                }
+            }
+        }
+
+        impl<'en> IntoStream<'en> for Trace {
+            fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                match self {
+                    $(Self::$variant(v) => ($kind, v).into_stream(encoder),)+
+                    Self::Unit => ("unit", ()).into_stream(encoder),
+                    Self::None => ("none", ()).into_stream(encoder),
+                    Self::Some(trace) => ("some", *trace).into_stream(encoder),
+                }
+            }
+        }
+    };
+}
+
+trace_codec!(
+    Bool => "bool",
+    I8 => "i8",
+    I16 => "i16",
+    I32 => "i32",
+    I64 => "i64",
+    U8 => "u8",
+    U16 => "u16",
+    U32 => "u32",
+    U64 => "u64",
+    F32 => "f32",
+    F64 => "f64",
+    ArrayBool => "array_bool",
+    ArrayI8 => "array_i8",
+    ArrayI16 => "array_i16",
+    ArrayI32 => "array_i32",
+    ArrayI64 => "array_i64",
+    ArrayU8 => "array_u8",
+    ArrayU16 => "array_u16",
+    ArrayU32 => "array_u32",
+    ArrayU64 => "array_u64",
+    ArrayF32 => "array_f32",
+    ArrayF64 => "array_f64",
+    String => "string",
+    Map => "map",
+    Seq => "seq"
+);
+
+#[async_trait]
+impl FromStream for Trace {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_tuple(2, TraceVisitor).await
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::{BoxFuture, FutureExt};
+
+use super::flatten::Captured;
+use super::{Decoder, Error, FromStream, MapAccess, Replay, Trace, Visitor};
+
+/// A set of decode functions for the implementations of a trait `T`, keyed by the tag which
+/// identifies each implementation, e.g. to decode types defined by a plugin as a trait object.
+///
+/// A tagged value is encoded as a map whose `tag_key` entry is the tag of its type and whose
+/// other entries are the fields of the value. Use [`Polymorphic`] to decode a tagged value.
+///
+/// The fields which follow the tag are passed to the registered type as they are decoded, so
+/// encoding the tag first avoids buffering. Any fields which precede the tag are buffered.
+///
+/// Example:
+/// ```
+/// # use std::sync::Arc;
+/// # use destream::de::{Polymorphic, Registry};
+/// trait Shape: Send {
+///     fn area(&self) -> f64;
+/// }
+///
+/// struct Square(f64);
+///
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let mut registry = Registry::<dyn Shape>::new("type");
+///
+/// // a `HashMap` stands in for a struct which implements `FromStream`
+/// registry.register("square", |fields: std::collections::HashMap<String, f64>| {
+///     Box::new(Square(fields["side"])) as Box<dyn Shape>
+/// });
+///
+/// assert!(registry.contains("square"));
+/// let _context: Arc<Registry<dyn Shape>> = Arc::new(registry);
+/// ```
+pub struct Registry<T: ?Sized> {
+    tag_key: &'static str,
+    decoders: HashMap<String, Box<dyn Decode<T>>>,
+}
+
+impl<T: ?Sized> Registry<T> {
+    /// Construct a new, empty [`Registry`] whose values are tagged with the given `tag_key`.
+    pub fn new(tag_key: &'static str) -> Self {
+        Self {
+            tag_key,
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// The map key whose value is the tag of a value's type.
+    pub fn tag_key(&self) -> &'static str {
+        self.tag_key
+    }
+
+    /// Return `true` if a decode function is registered for the given `tag`.
+    pub fn contains(&self, tag: &str) -> bool {
+        self.decoders.contains_key(tag)
+    }
+
+    /// Iterate over the registered tags, in no particular order.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.decoders.keys().map(String::as_str)
+    }
+
+    /// Register a type `U` under the given `tag`, replacing any type already registered there.
+    ///
+    /// A value with this tag is decoded from the remaining entries of its map as a `U`,
+    /// then converted into a `Box<T>` with the given function `into`.
+    pub fn register<U, F>(&mut self, tag: impl Into<String>, into: F) -> &mut Self
+    where
+        T: Send,
+        U: FromStream<Context = ()> + 'static,
+        F: Fn(U) -> Box<T> + Send + Sync + 'static,
+    {
+        let registered = Registered {
+            into,
+            marker: PhantomData,
+        };
+
+        self.decoders.insert(tag.into(), Box::new(registered));
+        self
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Registry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("tag_key", &self.tag_key)
+            .field("tags", &self.decoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A value of any type registered in a [`Registry`], decoded according to its tag.
+pub struct Polymorphic<T: ?Sized>(pub Box<T>);
+
+impl<T: ?Sized> Polymorphic<T> {
+    /// Return the decoded value.
+    pub fn into_inner(self) -> Box<T> {
+        self.0
+    }
+}
+
+#[async_trait]
+impl<T: ?Sized + Send + 'static> FromStream for Polymorphic<T> {
+    type Context = Arc<Registry<T>>;
+
+    async fn from_stream<D: Decoder>(
+        registry: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_map(PolymorphicVisitor { registry }).await
+    }
+}
+
+struct PolymorphicVisitor<T: ?Sized> {
+    registry: Arc<Registry<T>>,
+}
+
+#[async_trait]
+impl<T: ?Sized + Send + 'static> Visitor for PolymorphicVisitor<T> {
+    type Value = Polymorphic<T>;
+
+    fn expecting() -> &'static str {
+        "a tagged map"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let tag_key = self.registry.tag_key;
+        let mut buffered = Vec::new();
+
+        // buffer any fields which precede the tag, then stream the rest
+        let tag: String = loop {
+            match map.next_key::<String>(()).await? {
+                Some(key) if key == tag_key => break map.next_value(()).await?,
+                Some(key) => {
+                    let Captured(value) = map.next_value(()).await?;
+                    buffered.push((key, value));
+                }
+                None => return Err(A::Error::missing_field(tag_key)),
+            }
+        };
+
+        let decode = self.registry.decoders.get(&tag).ok_or_else(|| {
+            let mut tags = self.registry.tags().collect::<Vec<_>>();
+            tags.sort_unstable();
+            A::Error::invalid_value(&tag, format_args!("one of {}", tags.join(", ")))
+        })?;
+
+        let mut entries = MapEntries {
+            buffered: buffered.into_iter(),
+            map: &mut map,
+            done: false,
+            error: None,
+        };
+
+        let mut result = decode.decode(&mut entries).await;
+
+        // skip any entries which the registered type did not read
+        while result.is_ok() {
+            match entries.next_entry().await {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(cause) => result = Err(cause),
+            }
+        }
+
+        if let Some(cause) = entries.error {
+            return Err(cause);
+        }
+
+        result
+            .map(Polymorphic)
+            .map_err(|cause| cause.into_error::<A::Error>().context(tag))
+    }
+}
+
+/// A decode function for one type registered in a [`Registry`].
+trait Decode<T: ?Sized>: Send + Sync {
+    fn decode<'a>(
+        &'a self,
+        entries: &'a mut dyn Entries,
+    ) -> BoxFuture<'a, Result<Box<T>, RegistryError>>;
+}
+
+struct Registered<U, F> {
+    into: F,
+    marker: PhantomData<fn() -> U>,
+}
+
+impl<T, U, F> Decode<T> for Registered<U, F>
+where
+    T: ?Sized + Send,
+    U: FromStream<Context = ()>,
+    F: Fn(U) -> Box<T> + Send + Sync,
+{
+    fn decode<'a>(
+        &'a self,
+        entries: &'a mut dyn Entries,
+    ) -> BoxFuture<'a, Result<Box<T>, RegistryError>> {
+        async move {
+            let mut decoder = EntriesDecoder {
+                entries: Some(entries),
+            };
+
+            let value = U::from_stream((), &mut decoder).await?;
+            Ok((self.into)(value))
+        }
+        .boxed()
+    }
+}
+
+/// The remaining entries of a tagged map, erased over the type of its [`MapAccess`].
+trait Entries: Send {
+    fn next_entry(&mut self) -> BoxFuture<'_, Result<Option<(String, Trace)>, RegistryError>>;
+}
+
+/// The entries of a tagged map which preceded its tag, followed by the rest of the map.
+///
+/// An error decoding the map itself is kept in `error`, so that it can be returned as-is.
+struct MapEntries<'a, A: MapAccess> {
+    buffered: std::vec::IntoIter<(String, Trace)>,
+    map: &'a mut A,
+    done: bool,
+    error: Option<A::Error>,
+}
+
+impl<'a, A: MapAccess> MapEntries<'a, A> {
+    async fn next_from_map(&mut self) -> Result<Option<(String, Trace)>, A::Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        match self.map.next_key::<String>(()).await? {
+            Some(key) => {
+                let Captured(value) = self.map.next_value(()).await?;
+                Ok(Some((key, value)))
+            }
+            None => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'a, A: MapAccess> Entries for MapEntries<'a, A> {
+    fn next_entry(&mut self) -> BoxFuture<'_, Result<Option<(String, Trace)>, RegistryError>> {
+        async move {
+            if let Some(entry) = self.buffered.next() {
+                return Ok(Some(entry));
+            }
+
+            self.next_from_map().await.map_err(|cause| {
+                self.error = Some(cause);
+                Error::custom("failed to decode a tagged map")
+            })
+        }
+        .boxed()
+    }
+}
+
+/// A [`Decoder`] which presents the remaining [`Entries`] of a tagged map as a map,
+/// regardless of the type hint given.
+struct EntriesDecoder<'a> {
+    entries: Option<&'a mut dyn Entries>,
+}
+
+impl<'a> EntriesDecoder<'a> {
+    fn take(&mut self) -> Result<EntriesMap<'a>, RegistryError> {
+        let entries = self
+            .entries
+            .take()
+            .ok_or_else(|| Error::custom("the fields of a tagged map have already been decoded"))?;
+
+        Ok(EntriesMap {
+            entries,
+            key: None,
+            value: None,
+        })
+    }
+}
+
+macro_rules! entries_decoder {
+    ($($method:ident),+) => {
+        #[async_trait]
+        impl<'a> Decoder for EntriesDecoder<'a> {
+            type Error = RegistryError;
+
+            $(
+                async fn $method<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, Self::Error> {
+                    visitor.visit_map(self.take()?).await
+                }
+            )+
+
+            async fn decode_tuple<V: Visitor>(
+                &mut self,
+                _len: usize,
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                visitor.visit_map(self.take()?).await
+            }
+
+            async fn decode_ignored_any<V: Visitor>(
+                &mut self,
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                visitor.visit_map(self.take()?).await
+            }
+        }
+    };
+}
+
+decode_methods!(entries_decoder);
+
+struct EntriesMap<'a> {
+    entries: &'a mut dyn Entries,
+    key: Option<String>,
+    value: Option<Trace>,
+}
+
+#[async_trait]
+impl<'a> MapAccess for EntriesMap<'a> {
+    type Error = RegistryError;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        match self.entries.next_entry().await? {
+            Some((key, value)) => {
+                self.key = Some(key.clone());
+                self.value = Some(value);
+
+                K::from_stream(context, &mut Replay::new(Trace::String(key)))
+                    .await
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::custom("called next_value before next_key"))?;
+
+        let result: Result<V, RegistryError> =
+            V::from_stream(context, &mut Replay::new(value)).await;
+
+        match &self.key {
+            Some(key) => result.map_err(|cause| cause.with_key(key)),
+            None => result,
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// An error decoding the fields of a registered type, converted into the error type of the
+/// calling [`Decoder`] once its decode function returns.
+///
+/// The keys of the fields which failed to decode are kept, innermost first, and re-applied
+/// with [`Error::with_key`] when the error is converted.
+#[derive(Debug)]
+struct RegistryError {
+    message: String,
+    path: Vec<String>,
+}
+
+impl RegistryError {
+    fn into_error<E: Error>(self) -> E {
+        self.path
+            .into_iter()
+            .fold(E::custom(self.message), |cause, key| cause.with_key(key))
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for key in self.path.iter().rev() {
+            write!(f, "at key {}: ", key)?;
+        }
+
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl Error for RegistryError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            message: msg.to_string(),
+            path: Vec::new(),
+        }
+    }
+
+    fn with_context(mut self, context: String) -> Self {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+
+    fn with_key<K: fmt::Display>(mut self, key: K) -> Self {
+        self.path.push(key.to_string());
+        self
+    }
+}
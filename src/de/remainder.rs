@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream};
+
+/// A [`Decoder`] which can stop decoding and give back the remainder of its input, e.g. to hand
+/// a raw binary body which follows an encoded header to other code.
+#[async_trait]
+pub trait IntoRemainder: Decoder + Sized {
+    /// The type of the remaining input, e.g. the source stream of the decoder.
+    type Remainder;
+
+    /// Stop decoding and return the remaining input.
+    ///
+    /// Implementations must include any input which the decoder has buffered but not consumed,
+    /// e.g. by chaining the buffer in front of the source stream.
+    fn into_remainder(self) -> Self::Remainder;
+
+    /// Decode a single value of type `T`, such as a header, and return it
+    /// along with the remaining input.
+    async fn decode_prefix<T: FromStream>(
+        mut self,
+        context: T::Context,
+    ) -> Result<(T, Self::Remainder), Self::Error> {
+        let value = T::from_stream(context, &mut self).await?;
+        Ok((value, self.into_remainder()))
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::TryFutureExt;
+
+use super::{Decoder, Error, FromStream, KeyHint, MapAccess, SeqAccess, TextAccess, Visitor};
+
+/// A mapping of old map keys to new map keys, to apply when decoding.
+///
+/// This allows a schema migration to be handled in one place, rather than by every
+/// [`FromStream`] implementation which might encounter an old key.
+#[derive(Clone, Debug, Default)]
+pub struct Renaming {
+    keys: HashMap<String, String>,
+}
+
+impl Renaming {
+    /// Construct a new [`Renaming`] from an iterator of `(old, new)` key pairs.
+    pub fn new<I, O, N>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = (O, N)>,
+        O: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            keys: keys
+                .into_iter()
+                .map(|(old, new)| (old.into(), new.into()))
+                .collect(),
+        }
+    }
+
+    /// Return the new name of the given `key`, or the `key` itself if it has not been renamed.
+    pub fn rename(&self, key: String) -> String {
+        match self.keys.get(&key) {
+            Some(new) => new.clone(),
+            None => key,
+        }
+    }
+}
+
+/// A [`Decoder`] which renames map keys according to a [`Renaming`], recursively,
+/// before they are visited.
+///
+/// Only keys which are decoded as strings are renamed.
+pub struct Rename<'a, D> {
+    decoder: &'a mut D,
+    renaming: Arc<Renaming>,
+    key: bool,
+}
+
+impl<'a, D> Rename<'a, D> {
+    /// Wrap the given `decoder` so that it applies the given `renaming`.
+    pub fn new(decoder: &'a mut D, renaming: Arc<Renaming>) -> Self {
+        Self {
+            decoder,
+            renaming,
+            key: false,
+        }
+    }
+
+    fn visitor<V>(&self, visitor: V) -> RenameVisitor<V> {
+        RenameVisitor {
+            visitor,
+            renaming: self.renaming.clone(),
+            key: self.key,
+        }
+    }
+}
+
+forward_decoder!(
+    [<'a, D: Decoder>] Rename<'a, D>, D::Error;
+    |this, visitor, _len| this.decoder => this.visitor(visitor);
+
+    async fn skip_value(&mut self) -> Result<(), Self::Error> {
+        self.decoder.skip_value().await
+    }
+);
+
+struct RenameVisitor<V> {
+    visitor: V,
+    renaming: Arc<Renaming>,
+    key: bool,
+}
+
+forward_visitor!(
+    [<V: Visitor>] RenameVisitor<V> => V;
+    |this| this.visitor;
+    |array| array;
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        if self.key {
+            self.visitor.visit_string(self.renaming.rename(v))
+        } else {
+            self.visitor.visit_string(v)
+        }
+    }
+
+    async fn visit_text_stream<A: TextAccess>(self, text: A) -> Result<Self::Value, A::Error> {
+        if self.key {
+            let key = super::collect_text(text).await?;
+            self.visitor.visit_string(self.renaming.rename(key))
+        } else {
+            self.visitor.visit_text_stream(text).await
+        }
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        let mut decoder = Rename {
+            decoder,
+            renaming: self.renaming,
+            key: self.key,
+        };
+
+        self.visitor.visit_some(&mut decoder).await
+    }
+
+    async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+        let map = RenameMap {
+            map,
+            renaming: self.renaming,
+        };
+
+        self.visitor.visit_map(map).await
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let seq = RenameSeq {
+            seq,
+            renaming: self.renaming,
+        };
+
+        self.visitor.visit_seq(seq).await
+    }
+);
+
+/// A value decoded with a [`Renaming`] applied.
+struct Renamed<T>(T);
+
+#[async_trait]
+impl<T: FromStream> FromStream for Renamed<T> {
+    type Context = (T::Context, Arc<Renaming>, bool);
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let (context, renaming, key) = context;
+        let mut decoder = Rename {
+            decoder,
+            renaming,
+            key,
+        };
+
+        T::from_stream(context, &mut decoder).map_ok(Self).await
+    }
+}
+
+struct RenameMap<A> {
+    map: A,
+    renaming: Arc<Renaming>,
+}
+
+#[async_trait]
+impl<A: MapAccess> MapAccess for RenameMap<A> {
+    type Error = A::Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.next_key_as(KeyHint::Any, context).await
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        let context = (context, self.renaming.clone(), true);
+        let key: Option<Renamed<K>> = self.map.next_key_as(hint, context).await?;
+        Ok(key.map(|Renamed(key)| key))
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        let context = (context, self.renaming.clone(), false);
+        let Renamed(value) = self.map.next_value(context).await?;
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct RenameSeq<A> {
+    seq: A,
+    renaming: Arc<Renaming>,
+}
+
+#[async_trait]
+impl<A: SeqAccess> SeqAccess for RenameSeq<A> {
+    type Error = A::Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        let context = (context, self.renaming.clone(), false);
+        let element: Option<Renamed<T>> = self.seq.next_element(context).await?;
+        Ok(element.map(|Renamed(element)| element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
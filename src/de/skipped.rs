@@ -0,0 +1,212 @@
+use std::mem::size_of;
+
+use async_trait::async_trait;
+
+use super::{ArrayAccess, Decoder, Error, FromStream, MapAccess, SeqAccess, TextAccess, Visitor};
+
+const BUF_SIZE: usize = 4_096;
+
+/// The kind of value skipped by [`Skipped`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SkippedKind {
+    Unit,
+    None,
+    Bool,
+    Number,
+    String,
+    Array,
+    Seq,
+    Map,
+}
+
+/// Like [`IgnoredAny`](super::IgnoredAny), a value which is skipped rather than decoded,
+/// but which records a summary of what was skipped, e.g. to detect schema drift.
+///
+/// An optional value which is present is summarized as its contents.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Skipped {
+    kind: SkippedKind,
+    values: usize,
+    bytes: usize,
+}
+
+impl Skipped {
+    fn new(kind: SkippedKind) -> Self {
+        Self {
+            kind,
+            values: 0,
+            bytes: 0,
+        }
+    }
+
+    fn primitive(kind: SkippedKind, bytes: usize) -> Self {
+        Self {
+            kind,
+            values: 1,
+            bytes,
+        }
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.values += other.values;
+        self.bytes += other.bytes;
+    }
+
+    /// The kind of value skipped.
+    pub fn kind(&self) -> SkippedKind {
+        self.kind
+    }
+
+    /// The number of primitive values skipped, including the elements of any collection.
+    pub fn values(&self) -> usize {
+        self.values
+    }
+
+    /// The approximate size of the primitive values skipped, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+struct SkippedVisitor;
+
+async fn skip_array<P, A>(mut array: A) -> Result<Skipped, A::Error>
+where
+    P: Copy + Default + Send,
+    A: ArrayAccess<P>,
+{
+    let mut skipped = Skipped::new(SkippedKind::Array);
+    let mut buffer = vec![P::default(); BUF_SIZE];
+
+    loop {
+        let len = array.buffer(&mut buffer).await?;
+        if len == 0 {
+            break Ok(skipped);
+        }
+
+        skipped.values += len;
+        skipped.bytes += len * size_of::<P>();
+    }
+}
+
+macro_rules! skipped_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty)),+
+    ) => {
+        #[async_trait]
+        impl Visitor for SkippedVisitor {
+            type Value = Skipped;
+
+            fn expecting() -> &'static str {
+                "anything at all"
+            }
+
+            $(
+                fn $visit<E: Error>(self, _v: $ty) -> Result<Self::Value, E> {
+                    Ok(Skipped::primitive(SkippedKind::$kind, size_of::<$ty>()))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    skip_array(array).await
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Skipped::primitive(SkippedKind::String, v.len()))
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                mut text: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut skipped = Skipped::primitive(SkippedKind::String, 0);
+                let mut buffer = [0u8; BUF_SIZE];
+
+                loop {
+                    let len = text.buffer(&mut buffer).await?;
+                    if len == 0 {
+                        break Ok(skipped);
+                    }
+
+                    skipped.bytes += len;
+                }
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(Skipped::new(SkippedKind::Unit))
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(Skipped::new(SkippedKind::None))
+            }
+
+            async fn visit_some<D: Decoder>(
+                self,
+                decoder: &mut D,
+            ) -> Result<Self::Value, D::Error> {
+                Skipped::from_stream((), decoder).await
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut skipped = Skipped::new(SkippedKind::Map);
+
+                while let Some(key) = map.next_key::<Skipped>(()).await? {
+                    skipped.absorb(key);
+                    skipped.absorb(map.next_value(()).await?);
+                }
+
+                Ok(skipped)
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut skipped = Skipped::new(SkippedKind::Seq);
+
+                while let Some(element) = seq.next_element(()).await? {
+                    skipped.absorb(element);
+                }
+
+                Ok(skipped)
+            }
+        }
+    };
+}
+
+skipped_visitor!(
+    visit_bool(bool) => Bool,
+    visit_i8(i8) => Number,
+    visit_i16(i16) => Number,
+    visit_i32(i32) => Number,
+    visit_i64(i64) => Number,
+    visit_u8(u8) => Number,
+    visit_u16(u16) => Number,
+    visit_u32(u32) => Number,
+    visit_u64(u64) => Number,
+    visit_f32(f32) => Number,
+    visit_f64(f64) => Number;
+    visit_array_bool(bool),
+    visit_array_i8(i8),
+    visit_array_i16(i16),
+    visit_array_i32(i32),
+    visit_array_i64(i64),
+    visit_array_u8(u8),
+    visit_array_u16(u16),
+    visit_array_u32(u32),
+    visit_array_u64(u64),
+    visit_array_f32(f32),
+    visit_array_f64(f64)
+);
+
+#[async_trait]
+impl FromStream for Skipped {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_ignored_any(SkippedVisitor).await
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::future::{BoxFuture, Future, FutureExt};
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+
+use super::Error;
+
+/// Helpers for post-processing a stream of decoded elements, e.g. the output of a
+/// [`Visitor`](super::Visitor) which forwards each element of a sequence to a channel.
+///
+/// Every adapter ends after yielding the first error of its source stream.
+pub trait DecodedStreamExt<T, E>: Stream<Item = Result<T, E>> + Sized {
+    /// Collect the decoded elements of this stream into [`Vec`]s of up to `size` elements.
+    ///
+    /// Panics if `size` is zero.
+    fn chunks_of(self, size: usize) -> Chunks<Self, T> {
+        assert!(size > 0, "chunk size must be nonzero");
+
+        Chunks {
+            source: Some(self),
+            chunk: Vec::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Yield the decoded byte buffers of this stream until their total length exceeds `limit`,
+    /// at which point yield an error and end.
+    fn take_bytes(self, limit: usize) -> TakeBytes<Self>
+    where
+        T: AsRef<[u8]>,
+        E: Error,
+    {
+        TakeBytes {
+            source: Some(self),
+            limit,
+            len: 0,
+        }
+    }
+
+    /// Annotate an error with the given `path` and the index of the element which failed.
+    fn map_err_path<P: fmt::Display>(self, path: P) -> MapErrPath<Self>
+    where
+        E: Error,
+    {
+        MapErrPath {
+            source: Some(self),
+            path: path.to_string(),
+            index: 0,
+        }
+    }
+}
+
+impl<T, E, S: Stream<Item = Result<T, E>>> DecodedStreamExt<T, E> for S {}
+
+/// A stream of chunks of decoded elements, returned by [`DecodedStreamExt::chunks_of`].
+pub struct Chunks<S, T> {
+    source: Option<S>,
+    chunk: Vec<T>,
+    size: usize,
+}
+
+impl<T, E, S> Stream for Chunks<S, T>
+where
+    T: Unpin,
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    type Item = Result<Vec<T>, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        while let Some(source) = &mut this.source {
+            match ready!(source.poll_next_unpin(cxt)) {
+                Some(Ok(item)) => {
+                    this.chunk.push(item);
+
+                    if this.chunk.len() == this.size {
+                        let chunk = Vec::with_capacity(this.size);
+                        return Poll::Ready(Some(Ok(mem::replace(&mut this.chunk, chunk))));
+                    }
+                }
+                Some(Err(cause)) => {
+                    this.source = None;
+                    return Poll::Ready(Some(Err(cause)));
+                }
+                None => {
+                    this.source = None;
+
+                    if !this.chunk.is_empty() {
+                        return Poll::Ready(Some(Ok(mem::take(&mut this.chunk))));
+                    }
+                }
+            }
+        }
+
+        Poll::Ready(None)
+    }
+}
+
+/// A stream of decoded byte buffers with a total length limit,
+/// returned by [`DecodedStreamExt::take_bytes`].
+pub struct TakeBytes<S> {
+    source: Option<S>,
+    limit: usize,
+    len: usize,
+}
+
+impl<T, E, S> Stream for TakeBytes<S>
+where
+    T: AsRef<[u8]>,
+    E: Error,
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let source = match &mut self.source {
+            Some(source) => source,
+            None => return Poll::Ready(None),
+        };
+
+        let item = match ready!(source.poll_next_unpin(cxt)) {
+            Some(Ok(bytes)) => {
+                self.len = self.len.saturating_add(bytes.as_ref().len());
+
+                if self.len > self.limit {
+                    let limit = self.limit;
+                    self.source = None;
+                    Err(E::custom(format_args!(
+                        "decoded stream exceeds the limit of {} bytes",
+                        limit
+                    )))
+                } else {
+                    Ok(bytes)
+                }
+            }
+            Some(Err(cause)) => {
+                self.source = None;
+                Err(cause)
+            }
+            None => {
+                self.source = None;
+                return Poll::Ready(None);
+            }
+        };
+
+        Poll::Ready(Some(item))
+    }
+}
+
+/// A stream of decoded elements whose errors are annotated with a path,
+/// returned by [`DecodedStreamExt::map_err_path`].
+pub struct MapErrPath<S> {
+    source: Option<S>,
+    path: String,
+    index: usize,
+}
+
+impl<T, E, S> Stream for MapErrPath<S>
+where
+    E: Error,
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let source = match &mut self.source {
+            Some(source) => source,
+            None => return Poll::Ready(None),
+        };
+
+        let item = match ready!(source.poll_next_unpin(cxt)) {
+            Some(Ok(item)) => {
+                self.index += 1;
+                Ok(item)
+            }
+            Some(Err(cause)) => {
+                self.source = None;
+                Err(cause.with_index(self.index).context(&self.path))
+            }
+            None => {
+                self.source = None;
+                return Poll::Ready(None);
+            }
+        };
+
+        Poll::Ready(Some(item))
+    }
+}
+
+/// The items sent to a channel by a decoding task, followed by the task's error, if any.
+///
+/// The task is polled along with the channel, so that it can make progress while the consumer
+/// waits for the next item. Its error is only yielded once every item it sent has been yielded.
+pub(crate) struct Driven<'a, T, E> {
+    receiver: mpsc::Receiver<T>,
+    task: Option<BoxFuture<'a, Result<(), E>>>,
+    error: Option<E>,
+}
+
+impl<'a, T, E> Driven<'a, T, E> {
+    /// Construct a stream of the items which the given `task` sends to `receiver`.
+    pub(crate) fn new<F>(receiver: mpsc::Receiver<T>, task: F) -> Self
+    where
+        F: Future<Output = Result<(), E>> + Send + 'a,
+    {
+        Self {
+            receiver,
+            task: Some(task.boxed()),
+            error: None,
+        }
+    }
+}
+
+// no field is structurally pinned: the task is boxed, and the error is only ever moved out
+impl<'a, T, E> Unpin for Driven<'a, T, E> {}
+
+impl<'a, T, E> Stream for Driven<'a, T, E> {
+    type Item = Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Some(task) = self.task.as_mut() {
+            if let Poll::Ready(result) = task.poll_unpin(cxt) {
+                self.task = None;
+                self.error = result.err();
+            }
+        }
+
+        match ready!(self.receiver.poll_next_unpin(cxt)) {
+            Some(item) => Poll::Ready(Some(Ok(item))),
+            // the task may drop its sender before it completes
+            None if self.task.is_some() => Poll::Pending,
+            None => Poll::Ready(self.error.take().map(Err)),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::TryFutureExt;
+
+use super::{
+    Decoder, Error, FromStream, IgnoredAny, KeyHint, MapAccess, SeqAccess, TextAccess, Visitor,
+};
+
+/// A value decoded strictly, i.e. which fails to decode if any part of the encoded input would
+/// have been ignored, such as an unknown map key or a trailing sequence element.
+///
+/// Strictness applies recursively to every value nested within `T`: a value passed to
+/// [`Decoder::decode_ignored_any`] is an error, and so is a map or sequence which the visitor of
+/// `T` did not read to the end (other than a tuple which has exactly the expected length).
+/// The elements of a typed array, and the contents of a string, are never considered ignored.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Strict<T>(pub T);
+
+impl<T> Strict<T> {
+    /// Return the strictly-decoded value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Strict<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deref for Strict<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Strict<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[async_trait]
+impl<T: FromStream> FromStream for Strict<T> {
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let mut decoder = StrictDecoder { decoder };
+        T::from_stream(context, &mut decoder).map_ok(Self).await
+    }
+}
+
+struct StrictDecoder<'a, D> {
+    decoder: &'a mut D,
+}
+
+forward_decoder!(
+    [<'a, D: Decoder>] StrictDecoder<'a, D>, D::Error;
+    |this, visitor, len| this.decoder => StrictVisitor::new(visitor, len);
+    ignored_any: |_this, _visitor| {
+        Err(Error::custom("strict decoding does not allow a value to be ignored"))
+    };
+);
+
+struct StrictVisitor<V> {
+    visitor: V,
+    len: Option<usize>,
+}
+
+impl<V> StrictVisitor<V> {
+    fn new(visitor: V, len: Option<usize>) -> Self {
+        Self { visitor, len }
+    }
+}
+
+forward_visitor!(
+    [<V: Visitor>] StrictVisitor<V> => V;
+    |this| this.visitor;
+    |array| array;
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visitor.visit_string(v)
+    }
+
+    async fn visit_text_stream<A: TextAccess>(
+        self,
+        text: A,
+    ) -> Result<Self::Value, A::Error> {
+        self.visitor.visit_text_stream(text).await
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        let mut decoder = StrictDecoder { decoder };
+        self.visitor.visit_some(&mut decoder).await
+    }
+
+    async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+        let done = Arc::new(AtomicBool::new(false));
+        let map = StrictMap {
+            map,
+            done: done.clone(),
+        };
+
+        let value = self.visitor.visit_map(map).await?;
+
+        if done.load(Ordering::Acquire) {
+            Ok(value)
+        } else {
+            let message = "strict decoding requires a map to be read to the end";
+            Err(Error::custom(message))
+        }
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let done = Arc::new(AtomicBool::new(false));
+        let seq = StrictSeq {
+            seq,
+            len: self.len,
+            read: 0,
+            ended: false,
+            done: done.clone(),
+        };
+
+        let value = self.visitor.visit_seq(seq).await?;
+
+        if done.load(Ordering::Acquire) {
+            Ok(value)
+        } else {
+            let message = "strict decoding requires a sequence to be read to the end";
+            Err(Error::custom(message))
+        }
+    }
+);
+
+struct StrictMap<A> {
+    map: A,
+    done: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl<A: MapAccess> MapAccess for StrictMap<A> {
+    type Error = A::Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        self.next_key_as(KeyHint::Any, context).await
+    }
+
+    async fn next_key_as<K: FromStream>(
+        &mut self,
+        hint: KeyHint,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        let key: Option<Strict<K>> = self.map.next_key_as(hint, context).await?;
+        self.done.store(key.is_none(), Ordering::Release);
+        Ok(key.map(Strict::into_inner))
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        self.map
+            .next_value(context)
+            .map_ok(Strict::into_inner)
+            .await
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+struct StrictSeq<A> {
+    seq: A,
+    len: Option<usize>,
+    read: usize,
+    ended: bool,
+    done: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl<A: SeqAccess> SeqAccess for StrictSeq<A> {
+    type Error = A::Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        // the end of the inner sequence may already have been read by the check below
+        if self.ended {
+            return Ok(None);
+        }
+
+        let element: Option<Strict<T>> = self.seq.next_element(context).await?;
+
+        if element.is_some() {
+            self.read += 1;
+        } else {
+            self.ended = true;
+            self.done.store(true, Ordering::Release);
+        }
+
+        // a tuple visitor may stop reading once it has the expected number of elements,
+        // so check for trailing elements here rather than after the visitor returns
+        if element.is_some() && self.len == Some(self.read) {
+            if self.seq.next_element::<IgnoredAny>(()).await?.is_some() {
+                let message = "strict decoding does not allow trailing elements to be ignored";
+                return Err(Error::custom(message));
+            }
+
+            self.ended = true;
+            self.done.store(true, Ordering::Release);
+        }
+
+        Ok(element.map(Strict::into_inner))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
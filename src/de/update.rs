@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::primitive;
+use super::{ArrayAccess, Decoder, Error, FromStream, MapAccess, SeqAccess, Visitor};
+
+/// A data structure which can be updated in place from a stream, e.g. to apply a periodic delta
+/// to a long-lived value without reconstructing it or re-allocating its collections.
+///
+/// Implementations are provided for:
+///  - primitives and `String`, which are replaced
+///  - `Option<T>`, which is updated in place if it's already `Some` and the input is too
+///  - sequences and sets, which are extended with the decoded elements
+///  - maps, which are merged: the value of an existing key is updated in place, and any other
+///    entry is inserted
+#[async_trait]
+pub trait UpdateFromStream: FromStream {
+    /// Update this value in place using the given `context` and [`Decoder`].
+    async fn update_from_stream<D: Decoder>(
+        &mut self,
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<(), D::Error>;
+}
+
+macro_rules! update_replace {
+    ($($ty:ty),+) => {
+        $(
+            #[async_trait]
+            impl UpdateFromStream for $ty {
+                async fn update_from_stream<D: Decoder>(
+                    &mut self,
+                    context: (),
+                    decoder: &mut D,
+                ) -> Result<(), D::Error> {
+                    *self = Self::from_stream(context, decoder).await?;
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+update_replace!(bool, i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, String);
+
+struct OptionUpdate<'a, T: FromStream> {
+    target: &'a mut Option<T>,
+    context: T::Context,
+}
+
+#[async_trait]
+impl<'a, T: UpdateFromStream> Visitor for OptionUpdate<'a, T> {
+    type Value = ();
+
+    fn expecting() -> &'static str {
+        "an optional value, or none"
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        *self.target = None;
+        Ok(())
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        *self.target = None;
+        Ok(())
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        match self.target {
+            Some(value) => value.update_from_stream(self.context, decoder).await,
+            None => {
+                *self.target = Some(T::from_stream(self.context, decoder).await?);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T: UpdateFromStream> UpdateFromStream for Option<T> {
+    async fn update_from_stream<D: Decoder>(
+        &mut self,
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<(), D::Error> {
+        let visitor = OptionUpdate {
+            target: self,
+            context,
+        };
+
+        decoder.decode_option(visitor).await
+    }
+}
+
+struct ExtendVisitor<'a, C, T: FromStream> {
+    target: &'a mut C,
+    context: T::Context,
+}
+
+macro_rules! extend_visitor {
+    ($($visit_array:ident($elem:ty)),+) => {
+        #[async_trait]
+        impl<'a, C, T> Visitor for ExtendVisitor<'a, C, T>
+        where
+            C: Extend<T> + Send,
+            T: FromStream,
+            T::Context: Copy,
+        {
+            type Value = ();
+
+            fn expecting() -> &'static str {
+                "a sequence"
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while let Some(value) = seq.next_element(self.context).await? {
+                    self.target.extend(Some(value));
+                }
+
+                Ok(())
+            }
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let target = self.target;
+                    primitive::decode_elements(self.context, array, |value: T| {
+                        target.extend(Some(value));
+                    })
+                    .await
+                }
+            )+
+        }
+    };
+}
+
+extend_visitor!(
+    visit_array_bool(bool),
+    visit_array_i8(i8),
+    visit_array_i16(i16),
+    visit_array_i32(i32),
+    visit_array_i64(i64),
+    visit_array_u8(u8),
+    visit_array_u16(u16),
+    visit_array_u32(u32),
+    visit_array_u64(u64),
+    visit_array_f32(f32),
+    visit_array_f64(f64)
+);
+
+macro_rules! update_extend {
+    ($($ty:ident<T $(: $tbound1:ident $(+ $tbound2:ident)*)* $(, $typaram:ident : $bound1:ident $(+ $bound2:ident)*)*>),+) => {
+        $(
+            #[async_trait]
+            impl<T $(, $typaram)*> UpdateFromStream for $ty<T $(, $typaram)*>
+            where
+                T: FromStream $(+ $tbound1 $(+ $tbound2)*)*,
+                $($typaram: $bound1 $(+ $bound2)*,)*
+                T::Context: Copy,
+            {
+                async fn update_from_stream<D: Decoder>(
+                    &mut self,
+                    context: Self::Context,
+                    decoder: &mut D,
+                ) -> Result<(), D::Error> {
+                    let visitor = ExtendVisitor {
+                        target: self,
+                        context,
+                    };
+
+                    decoder.decode_seq(visitor).await
+                }
+            }
+        )+
+    };
+}
+
+update_extend!(
+    Vec<T>,
+    VecDeque<T>,
+    LinkedList<T>,
+    BinaryHeap<T: Ord>,
+    BTreeSet<T: Eq + Ord>,
+    HashSet<T: Eq + Hash, S: BuildHasher + Default + Send>
+);
+
+/// A map value decoded by updating an existing value in place.
+struct Update<'a, V> {
+    _target: PhantomData<&'a mut V>,
+}
+
+#[async_trait]
+impl<'a, V: UpdateFromStream<Context = ()>> FromStream for Update<'a, V> {
+    type Context = &'a mut V;
+
+    async fn from_stream<D: Decoder>(
+        target: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        target.update_from_stream((), decoder).await?;
+
+        Ok(Self {
+            _target: PhantomData,
+        })
+    }
+}
+
+macro_rules! update_merge {
+    ($($ty:ident<K $(: $kbound1:ident $(+ $kbound2:ident)*)*, V $(, $typaram:ident : $bound1:ident $(+ $bound2:ident)*)*>),+) => {
+        $(
+            #[async_trait]
+            impl<K, V $(, $typaram)*> UpdateFromStream for $ty<K, V $(, $typaram)*>
+            where
+                K: FromStream<Context = ()> $(+ $kbound1 $(+ $kbound2)*)*,
+                V: UpdateFromStream<Context = ()>,
+                $($typaram: $bound1 $(+ $bound2)*,)*
+            {
+                async fn update_from_stream<D: Decoder>(
+                    &mut self,
+                    _context: (),
+                    decoder: &mut D,
+                ) -> Result<(), D::Error> {
+                    struct MergeVisitor<'a, K, V $(, $typaram)*> {
+                        target: &'a mut $ty<K, V $(, $typaram)*>,
+                    }
+
+                    #[async_trait]
+                    impl<'a, K, V $(, $typaram)*> Visitor for MergeVisitor<'a, K, V $(, $typaram)*>
+                    where
+                        K: FromStream<Context = ()> $(+ $kbound1 $(+ $kbound2)*)*,
+                        V: UpdateFromStream<Context = ()>,
+                        $($typaram: $bound1 $(+ $bound2)*,)*
+                    {
+                        type Value = ();
+
+                        fn expecting() -> &'static str {
+                            concat!("a map, to merge into a ", stringify!($ty))
+                        }
+
+                        async fn visit_map<A: MapAccess>(
+                            self,
+                            mut map: A,
+                        ) -> Result<Self::Value, A::Error> {
+                            while let Some(key) = map.next_key::<K>(()).await? {
+                                match self.target.get_mut(&key) {
+                                    Some(value) => {
+                                        let _: Update<V> = map.next_value(value).await?;
+                                    }
+                                    None => {
+                                        let value = map.next_value(()).await?;
+                                        self.target.insert(key, value);
+                                    }
+                                }
+                            }
+
+                            Ok(())
+                        }
+                    }
+
+                    decoder.decode_map(MergeVisitor { target: self }).await
+                }
+            }
+        )+
+    };
+}
+
+update_merge!(
+    BTreeMap<K: Ord, V>,
+    HashMap<K: Eq + Hash, V, S: BuildHasher + Default + Send>
+);
@@ -0,0 +1,94 @@
+use std::ops::Deref;
+
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream, Pair, PairVisitor};
+
+const VERSION: &str = "version";
+const VALUE: &str = "value";
+
+/// A type which can be decoded from any of several versions of its encoding.
+///
+/// Implementations should match on the `version` given and decode an older representation
+/// (or the current one) from the `decoder`, converting it into the current type.
+#[async_trait]
+pub trait DecodeVersion: Send + Sized {
+    /// The decoding context of this type.
+    type Context: Send;
+
+    /// The type of the version tag, e.g. `u32` or `String`.
+    type Version: FromStream<Context = ()>;
+
+    /// Decode the given `version` of this type using the given `Decoder`.
+    async fn decode_version<D: Decoder>(
+        version: Self::Version,
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error>;
+}
+
+/// A value of type `T` preceded by a version tag, either as a map of the form
+/// `{"version": <version>, "value": <value>}` or as a tuple of the form `[<version>, <value>]`.
+///
+/// Decoding a `Versioned<T>` requires a self-describing format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Versioned<T> {
+    value: T,
+}
+
+impl<T> Versioned<T> {
+    /// Return the decoded value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[async_trait]
+impl<T: DecodeVersion> FromStream for Versioned<T> {
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let Payload { value } = decoder.decode_any(PairVisitor::new(context)).await?;
+        Ok(Self { value })
+    }
+}
+
+struct Payload<T> {
+    value: T,
+}
+
+#[async_trait]
+impl<T: DecodeVersion> FromStream for Payload<T> {
+    type Context = (T::Version, T::Context);
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let (version, context) = context;
+        let value = T::decode_version(version, context, decoder).await?;
+        Ok(Self { value })
+    }
+}
+
+impl<T: DecodeVersion> Pair for Payload<T> {
+    type First = T::Version;
+    type Outer = T::Context;
+
+    const KEYS: [&'static str; 2] = [VERSION, VALUE];
+
+    fn expecting() -> &'static str {
+        "a version tag followed by a value"
+    }
+}
@@ -0,0 +1,455 @@
+//! Reusable [`Visitor`]s for hand-written [`FromStream`] implementations.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+
+use super::{ArrayAccess, Decoder, Error, FromStream, MapAccess, SeqAccess, TextAccess, Visitor};
+
+/// A [`Visitor`] which collects the elements of a sequence into any collection `C`.
+pub struct SeqCollector<T: FromStream, C> {
+    context: T::Context,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<T: FromStream, C> SeqCollector<T, C> {
+    /// Construct a new [`SeqCollector`] which decodes each element with the given `context`.
+    pub fn new(context: T::Context) -> Self {
+        Self {
+            context,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, C> Visitor for SeqCollector<T, C>
+where
+    T: FromStream,
+    T::Context: Copy,
+    C: Default + Extend<T> + Send,
+{
+    type Value = C;
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut collection = C::default();
+
+        while let Some(element) = seq.next_element(self.context).await? {
+            collection.extend(Some(element));
+        }
+
+        Ok(collection)
+    }
+}
+
+/// A [`Visitor`] which collects the elements of a sequence into any collection `C`, decoding each
+/// element with a context derived from its index and the element before it, e.g. to decode each
+/// chunk of a delta-encoded sequence relative to the previous chunk.
+///
+/// Example:
+/// ```
+/// # use destream::de::visitors::SeqCollectorFn;
+/// # async fn decode<D: destream::Decoder>(decoder: &mut D) -> Result<Vec<u64>, D::Error> {
+/// // decode the sequence with the unit context, but check the index of each element
+/// let visitor = SeqCollectorFn::<u64, Vec<u64>, _>::new(|index, previous| {
+///     debug_assert_eq!(index == 0, previous.is_none());
+/// });
+///
+/// decoder.decode_seq(visitor).await
+/// # }
+/// ```
+pub struct SeqCollectorFn<T, C, F> {
+    context: F,
+    marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T, C, F> SeqCollectorFn<T, C, F>
+where
+    T: FromStream,
+    F: FnMut(usize, Option<&T>) -> T::Context,
+{
+    /// Construct a new [`SeqCollectorFn`] which calls `context` with the index of each element
+    /// and the previous element, if any, to construct the context to decode it with.
+    pub fn new(context: F) -> Self {
+        Self {
+            context,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, C, F> Visitor for SeqCollectorFn<T, C, F>
+where
+    T: FromStream,
+    C: Default + Extend<T> + Send,
+    F: FnMut(usize, Option<&T>) -> T::Context + Send,
+{
+    type Value = C;
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<A: SeqAccess>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut collection = C::default();
+        let mut previous = None;
+        let mut index = 0;
+
+        loop {
+            let context = (self.context)(index, previous.as_ref());
+
+            match seq.next_element(context).await? {
+                Some(element) => {
+                    collection.extend(previous.replace(element));
+                    index += 1;
+                }
+                None => break,
+            }
+        }
+
+        collection.extend(previous);
+        Ok(collection)
+    }
+}
+
+/// A [`Visitor`] which collects the entries of a map into any collection `C`,
+/// by default a `Vec` of key-value pairs in the order they were decoded.
+pub struct MapCollector<K: FromStream, V: FromStream, C = Vec<(K, V)>> {
+    key_context: K::Context,
+    value_context: V::Context,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<K: FromStream, V: FromStream, C> MapCollector<K, V, C> {
+    /// Construct a new [`MapCollector`] which decodes each key and value with the given contexts.
+    pub fn new(key_context: K::Context, value_context: V::Context) -> Self {
+        Self {
+            key_context,
+            value_context,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<K, V, C> Visitor for MapCollector<K, V, C>
+where
+    K: FromStream,
+    K::Context: Copy,
+    V: FromStream,
+    V::Context: Copy,
+    C: Default + Extend<(K, V)> + Send,
+{
+    type Value = C;
+
+    fn expecting() -> &'static str {
+        "a map"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut collection = C::default();
+
+        while let Some(key) = map.next_key(self.key_context).await? {
+            let value = map.next_value(self.value_context).await?;
+            collection.extend(Some((key, value)));
+        }
+
+        Ok(collection)
+    }
+}
+
+/// A [`Visitor`] which decodes a string and parses it using [`FromStr`].
+pub struct StringParser<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> StringParser<T> {
+    /// Construct a new [`StringParser`].
+    pub fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for StringParser<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Visitor for StringParser<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting() -> &'static str {
+        "a parseable string"
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        v.parse().map_err(|cause| {
+            Error::invalid_value(format_args!("{:?} ({})", v, cause), Self::expecting())
+        })
+    }
+}
+
+/// A primitive numeric type which can be converted from any other primitive number,
+/// if it can be represented exactly (or approximately, for floating-point types).
+pub trait Number: Sized {
+    /// Convert an `i64` into this type, if possible.
+    fn from_i64(v: i64) -> Option<Self>;
+
+    /// Convert a `u64` into this type, if possible.
+    fn from_u64(v: u64) -> Option<Self>;
+
+    /// Convert an `f64` into this type, if possible.
+    fn from_f64(v: f64) -> Option<Self>;
+}
+
+macro_rules! integer {
+    ($($ty:ty),+) => {
+        $(
+            impl Number for $ty {
+                fn from_i64(v: i64) -> Option<Self> {
+                    v.try_into().ok()
+                }
+
+                fn from_u64(v: u64) -> Option<Self> {
+                    v.try_into().ok()
+                }
+
+                fn from_f64(v: f64) -> Option<Self> {
+                    if v.is_finite() && v.fract() == 0. {
+                        // the cast saturates, and i128 can't overflow any smaller integer type
+                        (v as i128).try_into().ok()
+                    } else {
+                        None
+                    }
+                }
+            }
+        )+
+    };
+}
+
+integer!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+macro_rules! float {
+    ($($ty:ty),+) => {
+        $(
+            impl Number for $ty {
+                fn from_i64(v: i64) -> Option<Self> {
+                    Some(v as $ty)
+                }
+
+                fn from_u64(v: u64) -> Option<Self> {
+                    Some(v as $ty)
+                }
+
+                fn from_f64(v: f64) -> Option<Self> {
+                    Some(v as $ty)
+                }
+            }
+        )+
+    };
+}
+
+float!(f32, f64);
+
+/// A [`Visitor`] which accepts any primitive number which can be converted into a `T`,
+/// regardless of its encoded type.
+pub struct NumberVisitor<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> NumberVisitor<T> {
+    /// Construct a new [`NumberVisitor`].
+    pub fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for NumberVisitor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Number> Visitor for NumberVisitor<T> {
+    type Value = T;
+
+    fn expecting() -> &'static str {
+        "a number in range"
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        T::from_i64(v).ok_or_else(|| Error::invalid_value(v, Self::expecting()))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        T::from_u64(v).ok_or_else(|| Error::invalid_value(v, Self::expecting()))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        T::from_f64(v).ok_or_else(|| Error::invalid_value(v, Self::expecting()))
+    }
+}
+
+/// A [`Visitor`] adapter which converts the value produced by the given visitor `V`
+/// into a `U` using [`TryFrom`], raising [`Error::invalid_value`] if the conversion fails.
+///
+/// To decode a validated newtype from any [`FromStream`] type, see [`try_from_stream`].
+pub struct TryFromVisitor<V, U> {
+    visitor: V,
+    marker: PhantomData<fn() -> U>,
+}
+
+impl<V, U> TryFromVisitor<V, U> {
+    /// Wrap the given `visitor` so that its output is converted into a `U`.
+    pub fn new(visitor: V) -> Self {
+        Self {
+            visitor,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<V: Default, U> Default for TryFromVisitor<V, U> {
+    fn default() -> Self {
+        Self::new(V::default())
+    }
+}
+
+fn try_convert<T, U, E>(value: T, expected: &'static str) -> Result<U, E>
+where
+    U: TryFrom<T>,
+    U::Error: fmt::Display,
+    E: Error,
+{
+    U::try_from(value).map_err(|cause| E::invalid_value(cause, expected))
+}
+
+macro_rules! try_from_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array_kind:ident),+
+    ) => {
+        #[async_trait]
+        impl<V, U> Visitor for TryFromVisitor<V, U>
+        where
+            V: Visitor,
+            U: TryFrom<V::Value>,
+            U::Error: fmt::Display,
+        {
+            type Value = U;
+
+            fn expecting() -> &'static str {
+                V::expecting()
+            }
+
+            $(
+                fn $visit<E: Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    self.visitor.$visit(v).and_then(|v| try_convert(v, Self::expecting()))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let value = self.visitor.$visit_array(array).await?;
+                    try_convert(value, Self::expecting())
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                self.visitor.visit_string(v).and_then(|v| try_convert(v, Self::expecting()))
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                text: A,
+            ) -> Result<Self::Value, A::Error> {
+                let value = self.visitor.visit_text_stream(text).await?;
+                try_convert(value, Self::expecting())
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                self.visitor.visit_unit().and_then(|v| try_convert(v, Self::expecting()))
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                self.visitor.visit_none().and_then(|v| try_convert(v, Self::expecting()))
+            }
+
+            async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+                let value = self.visitor.visit_some(decoder).await?;
+                try_convert(value, Self::expecting())
+            }
+
+            async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+                let value = self.visitor.visit_map(map).await?;
+                try_convert(value, Self::expecting())
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+                let value = self.visitor.visit_seq(seq).await?;
+                try_convert(value, Self::expecting())
+            }
+        }
+    };
+}
+
+visit_methods!(try_from_visitor);
+
+/// Decode a `T` and convert it into a `U` using [`TryFrom`], raising
+/// [`Error::invalid_value`] with the conversion error if the conversion fails.
+///
+/// This allows a validated newtype to implement [`FromStream`] in one line:
+/// ```
+/// # use async_trait::async_trait;
+/// # use destream::de::{self, visitors::try_from_stream, Decoder, FromStream};
+/// struct Port(u16);
+///
+/// impl TryFrom<u64> for Port {
+///     type Error = String;
+///
+///     fn try_from(port: u64) -> Result<Self, String> {
+///         match u16::try_from(port) {
+///             Ok(port) if port > 0 => Ok(Port(port)),
+///             _ => Err(format!("invalid port {}", port)),
+///         }
+///     }
+/// }
+///
+/// #[async_trait]
+/// impl FromStream for Port {
+///     type Context = ();
+///
+///     async fn from_stream<D: Decoder>(cxt: (), decoder: &mut D) -> Result<Self, D::Error> {
+///         try_from_stream::<u64, _, _>(cxt, decoder).await
+///     }
+/// }
+/// ```
+pub async fn try_from_stream<T, U, D>(context: T::Context, decoder: &mut D) -> Result<U, D::Error>
+where
+    T: FromStream,
+    U: TryFrom<T>,
+    U::Error: fmt::Display,
+    D: Decoder,
+{
+    let value = T::from_stream(context, decoder).await?;
+    try_convert(value, std::any::type_name::<U>())
+}
@@ -0,0 +1,42 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+
+use super::{Decoder, FromStream};
+
+/// A data structure which can be decoded using a mutable reference to a shared context,
+/// such as a deduplication table, rather than a context passed by value.
+///
+/// See [`SeqAccess::next_element_with`](super::SeqAccess::next_element_with) and
+/// [`MapAccess::next_value_with`](super::MapAccess::next_value_with).
+#[async_trait]
+pub trait FromStreamWith<C: Send>: Send + Sized {
+    /// Decode this type using the given mutable `context` and [`Decoder`].
+    async fn from_stream_with<D: Decoder>(
+        context: &mut C,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error>;
+}
+
+/// A [`FromStreamWith`] type decoded as a [`FromStream`] type.
+pub(super) struct With<'a, T, C> {
+    pub value: T,
+    context: PhantomData<&'a mut C>,
+}
+
+#[async_trait]
+impl<'a, T: FromStreamWith<C>, C: Send> FromStream for With<'a, T, C> {
+    type Context = &'a mut C;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let value = T::from_stream_with(context, decoder).await?;
+
+        Ok(Self {
+            value,
+            context: PhantomData,
+        })
+    }
+}
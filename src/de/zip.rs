@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::sink::SinkExt;
+use futures::stream::Stream;
+
+use super::{Decoder, Driven, Error, FromStream, SeqAccess, Visitor};
+
+/// The number of pairs to buffer before pausing decoding.
+const PAIR_BUFFER: usize = 32;
+
+/// Decode two parallel sequences element-wise, one from each decoder, and return a [`Stream`]
+/// of pairs of elements, e.g. to re-join columnar input where ids and payloads arrive
+/// separately.
+///
+/// Yields an "invalid length" error if the sequences have different lengths.
+/// An error from `decoder_b` is converted into an error of `decoder_a`.
+pub fn zip_seqs<'a, A, B, DA, DB>(
+    context_a: A::Context,
+    context_b: B::Context,
+    decoder_a: &'a mut DA,
+    decoder_b: &'a mut DB,
+) -> impl Stream<Item = Result<(A, B), DA::Error>> + Send + 'a
+where
+    A: FromStream + 'a,
+    B: FromStream + 'a,
+    A::Context: Copy,
+    B::Context: Copy,
+    DA: Decoder + 'a,
+    DB: Decoder + 'a,
+{
+    let (pairs, receiver) = mpsc::channel(PAIR_BUFFER);
+
+    let visitor = OuterVisitor {
+        context_a,
+        context_b,
+        decoder_b,
+        pairs,
+        marker: PhantomData,
+    };
+
+    // the visitor (and therefore the sender) is dropped when decoding completes
+    Driven::new(receiver, decoder_a.decode_seq(visitor))
+}
+
+struct OuterVisitor<'a, A: FromStream, B: FromStream, DB> {
+    context_a: A::Context,
+    context_b: B::Context,
+    decoder_b: &'a mut DB,
+    pairs: mpsc::Sender<(A, B)>,
+    marker: PhantomData<fn() -> (A, B)>,
+}
+
+#[async_trait]
+impl<'a, A, B, DB> Visitor for OuterVisitor<'a, A, B, DB>
+where
+    A: FromStream,
+    B: FromStream,
+    A::Context: Copy,
+    B::Context: Copy,
+    DB: Decoder,
+{
+    type Value = ();
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<SA: SeqAccess>(self, seq_a: SA) -> Result<Self::Value, SA::Error> {
+        let visitor = InnerVisitor {
+            context_a: self.context_a,
+            context_b: self.context_b,
+            seq_a,
+            pairs: self.pairs,
+            marker: PhantomData,
+        };
+
+        self.decoder_b
+            .decode_seq(visitor)
+            .await
+            .map_err(SA::Error::custom)?
+    }
+}
+
+struct InnerVisitor<A: FromStream, B: FromStream, SA> {
+    context_a: A::Context,
+    context_b: B::Context,
+    seq_a: SA,
+    pairs: mpsc::Sender<(A, B)>,
+    marker: PhantomData<fn() -> (A, B)>,
+}
+
+#[async_trait]
+impl<A, B, SA> Visitor for InnerVisitor<A, B, SA>
+where
+    A: FromStream,
+    B: FromStream,
+    A::Context: Copy,
+    B::Context: Copy,
+    SA: SeqAccess,
+{
+    type Value = Result<(), SA::Error>;
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<SB: SeqAccess>(mut self, mut seq_b: SB) -> Result<Self::Value, SB::Error> {
+        let mut len = 0;
+
+        loop {
+            let a = match self.seq_a.next_element(self.context_a).await {
+                Ok(a) => a,
+                Err(cause) => return Ok(Err(cause)),
+            };
+
+            let b = seq_b.next_element(self.context_b).await?;
+
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    if self.pairs.send((a, b)).await.is_err() {
+                        // the receiver was dropped, so nobody is listening for further pairs
+                        break Ok(Ok(()));
+                    }
+                }
+                (None, None) => break Ok(Ok(())),
+                (Some(_), None) => {
+                    break Err(Error::invalid_length(
+                        len,
+                        "two sequences of the same length",
+                    ))
+                }
+                (None, Some(_)) => {
+                    break Ok(Err(Error::invalid_length(
+                        len,
+                        "two sequences of the same length",
+                    )));
+                }
+            }
+
+            len += 1;
+        }
+    }
+}
@@ -0,0 +1,661 @@
+//! Compare two self-describing streams structurally, without decoding either one in full.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::future::TryFutureExt;
+use futures::sink::SinkExt;
+use futures::stream::Stream;
+
+use crate::de::{
+    ArrayAccess, Decoder, Driven, Error, FromStream, MapAccess, SeqAccess, TextAccess, Visitor,
+};
+use crate::value::{Value, ValueVisitor};
+
+/// The number of [`DiffEvent`]s to buffer before pausing the comparison.
+const EVENT_BUFFER: usize = 32;
+
+/// One step of the path to a [`DiffEvent`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// The key of a map entry.
+    Key(Value),
+    /// The index of a sequence element.
+    Index(usize),
+    /// The value inside a `Some`.
+    Some,
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(Value::String(key)) => write!(f, ".{}", key),
+            Self::Key(key) => write!(f, "[{:?}]", key),
+            Self::Index(i) => write!(f, "[{}]", i),
+            Self::Some => f.write_str("?"),
+        }
+    }
+}
+
+/// A difference between two streams found by [`diff`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffEvent {
+    /// The path from the root of both streams to the differing values.
+    pub path: Vec<PathSegment>,
+    /// The value in the left stream, or `None` if it has no value at this path.
+    pub left: Option<Value>,
+    /// The value in the right stream, or `None` if it has no value at this path.
+    pub right: Option<Value>,
+}
+
+/// Compare the streams decoded by `left` and `right`, and return a [`Stream`] of the
+/// differences between them.
+///
+/// Maps and sequences are compared in lockstep, so only values which differ are held in
+/// memory. Map entries are matched by position: an entry whose key differs from the key of the
+/// entry at the same position in the other map is reported as a removal and an addition.
+///
+/// A `Some` is compared as its own node, whose contents have the path segment
+/// [`PathSegment::Some`], so `Some(x)` does not compare equal to `x`.
+///
+/// An error decoding `right` is reported as an error decoding `left`.
+pub fn diff<'a, L, R>(
+    left: &'a mut L,
+    right: &'a mut R,
+) -> impl Stream<Item = Result<DiffEvent, L::Error>> + Send + 'a
+where
+    L: Decoder + 'a,
+    R: Decoder + 'a,
+{
+    let (events, receiver) = mpsc::channel(EVENT_BUFFER);
+    let cursor = Cursor {
+        path: Vec::new(),
+        events,
+    };
+
+    let comparison = async move {
+        let mut right = DecoderRight(right);
+        LeftNode::<DecoderRight<R>>::from_stream((&mut right, cursor), left)
+            .map_ok(|_| ())
+            .await
+    };
+
+    // the cursor (and therefore the sender) is dropped when the comparison completes
+    Driven::new(receiver, comparison)
+}
+
+/// The current position in both streams.
+#[derive(Clone)]
+struct Cursor {
+    path: Vec<PathSegment>,
+    events: mpsc::Sender<DiffEvent>,
+}
+
+impl Cursor {
+    fn child(&self, segment: PathSegment) -> Self {
+        let mut path = self.path.clone();
+        path.push(segment);
+
+        Self {
+            path,
+            events: self.events.clone(),
+        }
+    }
+
+    async fn emit(&mut self, left: Option<Value>, right: Option<Value>) {
+        if left != right {
+            let event = DiffEvent {
+                path: self.path.clone(),
+                left,
+                right,
+            };
+
+            // if the receiver was dropped, nobody is listening for further events
+            let _ = self.events.send(event).await;
+        }
+    }
+}
+
+/// Decode the rest of the given `map` as a [`Value`].
+async fn drain_map<A: MapAccess>(map: &mut A) -> Result<Value, A::Error> {
+    let mut entries = Vec::new();
+
+    while let Some(key) = map.next_key::<Value>(()).await? {
+        let value = map.next_value(()).await?;
+        entries.push((key, value));
+    }
+
+    Ok(entries.into())
+}
+
+/// Decode the rest of the given `seq` as a [`Value`].
+async fn drain_seq<A: SeqAccess>(seq: &mut A) -> Result<Value, A::Error> {
+    let mut elements = Vec::new();
+
+    while let Some(element) = seq.next_element::<Value>(()).await? {
+        elements.push(element);
+    }
+
+    Ok(elements.into())
+}
+
+/// The source of the right-hand value to compare with the value being decoded on the left.
+#[async_trait]
+trait Right: Send {
+    type Error: Error;
+
+    /// Decode the next value, or return `None` if there is none at this position.
+    async fn value<T: FromStream>(&mut self, context: T::Context)
+        -> Result<Option<T>, Self::Error>;
+}
+
+struct DecoderRight<'a, D>(&'a mut D);
+
+#[async_trait]
+impl<'a, D: Decoder> Right for DecoderRight<'a, D> {
+    type Error = D::Error;
+
+    async fn value<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        T::from_stream(context, self.0).await.map(Some)
+    }
+}
+
+struct MapRight<'a, A>(&'a mut A);
+
+#[async_trait]
+impl<'a, A: MapAccess> Right for MapRight<'a, A> {
+    type Error = A::Error;
+
+    async fn value<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        self.0.next_value(context).await.map(Some)
+    }
+}
+
+struct SeqRight<'a, A> {
+    seq: &'a mut A,
+    done: bool,
+}
+
+#[async_trait]
+impl<'a, A: SeqAccess> Right for SeqRight<'a, A> {
+    type Error = A::Error;
+
+    async fn value<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let element = self.seq.next_element(context).await?;
+        self.done = element.is_none();
+        Ok(element)
+    }
+}
+
+// An error decoding the left stream is returned inside the `Ok` value of the right stream,
+// so that it reaches the caller with its original type.
+macro_rules! try_left {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(cause) => return Ok(Err(cause)),
+        }
+    };
+}
+
+/// A value on the left, partially decoded, to compare with a value on the right.
+#[async_trait]
+trait Left: Send + Sized {
+    type Error: Error;
+
+    /// Decode the rest of this value as a [`Value`].
+    async fn into_value(self) -> Result<Value, Self::Error>;
+
+    /// Compare this value with the given `right` leaf value, or with no value.
+    async fn compare(self, right: Option<Value>, mut cursor: Cursor) -> Result<(), Self::Error> {
+        let left = self.into_value().await?;
+        cursor.emit(Some(left), right).await;
+        Ok(())
+    }
+
+    /// Compare this value with the given `map` on the right.
+    async fn diff_map<A: MapAccess>(
+        self,
+        mut map: A,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, A::Error> {
+        let right = drain_map(&mut map).await?;
+        Ok(self.compare(Some(right), cursor).await)
+    }
+
+    /// Compare this value with the given `seq` on the right.
+    async fn diff_seq<A: SeqAccess>(
+        self,
+        mut seq: A,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, A::Error> {
+        let right = drain_seq(&mut seq).await?;
+        Ok(self.compare(Some(right), cursor).await)
+    }
+
+    /// Compare this value with the contents of a `Some` on the right, decoded by `right`.
+    async fn diff_some<D: Decoder>(
+        self,
+        right: &mut D,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, D::Error> {
+        let right = Value::from_stream((), right).await?;
+        try_left!(self.compare(None, cursor.clone()).await);
+
+        let mut cursor = cursor.child(PathSegment::Some);
+        cursor.emit(None, Some(right)).await;
+        Ok(Ok(()))
+    }
+}
+
+/// A leaf value on the left, already decoded.
+struct LeafLeft<E> {
+    value: Value,
+    error: PhantomData<E>,
+}
+
+impl<E> LeafLeft<E> {
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            error: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Error> Left for LeafLeft<E> {
+    type Error = E;
+
+    async fn into_value(self) -> Result<Value, Self::Error> {
+        Ok(self.value)
+    }
+}
+
+/// The contents of a `Some` on the left, not yet decoded.
+struct SomeLeft<'a, D>(&'a mut D);
+
+#[async_trait]
+impl<'a, L: Decoder> Left for SomeLeft<'a, L> {
+    type Error = L::Error;
+
+    async fn into_value(self) -> Result<Value, Self::Error> {
+        Value::from_stream((), self.0).await
+    }
+
+    async fn compare(self, right: Option<Value>, cursor: Cursor) -> Result<(), Self::Error> {
+        let left = self.into_value().await?;
+        cursor.child(PathSegment::Some).emit(Some(left), None).await;
+
+        if right.is_some() {
+            let mut cursor = cursor;
+            cursor.emit(None, right).await;
+        }
+
+        Ok(())
+    }
+
+    async fn diff_some<D: Decoder>(
+        self,
+        right: &mut D,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, D::Error> {
+        let mut right = DecoderRight(right);
+        let context = (&mut right, cursor.child(PathSegment::Some));
+        let left = LeftNode::<DecoderRight<D>>::from_stream(context, self.0).await;
+        Ok(left.map(|_| ()))
+    }
+}
+
+struct MapLeft<'a, A>(&'a mut A);
+
+#[async_trait]
+impl<'a, L: MapAccess> Left for MapLeft<'a, L> {
+    type Error = L::Error;
+
+    async fn into_value(self) -> Result<Value, Self::Error> {
+        drain_map(self.0).await
+    }
+
+    async fn diff_map<A: MapAccess>(
+        self,
+        mut map: A,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, A::Error> {
+        let left = self.0;
+
+        loop {
+            let left_key = try_left!(left.next_key::<Value>(()).await);
+            let right_key = map.next_key::<Value>(()).await?;
+
+            match (left_key, right_key) {
+                (None, None) => break Ok(Ok(())),
+                (Some(left_key), Some(right_key)) if left_key == right_key => {
+                    let mut right = MapRight(&mut map);
+                    let context = (&mut right, cursor.child(PathSegment::Key(left_key)));
+                    let _: LeftNode<MapRight<A>> = try_left!(left.next_value(context).await);
+                }
+                (left_key, right_key) => {
+                    if let Some(key) = left_key {
+                        let value = try_left!(left.next_value(()).await);
+                        let mut cursor = cursor.child(PathSegment::Key(key));
+                        cursor.emit(Some(value), None).await;
+                    }
+
+                    if let Some(key) = right_key {
+                        let value = map.next_value(()).await?;
+                        let mut cursor = cursor.child(PathSegment::Key(key));
+                        cursor.emit(None, Some(value)).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct SeqLeft<'a, A>(&'a mut A);
+
+#[async_trait]
+impl<'a, L: SeqAccess> Left for SeqLeft<'a, L> {
+    type Error = L::Error;
+
+    async fn into_value(self) -> Result<Value, Self::Error> {
+        drain_seq(self.0).await
+    }
+
+    async fn diff_seq<A: SeqAccess>(
+        self,
+        mut seq: A,
+        cursor: Cursor,
+    ) -> Result<Result<(), Self::Error>, A::Error> {
+        let left = self.0;
+        let mut right = SeqRight {
+            seq: &mut seq,
+            done: false,
+        };
+
+        let mut i = 0;
+        loop {
+            let context = (&mut right, cursor.child(PathSegment::Index(i)));
+            let element: Option<LeftNode<SeqRight<A>>> =
+                try_left!(left.next_element(context).await);
+
+            if element.is_none() {
+                break;
+            }
+
+            i += 1;
+        }
+
+        // the left sequence is exhausted, so any remaining elements on the right are additions
+        while !right.done {
+            match right.seq.next_element::<Value>(()).await? {
+                Some(value) => {
+                    let mut cursor = cursor.child(PathSegment::Index(i));
+                    cursor.emit(None, Some(value)).await;
+                    i += 1;
+                }
+                None => right.done = true,
+            }
+        }
+
+        Ok(Ok(()))
+    }
+}
+
+/// A value on the left, decoded by comparing it with the next value from the given [`Right`].
+struct LeftNode<'a, R> {
+    right: PhantomData<&'a mut R>,
+}
+
+#[async_trait]
+impl<'a, R: Right> FromStream for LeftNode<'a, R> {
+    type Context = (&'a mut R, Cursor);
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let (right, mut cursor) = context;
+
+        let visitor = LeftVisitor {
+            right: &mut *right,
+            cursor: cursor.clone(),
+        };
+
+        if let Some(left) = decoder.decode_any(visitor).await? {
+            let context = (LeafLeft::<D::Error>::new(left.clone()), cursor.clone());
+            let node: Option<RightNode<LeafLeft<D::Error>>> =
+                right.value(context).await.map_err(Error::custom)?;
+
+            match node {
+                Some(node) => node.result?,
+                None => cursor.emit(Some(left), None).await,
+            }
+        }
+
+        Ok(Self { right: PhantomData })
+    }
+}
+
+/// A value on the right, decoded by comparing it with the given [`Left`] value.
+///
+/// An error decoding the left value is returned in `result`, rather than converted into an
+/// error decoding the right value.
+struct RightNode<L: Left> {
+    result: Result<(), L::Error>,
+}
+
+#[async_trait]
+impl<L: Left> FromStream for RightNode<L> {
+    type Context = (L, Cursor);
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let (left, cursor) = context;
+
+        let visitor = RightVisitor {
+            left,
+            cursor: cursor.clone(),
+        };
+
+        let result = match decoder.decode_any(visitor).await? {
+            Ok(Some((left, right))) => left.compare(Some(right), cursor).await,
+            Ok(None) => Ok(()),
+            Err(cause) => Err(cause),
+        };
+
+        Ok(Self { result })
+    }
+}
+
+// A leaf value is returned (by `leaf`) so that it can be compared after the visitor returns,
+// since the primitive visit methods are not async.
+macro_rules! diff_visitor {
+    (
+        [$($generics:tt)*] $visitor:ty;
+        $($visit:ident($ty:ty)),+;
+        $($visit_array:ident($elem:ty)),+;
+        $($extra:tt)*
+    ) => {
+        #[async_trait]
+        impl $($generics)* Visitor for $visitor {
+            type Value = <Self as Leaf>::Value;
+
+            fn expecting() -> &'static str {
+                "any value"
+            }
+
+            $(
+                fn $visit<E: Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    ValueVisitor.$visit(v).map(|leaf| self.leaf(leaf))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let leaf = ValueVisitor.$visit_array(array).await?;
+                    Ok(self.leaf(leaf))
+                }
+            )+
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(self.leaf(v.into()))
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                text: A,
+            ) -> Result<Self::Value, A::Error> {
+                let leaf = ValueVisitor.visit_text_stream(text).await?;
+                Ok(self.leaf(leaf))
+            }
+
+            fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(self.leaf(Value::Unit))
+            }
+
+            fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+                Ok(self.leaf(Value::None))
+            }
+
+            $($extra)*
+        }
+    };
+}
+
+/// A visitor which returns a leaf value to be compared by its caller.
+trait Leaf {
+    type Value;
+
+    fn leaf(self, value: Value) -> Self::Value;
+}
+
+struct LeftVisitor<'a, R> {
+    right: &'a mut R,
+    cursor: Cursor,
+}
+
+impl<'a, R> Leaf for LeftVisitor<'a, R> {
+    type Value = Option<Value>;
+
+    fn leaf(self, value: Value) -> Self::Value {
+        Some(value)
+    }
+}
+
+macro_rules! left_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array_kind:ident),+
+    ) => {
+        diff_visitor!(
+            [<'a, R: Right>] LeftVisitor<'a, R>;
+            $($visit($ty)),+;
+            $($visit_array($elem)),+;
+
+            async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+                let context = (SomeLeft(&mut *decoder), self.cursor.clone());
+                let right: Option<RightNode<SomeLeft<D>>> =
+                    self.right.value(context).await.map_err(Error::custom)?;
+
+                match right {
+                    Some(right) => right.result?,
+                    None => SomeLeft(decoder).compare(None, self.cursor).await?,
+                }
+
+                Ok(None)
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let context = (MapLeft(&mut map), self.cursor.clone());
+                let right: Option<RightNode<MapLeft<A>>> =
+                    self.right.value(context).await.map_err(Error::custom)?;
+
+                match right {
+                    Some(right) => right.result?,
+                    None => MapLeft(&mut map).compare(None, self.cursor).await?,
+                }
+
+                Ok(None)
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let context = (SeqLeft(&mut seq), self.cursor.clone());
+                let right: Option<RightNode<SeqLeft<A>>> =
+                    self.right.value(context).await.map_err(Error::custom)?;
+
+                match right {
+                    Some(right) => right.result?,
+                    None => SeqLeft(&mut seq).compare(None, self.cursor).await?,
+                }
+
+                Ok(None)
+            }
+        );
+    };
+}
+
+struct RightVisitor<L> {
+    left: L,
+    cursor: Cursor,
+}
+
+impl<L: Left> Leaf for RightVisitor<L> {
+    type Value = Result<Option<(L, Value)>, L::Error>;
+
+    fn leaf(self, value: Value) -> Self::Value {
+        Ok(Some((self.left, value)))
+    }
+}
+
+macro_rules! right_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array_kind:ident),+
+    ) => {
+        diff_visitor!(
+            [<L: Left>] RightVisitor<L>;
+            $($visit($ty)),+;
+            $($visit_array($elem)),+;
+
+            async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+                let result = self.left.diff_some(decoder, self.cursor).await?;
+                Ok(result.map(|()| None))
+            }
+
+            async fn visit_map<A: MapAccess>(self, map: A) -> Result<Self::Value, A::Error> {
+                let result = self.left.diff_map(map, self.cursor).await?;
+                Ok(result.map(|()| None))
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, seq: A) -> Result<Self::Value, A::Error> {
+                let result = self.left.diff_seq(seq, self.cursor).await?;
+                Ok(result.map(|()| None))
+            }
+        );
+    };
+}
+
+crate::de::visit_methods!(left_visitor);
+crate::de::visit_methods!(right_visitor);
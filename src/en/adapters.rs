@@ -0,0 +1,463 @@
+//! Adapters which interpose on an [`Encoder`] or on its encoded output.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use uuid::Uuid;
+
+use super::{EncodeMap, EncodeSeq, EncodeStyle, EncodeTuple, Encoder, FloatFormat, IntoStream};
+
+/// An event observed by an [`Inspect`] encoder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Str(&'a str),
+    /// A binary value, with its length in bytes.
+    Bytes(usize),
+    Uuid(Uuid),
+    Unit,
+    None,
+    Some,
+    /// A typed array, with the name of its element type.
+    Array(&'static str),
+    /// The start of a map, with its length, if known.
+    Map(Option<usize>),
+    /// The start of a sequence, with its length, if known.
+    Seq(Option<usize>),
+    /// The start of a tuple, with its length.
+    Tuple(usize),
+    /// The end of a map, sequence, or tuple.
+    End,
+}
+
+/// An [`Encoder`] which calls a function with each [`Event`] passed to the underlying encoder,
+/// recursively, e.g. to log or count the values encoded.
+///
+/// The contents of map and sequence streams are observed as they are encoded, i.e. as the
+/// output stream is polled.
+pub struct Inspect<E, F> {
+    encoder: E,
+    inspect: Arc<F>,
+}
+
+impl<E, F> Inspect<E, F> {
+    /// Wrap the given `encoder` so that `inspect` is called with each [`Event`].
+    pub fn new(encoder: E, inspect: F) -> Self {
+        Self {
+            encoder,
+            inspect: Arc::new(inspect),
+        }
+    }
+}
+
+/// A value which will be encoded with an inspection function, recursively.
+pub struct Inspected<T, F> {
+    value: T,
+    inspect: Arc<F>,
+}
+
+impl<T, F> Inspected<T, F> {
+    /// Call `inspect` with each [`Event`] when encoding `value`.
+    pub fn new(value: T, inspect: F) -> Self {
+        Self {
+            value,
+            inspect: Arc::new(inspect),
+        }
+    }
+
+    fn shared(value: T, inspect: &Arc<F>) -> Self {
+        Self {
+            value,
+            inspect: inspect.clone(),
+        }
+    }
+
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'en, T, F> IntoStream<'en> for Inspected<T, F>
+where
+    T: IntoStream<'en> + 'en,
+    F: Fn(Event<'_>) + Send + Sync + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let encoder = Inspect {
+            encoder,
+            inspect: self.inspect,
+        };
+
+        self.value.into_stream(encoder)
+    }
+}
+
+/// Returned from `Inspect::encode_map`.
+pub struct InspectMap<M, F> {
+    map: M,
+    inspect: Arc<F>,
+}
+
+impl<'en, M, F> EncodeMap<'en> for InspectMap<M, F>
+where
+    M: EncodeMap<'en>,
+    F: Fn(Event<'_>) + Send + Sync + 'en,
+{
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        self.map.encode_key(Inspected::shared(key, &self.inspect))
+    }
+
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        (self.inspect)(Event::Str(key));
+        self.map.encode_key_str(key)
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        self.map
+            .encode_value(Inspected::shared(value, &self.inspect))
+    }
+
+    fn encode_entry<K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Self::Error> {
+        let key = Inspected::shared(key, &self.inspect);
+        let value = Inspected::shared(value, &self.inspect);
+        self.map.encode_entry(key, value)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::End);
+        self.map.end()
+    }
+}
+
+/// Returned from `Inspect::encode_seq` and `Inspect::encode_tuple`.
+pub struct InspectSeq<S, F> {
+    seq: S,
+    inspect: Arc<F>,
+}
+
+impl<'en, S, F> EncodeSeq<'en> for InspectSeq<S, F>
+where
+    S: EncodeSeq<'en>,
+    F: Fn(Event<'_>) + Send + Sync + 'en,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq
+            .encode_element(Inspected::shared(value, &self.inspect))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.seq.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::End);
+        self.seq.end()
+    }
+}
+
+impl<'en, S, F> EncodeTuple<'en> for InspectSeq<S, F>
+where
+    S: EncodeTuple<'en>,
+    F: Fn(Event<'_>) + Send + Sync + 'en,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq
+            .encode_element(Inspected::shared(value, &self.inspect))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::End);
+        self.seq.end()
+    }
+}
+
+macro_rules! inspect_encode {
+    ($this:ident, $method:ident, $chunks:ident: [$ty:ty] $($rest:tt)*) => {{
+        ($this.inspect)(Event::Array(stringify!($ty)));
+        $this.encoder.$method($chunks)
+    }};
+    ($this:ident, $method:ident, $value:ident: $ty:ty => $kind:ident $($rest:tt)*) => {{
+        ($this.inspect)(Event::$kind($value));
+        $this.encoder.$method($value)
+    }};
+}
+
+impl<'en, E, F> Encoder<'en> for Inspect<E, F>
+where
+    E: Encoder<'en>,
+    F: Fn(Event<'_>) + Send + Sync + 'en,
+{
+    type Ok = E::Ok;
+    type Error = E::Error;
+    type EncodeMap = InspectMap<E::EncodeMap, F>;
+    type EncodeSeq = InspectSeq<E::EncodeSeq, F>;
+    type EncodeTuple = InspectSeq<E::EncodeTuple, F>;
+
+    encode_primitives!(inspect_encode);
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::None);
+        self.encoder.encode_none()
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::Some);
+        self.encoder
+            .encode_some(Inspected::shared(value, &self.inspect))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::Unit);
+        self.encoder.encode_unit()
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        let bytes = bytes.into();
+        (self.inspect)(Event::Bytes(bytes.len()));
+        self.encoder.encode_bytes(bytes)
+    }
+
+    fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        (self.inspect)(Event::Map(len));
+
+        Ok(InspectMap {
+            map: self.encoder.encode_map(len)?,
+            inspect: self.inspect,
+        })
+    }
+
+    fn encode_map_stream<K, V, S>(self, map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        (self.inspect)(Event::Map(None));
+
+        let inspect = self.inspect;
+        let map = map.map(move |(key, value)| {
+            let key = Inspected::shared(key, &inspect);
+            let value = Inspected::shared(value, &inspect);
+            (key, value)
+        });
+
+        self.encoder.encode_map_stream(map)
+    }
+
+    fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        (self.inspect)(Event::Seq(len));
+
+        Ok(InspectSeq {
+            seq: self.encoder.encode_seq(len)?,
+            inspect: self.inspect,
+        })
+    }
+
+    fn encode_seq_stream<T, S>(self, seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        (self.inspect)(Event::Seq(None));
+
+        let inspect = self.inspect;
+        let seq = seq.map(move |value| Inspected::shared(value, &inspect));
+        self.encoder.encode_seq_stream(seq)
+    }
+
+    fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        (self.inspect)(Event::Tuple(len));
+
+        Ok(InspectSeq {
+            seq: self.encoder.encode_tuple(len)?,
+            inspect: self.inspect,
+        })
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        (self.inspect)(Event::Bytes(bytes.len()));
+        self.encoder.encode_bytes(bytes)
+    }
+
+    fn collect_f32_with(self, v: f32, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::F32(v));
+        self.encoder.collect_f32_with(v, format)
+    }
+
+    fn collect_f64_with(self, v: f64, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        (self.inspect)(Event::F64(v));
+        self.encoder.collect_f64_with(v, format)
+    }
+
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.encoder
+            .encode_memoized(key, Inspected::shared(value, &self.inspect))
+    }
+
+    fn style(&self) -> EncodeStyle {
+        self.encoder.style()
+    }
+
+    fn depth(&self) -> usize {
+        self.encoder.depth()
+    }
+
+    fn with_style(self, style: EncodeStyle) -> Self {
+        Self {
+            encoder: self.encoder.with_style(style),
+            ..self
+        }
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        let value = value.to_string();
+        (self.inspect)(Event::Str(&value));
+        self.encoder.encode_str(&value)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+struct Shared<S: Stream> {
+    source: S,
+    done: bool,
+    buffers: [VecDeque<S::Item>; 2],
+    wakers: [Option<Waker>; 2],
+    dropped: [bool; 2],
+}
+
+/// One of the two outputs of [`tee`].
+pub struct Tee<S: Stream> {
+    shared: Arc<Mutex<Shared<S>>>,
+    side: usize,
+}
+
+/// Split an encoded `output` stream into two streams which each yield every item of `output`,
+/// e.g. to write an encoded value to disk while also sending it in a response.
+///
+/// The value is only encoded once. Items are buffered until both outputs have yielded them,
+/// so if one output is polled much less often than the other, its buffer will grow.
+pub fn tee<S: Stream>(output: S) -> (Tee<S>, Tee<S>)
+where
+    S::Item: Clone,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        source: output,
+        done: false,
+        buffers: [VecDeque::new(), VecDeque::new()],
+        wakers: [None, None],
+        dropped: [false, false],
+    }));
+
+    let left = Tee {
+        shared: shared.clone(),
+        side: 0,
+    };
+
+    let right = Tee { shared, side: 1 };
+
+    (left, right)
+}
+
+impl<S: Stream> Tee<S> {
+    fn lock(&self) -> MutexGuard<'_, Shared<S>> {
+        match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<S> Stream for Tee<S>
+where
+    S: Stream + Unpin,
+    S::Item: Clone,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let side = self.side;
+        let other = 1 - side;
+        let mut shared = self.lock();
+
+        if let Some(item) = shared.buffers[side].pop_front() {
+            return Poll::Ready(Some(item));
+        } else if shared.done {
+            return Poll::Ready(None);
+        }
+
+        match shared.source.poll_next_unpin(cxt) {
+            Poll::Pending => {
+                shared.wakers[side] = Some(cxt.waker().clone());
+                Poll::Pending
+            }
+            Poll::Ready(item) => {
+                match &item {
+                    Some(item) if !shared.dropped[other] => {
+                        shared.buffers[other].push_back(item.clone())
+                    }
+                    Some(_) => {}
+                    None => shared.done = true,
+                }
+
+                if let Some(waker) = shared.wakers[other].take() {
+                    waker.wake();
+                }
+
+                Poll::Ready(item)
+            }
+        }
+    }
+}
+
+impl<S: Stream> Drop for Tee<S> {
+    fn drop(&mut self) {
+        let side = self.side;
+        let mut shared = self.lock();
+        shared.dropped[side] = true;
+        shared.buffers[side].clear();
+
+        // the other side may be waiting on a wakeup which was registered by this side
+        if let Some(waker) = shared.wakers[1 - side].take() {
+            waker.wake();
+        }
+    }
+}
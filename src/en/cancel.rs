@@ -0,0 +1,73 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::FutureExt;
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+
+use super::Error;
+
+/// Stop polling the given encoded `output` stream when the given `signal` completes
+/// (e.g. when a client disconnects), and yield a terminal "encoding cancelled" error instead.
+///
+/// The `signal` must be `Unpin`; use `Box::pin` if necessary.
+pub fn with_cancellation<S, F>(output: S, signal: F) -> Cancel<S, F> {
+    Cancel {
+        output: Some(output),
+        signal,
+        reason: "encoding cancelled",
+    }
+}
+
+/// Stop polling the given encoded `output` stream when the given `timer` completes
+/// (e.g. a sleep future from the application's runtime), and yield a terminal
+/// "encoding deadline exceeded" error instead.
+///
+/// The `timer` must be `Unpin`; use `Box::pin` if necessary.
+pub fn with_deadline<S, F>(output: S, timer: F) -> Cancel<S, F> {
+    Cancel {
+        output: Some(output),
+        signal: timer,
+        reason: "encoding deadline exceeded",
+    }
+}
+
+/// An encoded output stream which can be cut off by a signal.
+/// Returned by [`with_cancellation`] and [`with_deadline`].
+pub struct Cancel<S, F> {
+    output: Option<S>,
+    signal: F,
+    reason: &'static str,
+}
+
+impl<T, X, S, F> Stream for Cancel<S, F>
+where
+    S: Stream<Item = Result<T, X>> + Unpin,
+    X: Error,
+    F: Future + Unpin,
+{
+    type Item = Result<T, X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.output.is_none() {
+            return Poll::Ready(None);
+        }
+
+        if self.signal.poll_unpin(cxt).is_ready() {
+            self.output = None;
+            return Poll::Ready(Some(Err(X::custom(self.reason))));
+        }
+
+        let item = match self.output.as_mut() {
+            Some(output) => ready!(output.poll_next_unpin(cxt)),
+            None => None,
+        };
+
+        if item.is_none() {
+            self.output = None;
+        }
+
+        Poll::Ready(item)
+    }
+}
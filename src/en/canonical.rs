@@ -0,0 +1,502 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+use uuid::Uuid;
+
+use super::key::Key;
+use super::{EncodeMap, EncodeSeq, EncodeStyle, EncodeTuple, Encoder, Error, IntoStream, ToStream};
+
+/// A data structure whose encoding is deterministic when encoded with a [`Canonical`] encoder,
+/// i.e. any two equal values produce an identical stream.
+///
+/// This is the case for types whose map entries are always encoded in ascending order of their
+/// keys, so it is implemented for `BTreeMap` and `BTreeSet` but not `HashMap` or `HashSet`.
+/// The keys of a `BTreeMap` must also implement [`CanonicalKey`].
+///
+/// Use [`canonical`] to encode a value whose type guarantees a canonical encoding.
+pub trait CanonicalForm<'en>: ToStream<'en> {}
+
+macro_rules! canonical_form {
+    ($($ty:ty),+) => {
+        $(
+            impl<'en> CanonicalForm<'en> for $ty {}
+        )+
+    };
+}
+
+canonical_form!(
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    f32,
+    f64,
+    String,
+    Bytes,
+    Uuid,
+    ()
+);
+
+impl<'a, 'en> CanonicalForm<'en> for &'a str where 'a: 'en {}
+
+/// A map key whose encoding is a primitive (non-float) value, ordered the same way as its
+/// canonical encoding, so that a `BTreeMap` with keys of this type is always encoded in
+/// canonical order.
+pub trait CanonicalKey<'en>: CanonicalForm<'en> + Ord {}
+
+macro_rules! canonical_key {
+    ($($ty:ty),+) => {
+        $(
+            impl<'en> CanonicalKey<'en> for $ty {}
+        )+
+    };
+}
+
+canonical_key!(bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, String, Bytes, Uuid);
+
+impl<'a, 'en> CanonicalKey<'en> for &'a str where 'a: 'en {}
+
+impl<'en, K: CanonicalKey<'en> + 'en> CanonicalKey<'en> for Box<K> {}
+
+impl<'en, K: CanonicalKey<'en> + 'en> CanonicalKey<'en> for Arc<K> {}
+
+impl<'en, T: CanonicalForm<'en> + 'en> CanonicalForm<'en> for Option<T> {}
+
+impl<'en, T: CanonicalForm<'en> + 'en> CanonicalForm<'en> for Vec<T> {}
+
+impl<'en, T: CanonicalForm<'en> + 'en> CanonicalForm<'en> for Box<T> {}
+
+impl<'en, T: CanonicalForm<'en> + 'en> CanonicalForm<'en> for Arc<T> {}
+
+impl<'en, T: CanonicalForm<'en> + Ord + 'en> CanonicalForm<'en> for BTreeSet<T> {}
+
+impl<'en, K, V> CanonicalForm<'en> for BTreeMap<K, V>
+where
+    K: CanonicalKey<'en> + 'en,
+    V: CanonicalForm<'en> + 'en,
+{
+}
+
+macro_rules! canonical_tuple {
+    ($(($($name:ident),+))+) => {
+        $(
+            impl<'en, $($name),+> CanonicalForm<'en> for ($($name,)+)
+            where
+                $($name: CanonicalForm<'en> + 'en,)+
+            {
+            }
+        )+
+    };
+}
+
+canonical_tuple! {
+    (T0)
+    (T0, T1)
+    (T0, T1, T2)
+    (T0, T1, T2, T3)
+    (T0, T1, T2, T3, T4)
+    (T0, T1, T2, T3, T4, T5)
+    (T0, T1, T2, T3, T4, T5, T6)
+    (T0, T1, T2, T3, T4, T5, T6, T7)
+}
+
+/// Encode the given `value`, whose type guarantees a canonical encoding, using a
+/// [`Canonical`] encoder.
+pub fn canonical<'en, T: CanonicalForm<'en>>(value: &'en T) -> Canonicalized<&'en T> {
+    Canonicalized::new(value)
+}
+
+/// Return the canonical form of a floating-point number: zero is always positive,
+/// and every NaN is the same NaN.
+fn canonical_f64(v: f64) -> f64 {
+    if v == 0. {
+        0.
+    } else if v.is_nan() {
+        f64::NAN
+    } else {
+        v
+    }
+}
+
+fn canonical_f32(v: f32) -> f64 {
+    canonical_f64(v.into())
+}
+
+/// The canonical ordering of a map key.
+///
+/// Keys of different types are ordered by type: booleans, then integers, strings, byte strings,
+/// and finally `Uuid`s.
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+enum Ordinal {
+    Bool(bool),
+    Int(i128),
+    Str(String),
+    Bytes(Bytes),
+    Uuid(Uuid),
+}
+
+impl Ordinal {
+    fn of(key: &Key) -> Result<Self, &'static str> {
+        match key {
+            Key::Bool(v) => Ok(Self::Bool(*v)),
+            Key::I8(v) => Ok(Self::Int((*v).into())),
+            Key::I16(v) => Ok(Self::Int((*v).into())),
+            Key::I32(v) => Ok(Self::Int((*v).into())),
+            Key::I64(v) => Ok(Self::Int((*v).into())),
+            Key::U8(v) => Ok(Self::Int((*v).into())),
+            Key::U16(v) => Ok(Self::Int((*v).into())),
+            Key::U32(v) => Ok(Self::Int((*v).into())),
+            Key::U64(v) => Ok(Self::Int((*v).into())),
+            Key::Str(v) => Ok(Self::Str(v.clone())),
+            Key::Bytes(v) => Ok(Self::Bytes(v.clone())),
+            Key::Uuid(v) => Ok(Self::Uuid(*v)),
+            Key::F32(_) | Key::F64(_) => Err("a canonical map key cannot be a float"),
+            Key::Unit | Key::None => Err("a canonical map key cannot be empty"),
+            Key::Unsupported(_) => Err("a canonical map key must be a primitive value"),
+        }
+    }
+}
+
+/// Check that the given `key` follows the `last` key in canonical order.
+fn check_order(last: &mut Option<Ordinal>, key: &Key) -> Result<(), &'static str> {
+    let ordinal = Ordinal::of(key)?;
+
+    match last {
+        Some(last) if ordinal <= *last => {
+            Err("canonical map keys must be unique and in ascending order")
+        }
+        _ => {
+            *last = Some(ordinal);
+            Ok(())
+        }
+    }
+}
+
+/// An [`Encoder`] which enforces a deterministic, canonical encoding, recursively.
+///
+///  - integers are always encoded as an `i64` or a `u64`, and floats as an `f64`
+///  - negative zero is encoded as zero, and every NaN as the same NaN
+///  - map keys must be primitive (non-float) values in strictly ascending order, or encoding
+///    will fail
+///
+/// Map entries are not re-ordered, since that would require buffering every value in the map.
+/// Implement [`CanonicalForm`] for a type to guarantee that its encoding will not fail.
+pub struct Canonical<E> {
+    encoder: E,
+}
+
+impl<E> Canonical<E> {
+    /// Wrap the given `encoder` so that it enforces a canonical encoding.
+    pub fn new(encoder: E) -> Self {
+        Self { encoder }
+    }
+}
+
+/// A value which will be encoded with a [`Canonical`] encoder.
+pub struct Canonicalized<T> {
+    value: T,
+}
+
+impl<T> Canonicalized<T> {
+    /// Encode the given `value` with a [`Canonical`] encoder.
+    pub fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for Canonicalized<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.into_stream(Canonical::new(encoder))
+    }
+}
+
+impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Canonicalized<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.to_stream(Canonical::new(encoder))
+    }
+}
+
+/// A stream of map entries whose keys are checked for canonical order.
+struct CanonicalEntries<S> {
+    source: S,
+    last: Option<Ordinal>,
+}
+
+impl<'en, K, V, S> Stream for CanonicalEntries<S>
+where
+    K: IntoStream<'en> + 'en,
+    V: IntoStream<'en> + 'en,
+    S: Stream<Item = (K, V)> + Unpin,
+{
+    type Item = (Canonicalized<Key>, Canonicalized<V>);
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let entry = ready!(self.source.poll_next_unpin(cxt)).map(|(key, value)| {
+            let key = Key::probe(key);
+
+            // an out-of-order key will fail to encode
+            let key = match check_order(&mut self.last, &key) {
+                Ok(()) => key,
+                Err(cause) => Key::Unsupported(cause.to_string()),
+            };
+
+            (Canonicalized::new(key), Canonicalized::new(value))
+        });
+
+        Poll::Ready(entry)
+    }
+}
+
+/// Returned from `Canonical::encode_map`.
+pub struct CanonicalMap<M> {
+    map: M,
+    last: Option<Ordinal>,
+}
+
+impl<M> CanonicalMap<M> {
+    fn check_key<'en, K: IntoStream<'en> + 'en, E: Error>(
+        &mut self,
+        key: K,
+    ) -> Result<Canonicalized<Key>, E> {
+        let key = Key::probe(key).check()?;
+        check_order(&mut self.last, &key).map_err(E::custom)?;
+        Ok(Canonicalized::new(key))
+    }
+}
+
+impl<'en, M: EncodeMap<'en>> EncodeMap<'en> for CanonicalMap<M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        let key = self.check_key(key)?;
+        self.map.encode_key(key)
+    }
+
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        check_order(&mut self.last, &Key::Str(key.to_string())).map_err(Error::custom)?;
+        self.map.encode_key_str(key)
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        self.map.encode_value(Canonicalized::new(value))
+    }
+
+    fn encode_entry<K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Self::Error> {
+        let key = self.check_key(key)?;
+        self.map.encode_entry(key, Canonicalized::new(value))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.map.end()
+    }
+}
+
+/// Returned from `Canonical::encode_seq` and `Canonical::encode_tuple`.
+pub struct CanonicalSeq<S> {
+    seq: S,
+}
+
+impl<'en, S: EncodeSeq<'en>> EncodeSeq<'en> for CanonicalSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq.encode_element(Canonicalized::new(value))
+    }
+
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.seq.encode_element_u64(value.into())
+    }
+
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.seq.encode_element_u64(value)
+    }
+
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        self.seq.encode_element_f64(canonical_f64(value))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.seq.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.seq.end()
+    }
+}
+
+impl<'en, S: EncodeTuple<'en>> EncodeTuple<'en> for CanonicalSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq.encode_element(Canonicalized::new(value))
+    }
+
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.seq.encode_element_u64(value.into())
+    }
+
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.seq.encode_element_u64(value)
+    }
+
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        self.seq.encode_element_f64(canonical_f64(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.seq.end()
+    }
+}
+
+macro_rules! canonical_encode {
+    ($this:ident, $method:ident, $chunks:ident: [$ty:ty] => F32 as $widest:ident $($rest:tt)*) => {
+        $this
+            .encoder
+            .$widest($chunks.map(|chunk| chunk.into_iter().map(canonical_f32)))
+    };
+    ($this:ident, $method:ident, $chunks:ident: [$ty:ty] => F64 as $widest:ident $($rest:tt)*) => {
+        $this
+            .encoder
+            .$widest($chunks.map(|chunk| chunk.into_iter().map(canonical_f64)))
+    };
+    ($this:ident, $method:ident, $chunks:ident: [$ty:ty] => $kind:ident as $widest:ident($wide:ty)) => {
+        $this
+            .encoder
+            .$widest($chunks.map(|chunk| chunk.into_iter().map(<$wide>::from)))
+    };
+    ($this:ident, $method:ident, $value:ident: $ty:ty => F32 as $widest:ident $($rest:tt)*) => {
+        $this.encoder.$widest(canonical_f32($value))
+    };
+    ($this:ident, $method:ident, $value:ident: $ty:ty => F64 as $widest:ident $($rest:tt)*) => {
+        $this.encoder.$widest(canonical_f64($value))
+    };
+    ($this:ident, $method:ident, $value:ident: $ty:ty => $kind:ident as $widest:ident($wide:ty)) => {
+        $this.encoder.$widest(<$wide>::from($value))
+    };
+}
+
+impl<'en, E: Encoder<'en>> Encoder<'en> for Canonical<E> {
+    type Ok = E::Ok;
+    type Error = E::Error;
+    type EncodeMap = CanonicalMap<E::EncodeMap>;
+    type EncodeSeq = CanonicalSeq<E::EncodeSeq>;
+    type EncodeTuple = CanonicalSeq<E::EncodeTuple>;
+
+    encode_primitives!(canonical_encode);
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_none()
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_some(Canonicalized::new(value))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_unit()
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_bytes(bytes)
+    }
+
+    fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        Ok(CanonicalMap {
+            map: self.encoder.encode_map(len)?,
+            last: None,
+        })
+    }
+
+    fn encode_map_stream<K, V, S>(self, map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        let map = CanonicalEntries {
+            source: map,
+            last: None,
+        };
+
+        self.encoder.encode_map_stream(map)
+    }
+
+    fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Ok(CanonicalSeq {
+            seq: self.encoder.encode_seq(len)?,
+        })
+    }
+
+    fn encode_seq_stream<T, S>(self, seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        self.encoder.encode_seq_stream(seq.map(Canonicalized::new))
+    }
+
+    fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Ok(CanonicalSeq {
+            seq: self.encoder.encode_tuple(len)?,
+        })
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_bytes(bytes)
+    }
+
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_memoized(key, Canonicalized::new(value))
+    }
+
+    fn style(&self) -> EncodeStyle {
+        self.encoder.style()
+    }
+
+    fn depth(&self) -> usize {
+        self.encoder.depth()
+    }
+
+    fn with_style(self, style: EncodeStyle) -> Self {
+        Self {
+            encoder: self.encoder.with_style(style),
+        }
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_str(value)
+    }
+}
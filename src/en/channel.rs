@@ -0,0 +1,146 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+
+/// A channel which carries an error raised by a fallible source stream during lazy encoding
+/// (e.g. by [`Encoder::encode_seq_stream`](super::Encoder::encode_seq_stream)) through to the
+/// consumer of the encoded stream.
+///
+/// Wrap the source with [`ErrorChannel::watch`] before passing it to the [`Encoder`](super::Encoder),
+/// and wrap the encoded output with [`ErrorChannel::guard`]. If the source fails, it ends early
+/// and the guarded output yields the error instead of silently ending with a truncated encoding.
+pub struct ErrorChannel<E> {
+    error: Arc<Mutex<Option<E>>>,
+}
+
+impl<E> ErrorChannel<E> {
+    /// Construct a new, empty [`ErrorChannel`].
+    pub fn new() -> Self {
+        Self {
+            error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Wrap a fallible `source` stream so that it yields only successful items,
+    /// and ends at the first error, which is sent to this channel.
+    pub fn watch<S>(&self, source: S) -> Watch<S, E> {
+        Watch {
+            source: Some(source),
+            error: self.error.clone(),
+        }
+    }
+
+    /// Wrap a fallible encoded `output` stream so that it yields the error sent to this channel,
+    /// if any, and then ends.
+    pub fn guard<S>(&self, output: S) -> Guard<S, E> {
+        Guard {
+            output: Some(output),
+            error: self.error.clone(),
+        }
+    }
+
+    /// Take the error sent to this channel, if any.
+    pub fn take(&self) -> Option<E> {
+        take(&self.error)
+    }
+}
+
+impl<E> Clone for ErrorChannel<E> {
+    fn clone(&self) -> Self {
+        Self {
+            error: self.error.clone(),
+        }
+    }
+}
+
+impl<E> Default for ErrorChannel<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn take<E>(error: &Mutex<Option<E>>) -> Option<E> {
+    match error.lock() {
+        Ok(mut error) => error.take(),
+        Err(poisoned) => poisoned.into_inner().take(),
+    }
+}
+
+/// A source stream watched by an [`ErrorChannel`].
+pub struct Watch<S, E> {
+    source: Option<S>,
+    error: Arc<Mutex<Option<E>>>,
+}
+
+impl<T, E, S> Stream for Watch<S, E>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let source = match self.source.as_mut() {
+            Some(source) => source,
+            None => return Poll::Ready(None),
+        };
+
+        match ready!(source.poll_next_unpin(cxt)) {
+            Some(Ok(item)) => Poll::Ready(Some(item)),
+            Some(Err(cause)) => {
+                self.source = None;
+
+                let mut error = match self.error.lock() {
+                    Ok(error) => error,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+
+                if error.is_none() {
+                    *error = Some(cause);
+                }
+
+                Poll::Ready(None)
+            }
+            None => {
+                self.source = None;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+/// An encoded output stream guarded by an [`ErrorChannel`].
+pub struct Guard<S, E> {
+    output: Option<S>,
+    error: Arc<Mutex<Option<E>>>,
+}
+
+impl<T, X, E, S> Stream for Guard<S, E>
+where
+    S: Stream<Item = Result<T, X>> + Unpin,
+    X: From<E>,
+{
+    type Item = Result<T, X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let output = match self.output.as_mut() {
+            Some(output) => output,
+            None => return Poll::Ready(None),
+        };
+
+        let item = ready!(output.poll_next_unpin(cxt));
+
+        if let Some(cause) = take(&self.error) {
+            self.output = None;
+            return Poll::Ready(Some(Err(cause.into())));
+        }
+
+        if item.is_none() {
+            self.output = None;
+        }
+
+        Poll::Ready(item)
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt;
+use std::thread;
+
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+
+use super::{
+    EncodeMap, EncodeSeq, EncodeStyle, EncodeTuple, Encoder, FloatFormat, IntoStream, ToStream,
+};
+
+/// An [`Encoder`] which panics, in debug builds, when it's used incorrectly, recursively.
+///
+/// The checks are:
+///  - a map key must be followed by exactly one value before the next key or the end of the map
+///  - a map, sequence, or tuple must be ended with `end` or `finish`, unless one of its methods
+///    returned an error
+///  - the number of entries or elements encoded must match the length declared when encoding
+///    the map, sequence, or tuple began
+///
+/// In release builds, a [`DebugChecked`] encoder only forwards each call to the wrapped encoder.
+pub struct DebugChecked<E> {
+    encoder: E,
+}
+
+impl<E> DebugChecked<E> {
+    /// Wrap the given `encoder` so that its use is checked in debug builds.
+    pub fn new(encoder: E) -> Self {
+        Self { encoder }
+    }
+}
+
+/// A value which will be encoded with a [`DebugChecked`] encoder.
+pub struct Checked<T> {
+    value: T,
+}
+
+impl<T> Checked<T> {
+    /// Encode the given `value` with a [`DebugChecked`] encoder.
+    pub fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for Checked<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.into_stream(DebugChecked::new(encoder))
+    }
+}
+
+impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Checked<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.to_stream(DebugChecked::new(encoder))
+    }
+}
+
+/// Panic with the given message if this is a debug build.
+fn misuse(kind: &str, msg: fmt::Arguments) {
+    if cfg!(debug_assertions) {
+        panic!("incorrect use of an encoded {kind}: {msg}");
+    }
+}
+
+/// The state of a map, sequence, or tuple being encoded, shared by its [`DebugChecked`] wrapper.
+struct Checks {
+    kind: &'static str,
+    len: Option<usize>,
+    count: usize,
+    ended: bool,
+    failed: bool,
+}
+
+impl Checks {
+    fn new(kind: &'static str, len: Option<usize>) -> Self {
+        Self {
+            kind,
+            len,
+            count: 0,
+            ended: false,
+            failed: false,
+        }
+    }
+
+    fn check<T, E>(&mut self, result: Result<T, E>) -> Result<T, E> {
+        self.failed |= result.is_err();
+        result
+    }
+
+    fn count(&mut self) {
+        self.count += 1;
+
+        if let Some(len) = self.len {
+            if self.count > len {
+                misuse(
+                    self.kind,
+                    format_args!("encoded more than the declared length of {len}"),
+                );
+            }
+        }
+    }
+
+    fn end(&mut self) {
+        self.ended = true;
+
+        if let Some(len) = self.len {
+            if self.count != len {
+                misuse(
+                    self.kind,
+                    format_args!("encoded {} of the declared length of {len}", self.count),
+                );
+            }
+        }
+    }
+}
+
+impl Drop for Checks {
+    fn drop(&mut self) {
+        if !self.ended && !self.failed && !thread::panicking() {
+            misuse(self.kind, format_args!("dropped without calling `end`"));
+        }
+    }
+}
+
+/// Returned from `DebugChecked::encode_map`.
+pub struct CheckedMap<M> {
+    map: M,
+    checks: Checks,
+    key: bool,
+}
+
+impl<M> CheckedMap<M> {
+    fn key(&mut self) {
+        if self.key {
+            misuse(
+                "map",
+                format_args!("`encode_key` called twice without a value"),
+            );
+        }
+
+        self.key = true;
+        self.checks.count();
+    }
+
+    fn value(&mut self) {
+        if !self.key {
+            misuse(
+                "map",
+                format_args!("`encode_value` called before `encode_key`"),
+            );
+        }
+
+        self.key = false;
+    }
+}
+
+impl<'en, M: EncodeMap<'en>> EncodeMap<'en> for CheckedMap<M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        self.key();
+        let result = self.map.encode_key(Checked::new(key));
+        self.checks.check(result)
+    }
+
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        self.key();
+        let result = self.map.encode_key_str(key);
+        self.checks.check(result)
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        self.value();
+        let result = self.map.encode_value(Checked::new(value));
+        self.checks.check(result)
+    }
+
+    fn encode_entry<K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Self::Error> {
+        self.key();
+        self.value();
+        let result = self
+            .map
+            .encode_entry(Checked::new(key), Checked::new(value));
+        self.checks.check(result)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.key {
+            misuse("map", format_args!("ended after a key without a value"));
+        }
+
+        self.checks.end();
+        self.map.end()
+    }
+}
+
+/// Returned from `DebugChecked::encode_seq` and `DebugChecked::encode_tuple`.
+pub struct CheckedSeq<S> {
+    seq: S,
+    checks: Checks,
+}
+
+impl<S> CheckedSeq<S> {
+    fn seq(&mut self) -> &mut S {
+        self.checks.count();
+        &mut self.seq
+    }
+}
+
+impl<'en, S: EncodeSeq<'en>> EncodeSeq<'en> for CheckedSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element(Checked::new(value));
+        self.checks.check(result)
+    }
+
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_u8(value);
+        self.checks.check(result)
+    }
+
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_u64(value);
+        self.checks.check(result)
+    }
+
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_f64(value);
+        self.checks.check(result)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.seq.reserve(additional)
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.checks.end();
+        self.seq.end()
+    }
+}
+
+impl<'en, S: EncodeTuple<'en>> EncodeTuple<'en> for CheckedSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element(Checked::new(value));
+        self.checks.check(result)
+    }
+
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_u8(value);
+        self.checks.check(result)
+    }
+
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_u64(value);
+        self.checks.check(result)
+    }
+
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        let result = self.seq().encode_element_f64(value);
+        self.checks.check(result)
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.checks.end();
+        self.seq.end()
+    }
+}
+
+impl<'en, E: Encoder<'en>> Encoder<'en> for DebugChecked<E> {
+    type Ok = E::Ok;
+    type Error = E::Error;
+    type EncodeMap = CheckedMap<E::EncodeMap>;
+    type EncodeSeq = CheckedSeq<E::EncodeSeq>;
+    type EncodeTuple = CheckedSeq<E::EncodeTuple>;
+
+    encode_primitives!(forward_primitive);
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_none()
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_some(Checked::new(value))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_unit()
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_bytes(bytes)
+    }
+
+    fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        Ok(CheckedMap {
+            map: self.encoder.encode_map(len)?,
+            checks: Checks::new("map", len),
+            key: false,
+        })
+    }
+
+    fn encode_map_stream<K, V, S>(self, map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        let map = map.map(|(key, value)| (Checked::new(key), Checked::new(value)));
+        self.encoder.encode_map_stream(map)
+    }
+
+    fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Ok(CheckedSeq {
+            seq: self.encoder.encode_seq(len)?,
+            checks: Checks::new("sequence", len),
+        })
+    }
+
+    fn encode_seq_stream<T, S>(self, seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        self.encoder.encode_seq_stream(seq.map(Checked::new))
+    }
+
+    fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Ok(CheckedSeq {
+            seq: self.encoder.encode_tuple(len)?,
+            checks: Checks::new("tuple", Some(len)),
+        })
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_bytes(bytes)
+    }
+
+    fn collect_f32_with(self, v: f32, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_f32_with(v, format)
+    }
+
+    fn collect_f64_with(self, v: f64, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_f64_with(v, format)
+    }
+
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_memoized(key, Checked::new(value))
+    }
+
+    fn style(&self) -> EncodeStyle {
+        self.encoder.style()
+    }
+
+    fn depth(&self) -> usize {
+        self.encoder.depth()
+    }
+
+    fn with_style(self, style: EncodeStyle) -> Self {
+        Self {
+            encoder: self.encoder.with_style(style),
+        }
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_str(value)
+    }
+}
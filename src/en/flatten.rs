@@ -0,0 +1,217 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use bytes::Bytes;
+use futures::stream::{self, Stream};
+use uuid::Uuid;
+
+use super::{EncodeMap, EncodeSeq, EncodeTuple, Encoder, Error, IntoStream};
+
+/// Encode the entries of the given `value`, which must encode itself as a map, into the given
+/// parent `map`, e.g. to flatten the fields of a nested struct into the map of its parent.
+///
+/// The parent must not declare a length when it's created with `encode_map`, unless that length
+/// includes the flattened entries. A value which encodes a map as a stream cannot be flattened.
+/// Use [`Unmatched`](crate::de::Unmatched) to decode a flattened value.
+pub fn flatten_into<'en, M, T>(map: &mut M, value: T) -> Result<(), M::Error>
+where
+    M: EncodeMap<'en>,
+    T: IntoStream<'en> + 'en,
+{
+    value.into_stream(Flatten { map }).map(|_| ())
+}
+
+/// An [`Encoder`] which encodes the entries of a map into a parent map.
+struct Flatten<'a, M> {
+    map: &'a mut M,
+}
+
+fn not_a_map<E: Error>(kind: &str) -> E {
+    E::custom(format_args!("cannot flatten {kind} into a map"))
+}
+
+/// Returned from `Flatten::encode_map`.
+struct FlattenMap<'a, M> {
+    map: &'a mut M,
+}
+
+impl<'a, 'en, M: EncodeMap<'en>> EncodeMap<'en> for FlattenMap<'a, M> {
+    type Ok = stream::Empty<()>;
+    type Error = M::Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        self.map.encode_key(key)
+    }
+
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        self.map.encode_key_str(key)
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        self.map.encode_value(value)
+    }
+
+    fn encode_entry<K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Self::Error> {
+        self.map.encode_entry(key, value)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(stream::empty())
+    }
+}
+
+/// Returned from `Flatten::encode_seq` and `Flatten::encode_tuple`, which always fail.
+struct NotAMap<E> {
+    error: PhantomData<E>,
+}
+
+impl<'en, E: Error + Send + Unpin + 'en> EncodeSeq<'en> for NotAMap<E> {
+    type Ok = stream::Empty<()>;
+    type Error = E;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, _value: V) -> Result<(), E> {
+        Err(not_a_map("a sequence"))
+    }
+
+    fn end(self) -> Result<Self::Ok, E> {
+        Err(not_a_map("a sequence"))
+    }
+}
+
+impl<'en, E: Error + Send + Unpin + 'en> EncodeTuple<'en> for NotAMap<E> {
+    type Ok = stream::Empty<()>;
+    type Error = E;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, _value: V) -> Result<(), E> {
+        Err(not_a_map("a tuple"))
+    }
+
+    fn end(self) -> Result<Self::Ok, E> {
+        Err(not_a_map("a tuple"))
+    }
+}
+
+macro_rules! not_a_map {
+    ($($method:ident($ty:ty)),+) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<Self::Ok, Self::Error> {
+                Err(not_a_map(stringify!($ty)))
+            }
+        )+
+    };
+}
+
+macro_rules! not_a_map_array {
+    ($($method:ident($ty:ty)),+) => {
+        $(
+            fn $method<T, S>(self, _chunks: S) -> Result<Self::Ok, Self::Error>
+            where
+                T: IntoIterator<Item = $ty> + Send + Unpin + 'en,
+                S: Stream<Item = T> + Send + Unpin + 'en,
+                <T as IntoIterator>::IntoIter: Send + Unpin + 'en,
+            {
+                Err(not_a_map(concat!("an array of ", stringify!($ty))))
+            }
+        )+
+    };
+}
+
+impl<'a, 'en, M: EncodeMap<'en>> Encoder<'en> for Flatten<'a, M> {
+    type Ok = stream::Empty<()>;
+    type Error = M::Error;
+    type EncodeMap = FlattenMap<'a, M>;
+    type EncodeSeq = NotAMap<M::Error>;
+    type EncodeTuple = NotAMap<M::Error>;
+
+    not_a_map!(
+        encode_bool(bool),
+        encode_i8(i8),
+        encode_i16(i16),
+        encode_i32(i32),
+        encode_i64(i64),
+        encode_u8(u8),
+        encode_u16(u16),
+        encode_u32(u32),
+        encode_u64(u64),
+        encode_f32(f32),
+        encode_f64(f64),
+        encode_str(&str),
+        encode_uuid(Uuid)
+    );
+
+    not_a_map_array!(
+        encode_array_bool(bool),
+        encode_array_i8(i8),
+        encode_array_i16(i16),
+        encode_array_i32(i32),
+        encode_array_i64(i64),
+        encode_array_u8(u8),
+        encode_array_u16(u16),
+        encode_array_u32(u32),
+        encode_array_u64(u64),
+        encode_array_f32(f32),
+        encode_array_f64(f64)
+    );
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        // an absent value has no entries
+        Ok(stream::empty())
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        value.into_stream(self)
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_map("()"))
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, _bytes: B) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_map("a binary value"))
+    }
+
+    fn encode_map(self, _len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        Ok(FlattenMap { map: self.map })
+    }
+
+    fn encode_map_stream<K, V, S>(self, _map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        Err(not_a_map("a map stream"))
+    }
+
+    fn encode_seq(self, _len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Err(not_a_map("a sequence"))
+    }
+
+    fn encode_seq_stream<T, S>(self, _seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        Err(not_a_map("a sequence"))
+    }
+
+    fn encode_tuple(self, _len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Err(not_a_map("a tuple"))
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, _bytes: B) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_map("a binary value"))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_map("a string"))
+    }
+}
@@ -1,14 +1,20 @@
+use std::cmp::{Ordering, Reverse};
 use std::collections::*;
 use std::fmt;
 use std::hash::{BuildHasher, Hash};
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
 
 use bytes::Bytes;
 use futures::stream::Stream;
+#[cfg(feature = "once_cell")]
+use once_cell::sync::OnceCell;
 use uuid::Uuid;
 
-use super::{EncodeTuple, Encoder, IntoStream, MapStream, SeqStream, ToStream};
+use crate::types::Timestamp;
+
+use super::{EncodeTuple, Encoder, Error, IntoStream, MapStream, SeqStream, ToStream};
 
 macro_rules! autoencode {
     ($ty:ident, $method:ident $($cast:tt)*) => {
@@ -130,6 +136,35 @@ impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Option<T> {
     }
 }
 
+macro_rules! encode_cell {
+    ($($(#[$attr:meta])* $ty:ident),+) => {
+        $(
+            $(#[$attr])*
+            impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for $ty<T> {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    self.into_inner().into_stream(encoder)
+                }
+            }
+
+            $(#[$attr])*
+            impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for $ty<T> {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    match self.get() {
+                        Some(value) => encoder.encode_some(value),
+                        None => encoder.encode_none(),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+encode_cell!(
+    OnceLock,
+    #[cfg(feature = "once_cell")]
+    OnceCell
+);
+
 ////////////////////////////////////////////////////////////////////////////////
 
 impl<'en, T: ?Sized> IntoStream<'en> for PhantomData<T> {
@@ -146,6 +181,32 @@ impl<'en, T: ?Sized> ToStream<'en> for PhantomData<T> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+impl<'en, T: IntoStream<'en>> IntoStream<'en> for Reverse<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.0.into_stream(encoder)
+    }
+}
+
+impl<'en, T: ToStream<'en>> ToStream<'en> for Reverse<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.0.to_stream(encoder)
+    }
+}
+
+impl<'en> IntoStream<'en> for Ordering {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_i8(self as i8)
+    }
+}
+
+impl<'en> ToStream<'en> for Ordering {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_i8(*self as i8)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 // Does not require T: IntoStream.
 impl<'en, T> IntoStream<'en> for [T; 0] {
     fn into_stream<E: Encoder<'en>>(
@@ -192,7 +253,7 @@ macro_rules! encode_array {
 }
 
 encode_array! {
-    01 02 03 04 05 06 07 08 09 10
+    1 2 3 4 5 6 7 8 9 10
     11 12 13 14 15 16 17 18 19 20
     21 22 23 24 25 26 27 28 29 30
     31 32
@@ -357,7 +418,7 @@ encode_map!(HashMap<K: Eq + Hash, V, H: BuildHasher>);
 
 impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Arc<T> {
     fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
-        (&**self).to_stream(encoder)
+        (**self).to_stream(encoder)
     }
 }
 
@@ -369,7 +430,7 @@ impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for Box<T> {
 
 impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Box<T> {
     fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
-        (&**self).to_stream(encoder)
+        (**self).to_stream(encoder)
     }
 }
 
@@ -460,3 +521,32 @@ impl<'en> ToStream<'en> for Uuid {
         encoder.encode_uuid(*self)
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+impl<'en> IntoStream<'en> for Duration {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        (self.as_secs(), self.subsec_nanos()).into_stream(encoder)
+    }
+}
+
+impl<'en> ToStream<'en> for Duration {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        (*self).into_stream(encoder)
+    }
+}
+
+impl<'en> IntoStream<'en> for SystemTime {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        // a time before the epoch has a negative number of seconds, like a `Timestamp`
+        self.to_unix()
+            .ok_or_else(|| E::Error::custom("cannot encode a SystemTime out of range"))?
+            .into_stream(encoder)
+    }
+}
+
+impl<'en> ToStream<'en> for SystemTime {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        (*self).into_stream(encoder)
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::stream::Stream;
+use uuid::Uuid;
+
+use super::{EncodeMap, EncodeSeq, EncodeTuple, Encoder, Error, IntoStream};
+
+/// A primitive map key, captured so that it can be inspected before being encoded.
+pub(super) enum Key {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Str(String),
+    Bytes(Bytes),
+    Uuid(Uuid),
+    Unit,
+    None,
+    Unsupported(String),
+}
+
+impl Key {
+    pub(super) fn probe<'en, K: IntoStream<'en> + 'en>(key: K) -> Self {
+        match key.into_stream(KeyProbe) {
+            Ok(mut captured) => captured.0.take().expect("captured key"),
+            Err(cause) => Self::Unsupported(cause.0),
+        }
+    }
+
+    pub(super) fn check<E: Error>(self) -> Result<Self, E> {
+        match self {
+            Self::Unsupported(cause) => Err(E::custom(cause)),
+            key => Ok(key),
+        }
+    }
+}
+
+impl<'en> IntoStream<'en> for Key {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        match self {
+            Self::Bool(v) => encoder.encode_bool(v),
+            Self::I8(v) => encoder.encode_i8(v),
+            Self::I16(v) => encoder.encode_i16(v),
+            Self::I32(v) => encoder.encode_i32(v),
+            Self::I64(v) => encoder.encode_i64(v),
+            Self::U8(v) => encoder.encode_u8(v),
+            Self::U16(v) => encoder.encode_u16(v),
+            Self::U32(v) => encoder.encode_u32(v),
+            Self::U64(v) => encoder.encode_u64(v),
+            Self::F32(v) => encoder.encode_f32(v),
+            Self::F64(v) => encoder.encode_f64(v),
+            Self::Str(v) => encoder.encode_str(&v),
+            Self::Bytes(v) => encoder.encode_bytes(v),
+            Self::Uuid(v) => encoder.encode_uuid(v),
+            Self::Unit => encoder.encode_unit(),
+            Self::None => encoder.encode_none(),
+            Self::Unsupported(cause) => Err(Error::custom(cause)),
+        }
+    }
+}
+
+struct ProbeError(String);
+
+impl Error for ProbeError {
+    fn custom<I: fmt::Display>(info: I) -> Self {
+        Self(info.to_string())
+    }
+}
+
+struct Captured(Option<Key>);
+
+impl Stream for Captured {
+    type Item = Key;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.take())
+    }
+}
+
+enum Unsupported {}
+
+impl<'en> EncodeMap<'en> for Unsupported {
+    type Ok = Captured;
+    type Error = ProbeError;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, _key: T) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, _value: T) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+impl<'en> EncodeSeq<'en> for Unsupported {
+    type Ok = Captured;
+    type Error = ProbeError;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, _value: V) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+impl<'en> EncodeTuple<'en> for Unsupported {
+    type Ok = Captured;
+    type Error = ProbeError;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, _value: V) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {}
+    }
+}
+
+/// An [`Encoder`] which captures a primitive map key rather than encoding it.
+struct KeyProbe;
+
+macro_rules! capture_key {
+    ($($method:ident($ty:ty) => $variant:ident),+) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                Ok(Captured(Some(Key::$variant(v.into()))))
+            }
+        )+
+    };
+}
+
+macro_rules! unsupported_key_array {
+    ($($method:ident($ty:ty)),+) => {
+        $(
+            fn $method<T, S>(self, _chunks: S) -> Result<Self::Ok, Self::Error>
+            where
+                T: IntoIterator<Item = $ty> + Send + Unpin + 'en,
+                S: Stream<Item = T> + Send + Unpin + 'en,
+                <T as IntoIterator>::IntoIter: Send + Unpin + 'en,
+            {
+                Err(unsupported("an array"))
+            }
+        )+
+    };
+}
+
+fn unsupported(kind: &str) -> ProbeError {
+    ProbeError(format!("a map key must be a primitive value, not {}", kind))
+}
+
+impl<'en> Encoder<'en> for KeyProbe {
+    type Ok = Captured;
+    type Error = ProbeError;
+    type EncodeMap = Unsupported;
+    type EncodeSeq = Unsupported;
+    type EncodeTuple = Unsupported;
+
+    capture_key!(
+        encode_bool(bool) => Bool,
+        encode_i8(i8) => I8,
+        encode_i16(i16) => I16,
+        encode_i32(i32) => I32,
+        encode_i64(i64) => I64,
+        encode_u8(u8) => U8,
+        encode_u16(u16) => U16,
+        encode_u32(u32) => U32,
+        encode_u64(u64) => U64,
+        encode_f32(f32) => F32,
+        encode_f64(f64) => F64,
+        encode_str(&str) => Str,
+        encode_uuid(Uuid) => Uuid
+    );
+
+    unsupported_key_array!(
+        encode_array_bool(bool),
+        encode_array_i8(i8),
+        encode_array_i16(i16),
+        encode_array_i32(i32),
+        encode_array_i64(i64),
+        encode_array_u8(u8),
+        encode_array_u16(u16),
+        encode_array_u32(u32),
+        encode_array_u64(u64),
+        encode_array_f32(f32),
+        encode_array_f64(f64)
+    );
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Captured(Some(Key::None)))
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, _value: T) -> Result<Self::Ok, Self::Error> {
+        Err(unsupported("an optional value"))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Captured(Some(Key::Unit)))
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        Ok(Captured(Some(Key::Bytes(bytes.into()))))
+    }
+
+    fn encode_map(self, _len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        Err(unsupported("a map"))
+    }
+
+    fn encode_map_stream<K, V, S>(self, _map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        Err(unsupported("a map"))
+    }
+
+    fn encode_seq(self, _len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Err(unsupported("a sequence"))
+    }
+
+    fn encode_seq_stream<T, S>(self, _seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        Err(unsupported("a sequence"))
+    }
+
+    fn encode_tuple(self, _len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Err(unsupported("a tuple"))
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        Ok(Captured(Some(Key::Bytes(bytes.into()))))
+    }
+}
+
+/// A policy for converting a map key into a string, for formats which only support string keys.
+///
+/// A format should call [`KeyToString::key_to_string`] from its implementation of
+/// [`EncodeMap::encode_key`], and can then handle the result like a key passed to
+/// [`EncodeMap::encode_key_str`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KeyToString {
+    /// Accept only string keys.
+    StringOnly,
+    /// Also accept boolean, integer, and `Uuid` keys, written using their [`fmt::Display`]
+    /// implementations, and finite float keys, written in their shortest form with negative
+    /// zero written as `0`. A NaN or infinite key is an error, since it can't be decoded as the
+    /// same map key.
+    #[default]
+    Primitive,
+}
+
+impl KeyToString {
+    /// Convert the given `key` into a string according to this policy.
+    pub fn key_to_string<'en, K: IntoStream<'en> + 'en, E: Error>(
+        self,
+        key: K,
+    ) -> Result<String, E> {
+        let key = match (self, Key::probe(key).check()?) {
+            (_, Key::Str(v)) => return Ok(v),
+            (Self::StringOnly, _) => return Err(E::custom("a map key must be a string")),
+            (Self::Primitive, key) => key,
+        };
+
+        match key {
+            Key::Bool(v) => Ok(v.to_string()),
+            Key::I8(v) => Ok(v.to_string()),
+            Key::I16(v) => Ok(v.to_string()),
+            Key::I32(v) => Ok(v.to_string()),
+            Key::I64(v) => Ok(v.to_string()),
+            Key::U8(v) => Ok(v.to_string()),
+            Key::U16(v) => Ok(v.to_string()),
+            Key::U32(v) => Ok(v.to_string()),
+            Key::U64(v) => Ok(v.to_string()),
+            Key::F32(v) if v.is_finite() => Ok((v + 0.).to_string()),
+            Key::F64(v) if v.is_finite() => Ok((v + 0.).to_string()),
+            Key::F32(_) | Key::F64(_) => Err(E::custom("a map key cannot be NaN or infinite")),
+            Key::Uuid(v) => Ok(v.to_string()),
+            Key::Bytes(_) => Err(E::custom("a map key cannot be a byte string")),
+            Key::Unit | Key::None => Err(E::custom("a map key cannot be empty")),
+            Key::Str(_) | Key::Unsupported(_) => unreachable!("checked map key"),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::hash_map::{self, HashMap};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures::ready;
+use futures::stream::{self, Stream, StreamExt};
+
+use super::{Encoder, IntoStream, ToStream};
+
+/// A value which is encoded using [`Encoder::encode_memoized`], so that a format which supports
+/// memoization can re-emit a cached encoding of an earlier value with the same `key`, e.g. a large
+/// schema fragment which is repeated many times in the same document.
+///
+/// The `key` must identify the contents of the value uniquely within a single encoded document.
+pub struct Memoized<T> {
+    key: u64,
+    value: T,
+}
+
+impl<T> Memoized<T> {
+    /// Construct a new [`Memoized`] value identified by the given `key`.
+    pub fn new(key: u64, value: T) -> Self {
+        Self { key, value }
+    }
+
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for Memoized<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_memoized(self.key, self.value)
+    }
+}
+
+impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Memoized<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_memoized(self.key, &self.value)
+    }
+}
+
+enum Entry<C> {
+    Recording,
+    Ready(Arc<[C]>),
+}
+
+/// A stream of encoded chunks returned by [`MemoCache::encode`].
+pub type Memo<'en, C, X> = Pin<Box<dyn Stream<Item = Result<C, X>> + Send + 'en>>;
+
+/// A cache of encoded chunks, for a format whose output is a stream of chunks to implement
+/// [`Encoder::encode_memoized`]. Clones of a cache share the same entries, so every encoder
+/// created while encoding a single document should hold a clone of the same cache.
+pub struct MemoCache<C> {
+    entries: Arc<Mutex<HashMap<u64, Entry<C>>>>,
+}
+
+impl<C> MemoCache<C> {
+    /// Construct a new, empty [`MemoCache`].
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, Entry<C>>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<C: Clone + Send + Sync + Unpin + 'static> MemoCache<C> {
+    /// Return the encoded chunks of the value with the given `key`, encoded by calling `encode`.
+    ///
+    /// The first time a key is encoded, its chunks are recorded as they're streamed. Since an
+    /// encoded document is streamed in order, a later value with the same key is replayed from
+    /// the cache when it's polled, without polling its own (lazily encoded) stream. If the
+    /// recording didn't complete (e.g. because of an error), the later value's stream is used.
+    pub fn encode<'en, X, S, F>(&self, key: u64, encode: F) -> Result<Memo<'en, C, X>, X>
+    where
+        X: Send + 'en,
+        S: Stream<Item = Result<C, X>> + Send + 'en,
+        F: FnOnce() -> Result<S, X>,
+    {
+        let recording = match self.lock().entry(key) {
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(Entry::Recording);
+                true
+            }
+            hash_map::Entry::Occupied(_) => false,
+        };
+
+        if !recording {
+            // the earlier value may not have been streamed yet, so check the cache when polled
+            let encoded = encode()?;
+            let cache = self.clone();
+            let replay = stream::once(async move {
+                let cached = match cache.lock().get(&key) {
+                    Some(Entry::Ready(chunks)) => Some(chunks.clone()),
+                    _ => None,
+                };
+
+                let chunks: Memo<'en, C, X> = match cached {
+                    Some(chunks) => {
+                        let len = chunks.len();
+                        Box::pin(stream::iter((0..len).map(move |i| Ok(chunks[i].clone()))))
+                    }
+                    None => Box::pin(encoded),
+                };
+
+                chunks
+            });
+
+            Ok(Box::pin(replay.flatten()))
+        } else {
+            match encode() {
+                Ok(encoded) => Ok(Box::pin(Record {
+                    source: Box::pin(encoded),
+                    chunks: Some(Vec::new()),
+                    cache: self.clone(),
+                    key,
+                })),
+                Err(cause) => {
+                    self.lock().remove(&key);
+                    Err(cause)
+                }
+            }
+        }
+    }
+}
+
+impl<C> Clone for MemoCache<C> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<C> Default for MemoCache<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Record<'en, C, X> {
+    source: Memo<'en, C, X>,
+    chunks: Option<Vec<C>>,
+    cache: MemoCache<C>,
+    key: u64,
+}
+
+impl<'en, C: Clone + Unpin, X> Stream for Record<'en, C, X> {
+    type Item = Result<C, X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let item = ready!(self.source.poll_next_unpin(cxt));
+
+        match &item {
+            Some(Ok(chunk)) => {
+                if let Some(chunks) = self.chunks.as_mut() {
+                    chunks.push(chunk.clone());
+                }
+            }
+            Some(Err(_)) => {
+                if self.chunks.take().is_some() {
+                    self.cache.lock().remove(&self.key);
+                }
+            }
+            None => {
+                if let Some(chunks) = self.chunks.take() {
+                    let key = self.key;
+                    self.cache.lock().insert(key, Entry::Ready(chunks.into()));
+                }
+            }
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+impl<'en, C, X> Drop for Record<'en, C, X> {
+    fn drop(&mut self) {
+        // an incomplete recording must not be replayed
+        if self.chunks.is_some() {
+            self.cache.lock().remove(&self.key);
+        }
+    }
+}
@@ -29,8 +29,13 @@
 //!  - **Common standard library types**:
 //!    - String
 //!    - Option\<T\>
+//!    - OnceLock\<T\>
 //!    - Result\<T, E\>
 //!    - PhantomData\<T\>
+//!    - Duration
+//!    - SystemTime
+//!    - Ordering
+//!    - Reverse\<T\>
 //!  - **Other common types**:
 //!    - Bytes
 //!    - Uuid
@@ -63,7 +68,8 @@
 //!  - MapStream<Item = Result<(K, V), E>>
 //!  - SeqStream<Item = Result<T, E>>
 //!
-//! Enable support for `SmallVec` using the `smallvec` feature flag.
+//! Enable support for `SmallVec` using the `smallvec` feature flag, and for
+//! `once_cell::sync::OnceCell` using the `once_cell` feature flag.
 
 use std::convert::Infallible;
 use std::fmt;
@@ -72,7 +78,108 @@ use bytes::Bytes;
 use futures::Stream;
 use uuid::Uuid;
 
+/// Implement every method of [`Encoder`] which encodes a primitive value or a typed array by
+/// expanding the given macro `$body` with the encoder, the name of the method, its argument and
+/// the argument's type, then the kind of the value and the method (and type) which encodes the
+/// widest type of the same kind. Since `[$ty]` also parses as a type, a `$body` must match a
+/// typed array before it matches a primitive value.
+///
+/// ```text
+/// macro_rules! body {
+///     ($this:ident, $method:ident, $chunks:ident: [$ty:ty] => $kind:ident as $widest:ident($wide:ty)) => { ... };
+///     ($this:ident, $method:ident, $value:ident: $ty:ty => $kind:ident as $widest:ident($wide:ty)) => { ... };
+/// }
+///
+/// encode_primitives!(body);
+/// ```
+macro_rules! encode_primitives {
+    ($body:ident) => {
+        encode_primitives! {
+            @impl $body;
+            encode_bool(bool) => Bool as encode_bool(bool),
+            encode_i8(i8) => I8 as encode_i64(i64),
+            encode_i16(i16) => I16 as encode_i64(i64),
+            encode_i32(i32) => I32 as encode_i64(i64),
+            encode_i64(i64) => I64 as encode_i64(i64),
+            encode_u8(u8) => U8 as encode_u64(u64),
+            encode_u16(u16) => U16 as encode_u64(u64),
+            encode_u32(u32) => U32 as encode_u64(u64),
+            encode_u64(u64) => U64 as encode_u64(u64),
+            encode_f32(f32) => F32 as encode_f64(f64),
+            encode_f64(f64) => F64 as encode_f64(f64),
+            encode_str(&str) => Str as encode_str(&str),
+            encode_uuid(::uuid::Uuid) => Uuid as encode_uuid(::uuid::Uuid);
+            encode_array_bool(bool) => Bool as encode_array_bool(bool),
+            encode_array_i8(i8) => I8 as encode_array_i64(i64),
+            encode_array_i16(i16) => I16 as encode_array_i64(i64),
+            encode_array_i32(i32) => I32 as encode_array_i64(i64),
+            encode_array_i64(i64) => I64 as encode_array_i64(i64),
+            encode_array_u8(u8) => U8 as encode_array_u64(u64),
+            encode_array_u16(u16) => U16 as encode_array_u64(u64),
+            encode_array_u32(u32) => U32 as encode_array_u64(u64),
+            encode_array_u64(u64) => U64 as encode_array_u64(u64),
+            encode_array_f32(f32) => F32 as encode_array_f64(f64),
+            encode_array_f64(f64) => F64 as encode_array_f64(f64)
+        }
+    };
+    (
+        @impl $body:ident;
+        $($method:ident($ty:ty) => $kind:ident as $widest:ident($wide:ty)),+;
+        $($array:ident($elem:ty) => $elem_kind:ident as $elem_widest:ident($elem_wide:ty)),+
+    ) => {
+        $(
+            fn $method(self, value: $ty) -> Result<Self::Ok, Self::Error> {
+                $body!(self, $method, value: $ty => $kind as $widest($wide))
+            }
+        )+
+
+        $(
+            fn $array<T, S>(self, chunks: S) -> Result<Self::Ok, Self::Error>
+            where
+                T: IntoIterator<Item = $elem> + Send + Unpin + 'en,
+                S: ::futures::stream::Stream<Item = T> + Send + Unpin + 'en,
+                <T as IntoIterator>::IntoIter: Send + Unpin + 'en,
+            {
+                $body!(self, $array, chunks: [$elem] => $elem_kind as $elem_widest($elem_wide))
+            }
+        )+
+    };
+}
+
+/// A body for [`encode_primitives`] which forwards each value as-is to the wrapped `encoder`.
+macro_rules! forward_primitive {
+    ($this:ident, $method:ident, $value:ident $($rest:tt)*) => {
+        $this.encoder.$method($value)
+    };
+}
+
+pub mod adapters;
+mod cancel;
+mod canonical;
+mod channel;
+mod checked;
+mod flatten;
 mod impls;
+mod key;
+mod memo;
+mod prefetch;
+mod redact;
+mod split;
+mod with;
+
+pub use cancel::{with_cancellation, with_deadline, Cancel};
+pub use canonical::{
+    canonical, Canonical, CanonicalForm, CanonicalKey, CanonicalMap, CanonicalSeq, Canonicalized,
+};
+pub use channel::{ErrorChannel, Guard, Watch};
+pub use checked::{Checked, CheckedMap, CheckedSeq, DebugChecked};
+pub use flatten::flatten_into;
+pub use key::KeyToString;
+pub use memo::{Memo, MemoCache, Memoized};
+pub use prefetch::{prefetch, Prefetch};
+pub use redact::{Redact, RedactMap, RedactSeq, Redacted, Redaction};
+pub use split::{encode_each, join_outputs, JoinOutputs};
+pub use with::{EncodeWith, IntoStreamWith};
 
 /// A stream encoding error
 pub trait Error {
@@ -98,12 +205,86 @@ impl<'en, K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en, S: Stream<Item = (
     }
 }
 
+// the `'en` bounds are kept so that this impl applies to exactly the same types as before
+#[allow(clippy::extra_unused_lifetimes)]
 impl<'en, K: 'en, V: 'en, S: Stream<Item = (K, V)> + 'en> From<S> for MapStream<K, V, S> {
     fn from(source: S) -> Self {
         Self { source }
     }
 }
 
+/// Returned from [`EncodeMap::finish`], [`EncodeSeq::finish`], and [`EncodeTuple::finish`]
+/// to show that a compound value was completely encoded.
+///
+/// A compound encoder which is dropped without calling `end` or `finish` will produce an
+/// incomplete encoding, or none at all.
+#[must_use = "an encoded stream does nothing unless it is consumed"]
+pub struct Finished<T>(T);
+
+impl<T> Finished<T> {
+    /// Return the encoded stream.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// How a text format should write a floating-point number, passed to
+/// [`Encoder::collect_f32_with`] and [`Encoder::collect_f64_with`].
+///
+/// This is a hint: binary formats, which encode the exact value, ignore it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum FloatFormat {
+    /// The shortest representation which decodes to the same value, e.g. `0.1`.
+    #[default]
+    Shortest,
+    /// A fixed number of digits after the decimal point, e.g. `0.100` for `Fixed(3)`.
+    Fixed(usize),
+    /// Scientific notation with the shortest mantissa which decodes to the same value,
+    /// e.g. `1e-1`.
+    Scientific,
+}
+
+impl FloatFormat {
+    /// Format the given floating-point number `v` as a string in this format.
+    pub fn format<F: fmt::Display + fmt::LowerExp>(self, v: F) -> String {
+        match self {
+            Self::Shortest => v.to_string(),
+            Self::Fixed(precision) => format!("{:.*}", precision, v),
+            Self::Scientific => format!("{:e}", v),
+        }
+    }
+}
+
+/// How a text format should lay out compound values, returned by [`Encoder::style`] and passed
+/// to [`Encoder::with_style`].
+///
+/// This is a hint: binary formats ignore it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum EncodeStyle {
+    /// Write each compound value on a single line, without any whitespace, e.g. `[1,2]`.
+    #[default]
+    Compact,
+    /// Write each element or entry of a compound value on its own line, indented by the given
+    /// number of spaces per level of nesting.
+    Pretty(usize),
+}
+
+impl EncodeStyle {
+    /// Return the whitespace which should precede an element or entry at the given `depth`,
+    /// i.e. nothing if this style is compact, or otherwise a newline and indentation.
+    pub fn indent(self, depth: usize) -> String {
+        match self {
+            Self::Compact => String::new(),
+            Self::Pretty(width) => {
+                let mut indent = String::with_capacity(1 + (width * depth));
+                indent.push('\n');
+                indent.push_str(&" ".repeat(width * depth));
+                indent
+            }
+        }
+    }
+}
+
 /// Returned from `Encoder::encode_map`.
 pub trait EncodeMap<'en> {
     /// Must match the `Ok` type of the parent [`Encoder`].
@@ -127,6 +308,17 @@ pub trait EncodeMap<'en> {
     /// bogus results.
     fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error>;
 
+    /// Encode a map key which is a string.
+    ///
+    /// The default implementation copies the `key` and delegates to [`encode_key`]. Formats
+    /// which only support string keys should override it to write the key directly, and can use
+    /// a [`KeyToString`] policy to convert other keys passed to [`encode_key`].
+    ///
+    /// [`encode_key`]: #tymethod.encode_key
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        self.encode_key(key.to_string())
+    }
+
     /// Encode a map entry consisting of a key and a value.
     ///
     /// The default implementation delegates to [`encode_key`] and [`encode_value`].
@@ -145,8 +337,29 @@ pub trait EncodeMap<'en> {
         self.encode_value(value)
     }
 
+    /// Hint that at least `additional` more entries will be encoded, e.g. when the length of the
+    /// map was not known when [`Encoder::encode_map`] was called.
+    ///
+    /// This is only a hint, which encoders are free to ignore. The default implementation does
+    /// nothing.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
     /// Finish encoding the map.
     fn end(self) -> Result<Self::Ok, Self::Error>;
+
+    /// Finish encoding the map, returning a [`Finished`] token which proves that it was ended.
+    ///
+    /// The default implementation delegates to [`end`].
+    ///
+    /// [`end`]: #tymethod.end
+    fn finish(self) -> Result<Finished<Self::Ok>, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.end().map(Finished)
+    }
 }
 
 /// Disambiguate a sequence from a map when encoding a stream.
@@ -160,6 +373,7 @@ impl<'en, T: IntoStream<'en> + 'en, S: Stream<Item = T> + 'en> SeqStream<T, S> {
     }
 }
 
+#[allow(clippy::extra_unused_lifetimes)]
 impl<'en, T: 'en, S: Stream<Item = T> + 'en> From<S> for SeqStream<T, S> {
     fn from(source: S) -> Self {
         Self { source }
@@ -177,8 +391,50 @@ pub trait EncodeSeq<'en> {
     /// Encode the next element in the sequence.
     fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error>;
 
+    /// Encode the next element in the sequence, a `u8`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
+    /// Encode the next element in the sequence, a `u64`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
+    /// Encode the next element in the sequence, an `f64`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
+    /// Hint that at least `additional` more elements will be encoded, e.g. when the length of
+    /// the sequence was not known when [`Encoder::encode_seq`] was called.
+    ///
+    /// This is only a hint, which encoders are free to ignore. The default implementation does
+    /// nothing.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
     /// Finish encoding the sequence.
     fn end(self) -> Result<Self::Ok, Self::Error>;
+
+    /// Finish encoding the sequence, returning a [`Finished`] token which proves that it was ended.
+    ///
+    /// The default implementation delegates to [`end`].
+    ///
+    /// [`end`]: #tymethod.end
+    fn finish(self) -> Result<Finished<Self::Ok>, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.end().map(Finished)
+    }
 }
 
 /// Returned from `Encoder::encode_tuple`.
@@ -192,8 +448,41 @@ pub trait EncodeTuple<'en> {
     /// Encode the next element in the tuple.
     fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error>;
 
+    /// Encode the next element in the tuple, a `u8`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_u8(&mut self, value: u8) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
+    /// Encode the next element in the tuple, a `u64`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
+    /// Encode the next element in the tuple, an `f64`.
+    ///
+    /// Binary formats can override this to skip the generic [`IntoStream`] path.
+    fn encode_element_f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        self.encode_element(value)
+    }
+
     /// Finish encoding the tuple.
     fn end(self) -> Result<Self::Ok, Self::Error>;
+
+    /// Finish encoding the tuple, returning a [`Finished`] token which proves that it was ended.
+    ///
+    /// The default implementation delegates to [`end`].
+    ///
+    /// [`end`]: #tymethod.end
+    fn finish(self) -> Result<Finished<Self::Ok>, Self::Error>
+    where
+        Self: Sized,
+    {
+        self.end().map(Finished)
+    }
 }
 
 /// A data format that can encode and stream any data structure supported by destream.
@@ -254,6 +543,64 @@ pub trait Encoder<'en>: Sized {
     /// Encode an `f64` value.
     fn encode_f64(self, v: f64) -> Result<Self::Ok, Self::Error>;
 
+    /// Encode an `f32` value, written in the given `format` if this is a text format.
+    ///
+    /// The default implementation ignores the `format` and calls [`encode_f32`].
+    ///
+    /// [`encode_f32`]: #tymethod.encode_f32
+    fn collect_f32_with(self, v: f32, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        let _ = format;
+        self.encode_f32(v)
+    }
+
+    /// Encode an `f64` value, written in the given `format` if this is a text format.
+    ///
+    /// The default implementation ignores the `format` and calls [`encode_f64`].
+    ///
+    /// [`encode_f64`]: #tymethod.encode_f64
+    fn collect_f64_with(self, v: f64, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        let _ = format;
+        self.encode_f64(v)
+    }
+
+    /// Encode the given `value`, identified by the given `key`, which may be repeated many times
+    /// in the same document. A format may re-emit a cached encoding of an earlier value with the
+    /// same `key` instead of encoding it again (e.g. using a [`MemoCache`]).
+    ///
+    /// The default implementation encodes the value normally.
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let _ = key;
+        value.into_stream(self)
+    }
+
+    /// The [`EncodeStyle`] which this encoder uses to lay out compound values.
+    ///
+    /// The default implementation returns [`EncodeStyle::Compact`].
+    fn style(&self) -> EncodeStyle {
+        EncodeStyle::Compact
+    }
+
+    /// The nesting depth of the value which this encoder will encode, where a top-level value
+    /// has depth zero, so that a text format can indent it correctly.
+    ///
+    /// The default implementation returns zero.
+    fn depth(&self) -> usize {
+        0
+    }
+
+    /// Hint that the value which this encoder will encode, and its contents, should be laid out
+    /// in the given `style`, e.g. to keep a short tuple on one line in a pretty-printed document.
+    ///
+    /// The default implementation ignores the hint and returns this encoder unchanged.
+    fn with_style(self, style: EncodeStyle) -> Self {
+        let _ = style;
+        self
+    }
+
     /// Encode an array of `bool`s.
     fn encode_array_bool<T, S>(self, chunks: S) -> Result<Self::Ok, Self::Error>
     where
@@ -359,6 +706,7 @@ pub trait Encoder<'en>: Sized {
     ///
     /// The argument is the number of elements in the map, which may or may not be computable before
     /// iterating over the map.
+    #[must_use = "the map is not encoded until `end` or `finish` is called"]
     fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error>;
 
     /// Given a stream of encodable key-value pairs, return a stream encoded as a map.
@@ -373,6 +721,7 @@ pub trait Encoder<'en>: Sized {
     ///
     /// The argument is the number of elements in the sequence, which may or may not be computable
     /// before iterating over the sequence.
+    #[must_use = "the sequence is not encoded until `end` or `finish` is called"]
     fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error>;
 
     /// Given a stream of encodable values, return a stream encoded as a sequence.
@@ -384,6 +733,7 @@ pub trait Encoder<'en>: Sized {
     /// Begin encoding a statically sized sequence whose length will be known at decoding time
     /// without looking at the encoded data.
     /// This call must be followed by zero or more calls to `encode_element`, then `end`.
+    #[must_use = "the tuple is not encoded until `end` or `finish` is called"]
     fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error>;
 
     /// Collect an iterator of bytes.
@@ -451,6 +801,18 @@ pub trait IntoStream<'en> {
     fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error>;
 }
 
+/// Encode the given `value` using the given `encoder`.
+///
+/// This is equivalent to `value.into_stream(encoder)`, but gives application code
+/// a format-agnostic call shape.
+pub fn encode_into<'en, T, E>(value: T, encoder: E) -> Result<E::Ok, E::Error>
+where
+    T: IntoStream<'en>,
+    E: Encoder<'en>,
+{
+    value.into_stream(encoder)
+}
+
 fn iterator_len_hint<I>(iter: &I) -> Option<usize>
 where
     I: Iterator,
@@ -0,0 +1,57 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::future::{Future, FutureExt};
+use futures::stream::{Stream, StreamExt};
+
+/// Allow up to `window` items of the given `source` stream (e.g. the argument to
+/// [`Encoder::encode_seq_stream`](super::Encoder::encode_seq_stream)) to be pulled ahead of
+/// the consumer of the encoded output.
+///
+/// Returns a [`Prefetch`] stream to pass to the encoder in place of the `source`, and a future
+/// which pulls items from the `source` into a bounded buffer. The future must be spawned, or
+/// polled together with the encoded output (e.g. with `futures::join!`), so that the `source`
+/// keeps making progress while the consumer is busy. At most `window` items (and at least one)
+/// are ever buffered, so a slow consumer does not cause unbounded buffering.
+///
+/// The future completes when the `source` ends, or when the [`Prefetch`] stream is dropped.
+///
+/// Example:
+/// ```
+/// # use futures::stream::{self, StreamExt};
+/// # futures::executor::block_on(async {
+/// let (items, fill) = destream::en::prefetch(stream::iter(0..10), 4);
+/// let ((), items) = futures::join!(fill, items.collect::<Vec<_>>());
+/// assert_eq!(items, (0..10).collect::<Vec<_>>());
+/// # });
+/// ```
+pub fn prefetch<S>(source: S, window: usize) -> (Prefetch<S::Item>, impl Future<Output = ()>)
+where
+    S: Stream,
+{
+    // the sender has one guaranteed slot of its own in addition to the channel's buffer
+    let (sender, receiver) = mpsc::channel(window.saturating_sub(1));
+
+    // if the receiver is dropped, nobody is listening for the rest of the source
+    let fill = source.map(Ok).forward(sender).map(|_result| ());
+
+    (Prefetch { receiver }, fill)
+}
+
+/// A source stream which is pulled ahead of its consumer. Returned by [`prefetch`].
+pub struct Prefetch<T> {
+    receiver: mpsc::Receiver<T>,
+}
+
+impl<T> Stream for Prefetch<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cxt)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.receiver.size_hint()
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+
+use super::key::Key;
+use super::{
+    EncodeMap, EncodeSeq, EncodeStyle, EncodeTuple, Encoder, FloatFormat, IntoStream, ToStream,
+};
+
+/// A set of map keys whose entries should be redacted when encoding.
+///
+/// By default a redacted entry is dropped entirely. Use [`Redaction::replace_with`] to keep the
+/// key and encode a placeholder string in place of its value.
+#[derive(Clone, Debug, Default)]
+pub struct Redaction {
+    keys: HashSet<String>,
+    replacement: Option<String>,
+}
+
+impl Redaction {
+    /// Construct a new [`Redaction`] which drops map entries with any of the given `keys`.
+    pub fn new<I: IntoIterator<Item = K>, K: Into<String>>(keys: I) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            replacement: None,
+        }
+    }
+
+    /// Encode the given `replacement` in place of a redacted value, instead of dropping the entry.
+    pub fn replace_with<R: Into<String>>(mut self, replacement: R) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    /// Return `true` if a map entry with the given `key` will be redacted.
+    pub fn is_redacted(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    fn matches(&self, key: &Key) -> bool {
+        match key {
+            Key::Str(key) => self.is_redacted(key),
+            _ => false,
+        }
+    }
+}
+
+/// An [`Encoder`] which redacts map entries according to a [`Redaction`], recursively.
+///
+/// Map keys are inspected before being passed to the underlying encoder, so they must be
+/// primitive values (strings, numbers, booleans, bytes, or `Uuid`s). Only string keys can match.
+///
+/// If entries are dropped (rather than replaced) the length hint of a map is not forwarded,
+/// since the number of entries actually encoded may be smaller.
+pub struct Redact<E> {
+    encoder: E,
+    redaction: Arc<Redaction>,
+}
+
+impl<E> Redact<E> {
+    /// Wrap the given `encoder` so that it applies the given `redaction`.
+    pub fn new(encoder: E, redaction: Arc<Redaction>) -> Self {
+        Self { encoder, redaction }
+    }
+}
+
+/// A value which will be encoded with the given [`Redaction`] applied, recursively.
+pub struct Redacted<T> {
+    value: T,
+    redaction: Arc<Redaction>,
+}
+
+impl<T> Redacted<T> {
+    /// Apply the given `redaction` when encoding `value`.
+    pub fn new(value: T, redaction: Arc<Redaction>) -> Self {
+        Self { value, redaction }
+    }
+
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for Redacted<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.into_stream(Redact::new(encoder, self.redaction))
+    }
+}
+
+impl<'en, T: ToStream<'en> + 'en> ToStream<'en> for Redacted<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value
+            .to_stream(Redact::new(encoder, self.redaction.clone()))
+    }
+}
+
+enum RedactedValue<T> {
+    Value(Redacted<T>),
+    Replacement(String),
+}
+
+impl<'en, T: IntoStream<'en> + 'en> IntoStream<'en> for RedactedValue<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        match self {
+            Self::Value(value) => value.into_stream(encoder),
+            Self::Replacement(replacement) => encoder.encode_str(&replacement),
+        }
+    }
+}
+
+/// Decide how to encode a map entry, or return `None` if it should be dropped.
+fn redact_entry<V>(
+    redaction: &Arc<Redaction>,
+    key: Key,
+    value: V,
+) -> Option<(Key, RedactedValue<V>)> {
+    if redaction.matches(&key) {
+        let replacement = redaction.replacement.clone()?;
+        Some((key, RedactedValue::Replacement(replacement)))
+    } else {
+        let value = Redacted::new(value, redaction.clone());
+        Some((key, RedactedValue::Value(value)))
+    }
+}
+
+/// A stream of map entries with redacted entries removed or replaced.
+struct RedactEntries<S> {
+    source: S,
+    redaction: Arc<Redaction>,
+}
+
+impl<'en, K, V, S> Stream for RedactEntries<S>
+where
+    K: IntoStream<'en> + 'en,
+    V: IntoStream<'en> + 'en,
+    S: Stream<Item = (K, V)> + Unpin,
+{
+    type Item = (Key, RedactedValue<V>);
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            match ready!(self.source.poll_next_unpin(cxt)) {
+                Some((key, value)) => {
+                    if let Some(entry) = redact_entry(&self.redaction, Key::probe(key), value) {
+                        break Poll::Ready(Some(entry));
+                    }
+                }
+                None => break Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Returned from `Redact::encode_map`.
+pub struct RedactMap<M> {
+    map: M,
+    redaction: Arc<Redaction>,
+    redacted: bool,
+}
+
+impl<'en, M: EncodeMap<'en>> EncodeMap<'en> for RedactMap<M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        let key = Key::probe(key).check()?;
+        self.redacted = self.redaction.matches(&key);
+
+        if self.redacted && self.redaction.replacement.is_none() {
+            Ok(())
+        } else {
+            self.map.encode_key(key)
+        }
+    }
+
+    fn encode_key_str(&mut self, key: &str) -> Result<(), Self::Error> {
+        self.redacted = self.redaction.is_redacted(key);
+
+        if self.redacted && self.redaction.replacement.is_none() {
+            Ok(())
+        } else {
+            self.map.encode_key_str(key)
+        }
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        if self.redacted {
+            match &self.redaction.replacement {
+                Some(replacement) => self.map.encode_value(replacement.clone()),
+                None => Ok(()),
+            }
+        } else {
+            self.map
+                .encode_value(Redacted::new(value, self.redaction.clone()))
+        }
+    }
+
+    fn encode_entry<K: IntoStream<'en> + 'en, V: IntoStream<'en> + 'en>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), Self::Error> {
+        let key = Key::probe(key).check()?;
+        self.redacted = false;
+
+        match redact_entry(&self.redaction, key, value) {
+            Some((key, value)) => self.map.encode_entry(key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.map.end()
+    }
+}
+
+/// Returned from `Redact::encode_seq` and `Redact::encode_tuple`.
+pub struct RedactSeq<S> {
+    seq: S,
+    redaction: Arc<Redaction>,
+}
+
+// primitive elements have no keys to redact
+macro_rules! forward_element {
+    ($($method:ident($ty:ty)),+) => {
+        $(
+            fn $method(&mut self, value: $ty) -> Result<(), Self::Error> {
+                self.seq.$method(value)
+            }
+        )+
+    };
+}
+
+impl<'en, S: EncodeSeq<'en>> EncodeSeq<'en> for RedactSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq
+            .encode_element(Redacted::new(value, self.redaction.clone()))
+    }
+
+    forward_element!(
+        encode_element_u8(u8),
+        encode_element_u64(u64),
+        encode_element_f64(f64)
+    );
+
+    fn reserve(&mut self, additional: usize) {
+        self.seq.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.seq.end()
+    }
+}
+
+impl<'en, S: EncodeTuple<'en>> EncodeTuple<'en> for RedactSeq<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.seq
+            .encode_element(Redacted::new(value, self.redaction.clone()))
+    }
+
+    forward_element!(
+        encode_element_u8(u8),
+        encode_element_u64(u64),
+        encode_element_f64(f64)
+    );
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.seq.end()
+    }
+}
+
+impl<'en, E: Encoder<'en>> Encoder<'en> for Redact<E> {
+    type Ok = E::Ok;
+    type Error = E::Error;
+    type EncodeMap = RedactMap<E::EncodeMap>;
+    type EncodeSeq = RedactSeq<E::EncodeSeq>;
+    type EncodeTuple = RedactSeq<E::EncodeTuple>;
+
+    encode_primitives!(forward_primitive);
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_none()
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        self.encoder
+            .encode_some(Redacted::new(value, self.redaction))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_unit()
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.encode_bytes(bytes)
+    }
+
+    fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        let len = if self.redaction.replacement.is_some() {
+            len
+        } else {
+            None
+        };
+
+        Ok(RedactMap {
+            map: self.encoder.encode_map(len)?,
+            redaction: self.redaction,
+            redacted: false,
+        })
+    }
+
+    fn encode_map_stream<K, V, S>(self, map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        let map = RedactEntries {
+            source: map,
+            redaction: self.redaction,
+        };
+
+        self.encoder.encode_map_stream(map)
+    }
+
+    fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Ok(RedactSeq {
+            seq: self.encoder.encode_seq(len)?,
+            redaction: self.redaction,
+        })
+    }
+
+    fn encode_seq_stream<T, S>(self, seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        let redaction = self.redaction;
+        let seq = seq.map(move |value| Redacted::new(value, redaction.clone()));
+        self.encoder.encode_seq_stream(seq)
+    }
+
+    fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Ok(RedactSeq {
+            seq: self.encoder.encode_tuple(len)?,
+            redaction: self.redaction,
+        })
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_bytes(bytes)
+    }
+
+    fn collect_f32_with(self, v: f32, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_f32_with(v, format)
+    }
+
+    fn collect_f64_with(self, v: f64, format: FloatFormat) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_f64_with(v, format)
+    }
+
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.encoder
+            .encode_memoized(key, Redacted::new(value, self.redaction))
+    }
+
+    fn style(&self) -> EncodeStyle {
+        self.encoder.style()
+    }
+
+    fn depth(&self) -> usize {
+        self.encoder.depth()
+    }
+
+    fn with_style(self, style: EncodeStyle) -> Self {
+        Self {
+            encoder: self.encoder.with_style(style),
+            ..self
+        }
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.encoder.collect_str(value)
+    }
+}
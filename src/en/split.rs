@@ -0,0 +1,87 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{Stream, StreamExt};
+
+use super::{Encoder, ToStream};
+
+/// Encode the same `value` with each of the given `encoders`, e.g. to persist a canonical
+/// binary form of a value while also returning it to a client as JSON.
+///
+/// The value is shared rather than copied, but it's traversed once per encoder. To encode with
+/// encoders of different types, call [`ToStream::to_stream`] once with each encoder instead.
+/// Use [`join_outputs`] to drive the encoded outputs concurrently.
+pub fn encode_each<'en, T, E, I>(value: &'en T, encoders: I) -> Result<Vec<E::Ok>, E::Error>
+where
+    T: ToStream<'en> + ?Sized,
+    E: Encoder<'en>,
+    I: IntoIterator<Item = E>,
+{
+    encoders
+        .into_iter()
+        .map(|encoder| value.to_stream(encoder))
+        .collect()
+}
+
+/// Poll each of the given encoded `outputs` concurrently, yielding each chunk along with the
+/// index of the output it came from, so that the caller can route it (e.g. to a file or to a
+/// client).
+///
+/// The outputs are polled in turn, so that one fast output doesn't starve the others. The joined
+/// stream ends once every output has ended, or with the first error from any output, after which
+/// the other outputs are dropped. Outputs whose items differ in type can be mapped to a common
+/// type first, e.g. with `StreamExt::map`.
+pub fn join_outputs<S, I>(outputs: I) -> JoinOutputs<S>
+where
+    I: IntoIterator<Item = S>,
+{
+    JoinOutputs {
+        outputs: outputs.into_iter().map(Some).collect(),
+        next: 0,
+    }
+}
+
+/// A set of encoded output streams polled concurrently. Returned by [`join_outputs`].
+pub struct JoinOutputs<S> {
+    outputs: Vec<Option<S>>,
+    next: usize,
+}
+
+impl<T, X, S> Stream for JoinOutputs<S>
+where
+    S: Stream<Item = Result<T, X>> + Unpin,
+{
+    type Item = Result<(usize, T), X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let len = self.outputs.len();
+
+        for offset in 0..len {
+            let index = (self.next + offset) % len;
+
+            let output = match self.outputs[index].as_mut() {
+                Some(output) => output,
+                None => continue,
+            };
+
+            match output.poll_next_unpin(cxt) {
+                Poll::Pending => {}
+                Poll::Ready(None) => self.outputs[index] = None,
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.next = (index + 1) % len;
+                    return Poll::Ready(Some(Ok((index, chunk))));
+                }
+                Poll::Ready(Some(Err(cause))) => {
+                    self.outputs.clear();
+                    return Poll::Ready(Some(Err(cause)));
+                }
+            }
+        }
+
+        if self.outputs.iter().all(Option::is_none) {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Encoder, IntoStream};
+
+/// A data structure which is serialized using an encoding environment `Cx`, such as a blob
+/// store used to resolve references. This is the encoding counterpart of
+/// [`FromStream::Context`](crate::de::FromStream::Context).
+///
+/// This trait is automatically implemented for every [`IntoStream`] type, which ignores the
+/// environment. To encode a nested value which needs the environment, wrap it in an
+/// [`EncodeWith`].
+pub trait IntoStreamWith<'en, Cx> {
+    /// Take ownership of this value and serialize it into the given encoder,
+    /// using the given `context`.
+    fn into_stream_with<E: Encoder<'en>>(self, context: Cx, encoder: E) -> Result<E::Ok, E::Error>;
+}
+
+impl<'en, T: IntoStream<'en>, Cx> IntoStreamWith<'en, Cx> for T {
+    fn into_stream_with<E: Encoder<'en>>(
+        self,
+        _context: Cx,
+        encoder: E,
+    ) -> Result<E::Ok, E::Error> {
+        self.into_stream(encoder)
+    }
+}
+
+/// A value paired with the encoding environment it needs, which implements [`IntoStream`].
+pub struct EncodeWith<T, Cx> {
+    value: T,
+    context: Cx,
+}
+
+impl<T, Cx> EncodeWith<T, Cx> {
+    /// Pair the given `value` with the given `context`.
+    pub fn new(value: T, context: Cx) -> Self {
+        Self { value, context }
+    }
+}
+
+impl<'en, T: IntoStreamWith<'en, Cx>, Cx> IntoStream<'en> for EncodeWith<T, Cx> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.value.into_stream_with(self.context, encoder)
+    }
+}
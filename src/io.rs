@@ -0,0 +1,294 @@
+//! Adapters between encoded byte values and asynchronous I/O, e.g. to save an uploaded blob to a
+//! file as it's decoded, or to embed the contents of a file in an encoded document, without
+//! holding the whole blob in memory.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use bytes::BytesMut;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::ready;
+use futures::stream::{self, Stream, StreamExt};
+use futures::TryFutureExt;
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{self, Encoder, IntoStream};
+
+const BUF_SIZE: usize = 4_096;
+
+/// A byte value decoded by writing it directly into the writer given as its context.
+///
+/// A `WriteTo` is decoded from a bytes value (streamed in chunks if the format supports it), a
+/// sequence of bytes, or a base64-encoded string like [`Bytes`](bytes::Bytes). The writer is
+/// flushed once the whole value has been written. An I/O error is returned as a decoding error.
+///
+/// Example:
+/// ```
+/// # use destream::io::WriteTo;
+/// # async fn save<D: destream::Decoder>(decoder: &mut D) -> Result<(), D::Error> {
+/// let file = Vec::<u8>::new(); // e.g. an async file handle
+/// let blob: WriteTo<Vec<u8>> = destream::FromStream::from_stream(file, decoder).await?;
+/// println!("wrote {} bytes", blob.written());
+/// # Ok(())
+/// # }
+/// ```
+pub struct WriteTo<W> {
+    writer: W,
+    written: u64,
+}
+
+impl<W> WriteTo<W> {
+    /// The number of bytes written.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Return the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> fmt::Debug for WriteTo<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(wrote {} bytes)", self.written)
+    }
+}
+
+impl<W: AsyncWrite + Send + Unpin> WriteTo<W> {
+    async fn write<E: de::Error>(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.writer.write_all(bytes).await.map_err(E::custom)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    async fn finish<E: de::Error>(mut self) -> Result<Self, E> {
+        self.writer.flush().await.map_err(E::custom)?;
+        Ok(self)
+    }
+}
+
+struct WriteToVisitor<W> {
+    target: WriteTo<W>,
+}
+
+enum Written<W> {
+    Complete(WriteTo<W>),
+    // writing is async, so a base64-encoded string is decoded eagerly and written afterward
+    Pending(WriteTo<W>, Vec<u8>),
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Send + Unpin> Visitor for WriteToVisitor<W> {
+    type Value = Written<W>;
+
+    fn expecting() -> &'static str {
+        "a byte string"
+    }
+
+    async fn visit_array_u8<A: ArrayAccess<u8>>(
+        mut self,
+        mut array: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut buffer = [0u8; BUF_SIZE];
+
+        loop {
+            let len = array.buffer(&mut buffer).await?;
+            if len == 0 {
+                break;
+            }
+
+            self.target.write(&buffer[..len]).await?;
+        }
+
+        self.target.finish().map_ok(Written::Complete).await
+    }
+
+    async fn visit_seq<A: SeqAccess>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut buffer = Vec::with_capacity(BUF_SIZE);
+
+        while let Some(byte) = seq.next_element(()).await? {
+            buffer.push(byte);
+
+            if buffer.len() == BUF_SIZE {
+                self.target.write(&buffer).await?;
+                buffer.clear();
+            }
+        }
+
+        self.target.write(&buffer).await?;
+        self.target.finish().map_ok(Written::Complete).await
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::engine::Engine;
+
+        match STANDARD.decode(&v) {
+            Ok(bytes) => Ok(Written::Pending(self.target, bytes)),
+            Err(_cause) => Err(de::Error::invalid_value(v, "a base64-encoded string")),
+        }
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Send + Unpin> FromStream for WriteTo<W> {
+    type Context = W;
+
+    async fn from_stream<D: Decoder>(writer: W, decoder: &mut D) -> Result<Self, D::Error> {
+        let target = WriteTo { writer, written: 0 };
+
+        match decoder.decode_bytes(WriteToVisitor { target }).await? {
+            Written::Complete(target) => Ok(target),
+            Written::Pending(mut target, bytes) => {
+                target.write(&bytes).await?;
+                target.finish().await
+            }
+        }
+    }
+}
+
+/// The contents of a reader, encoded as a bytes value which is streamed in chunks as it's read.
+///
+/// The encoder's chunk stream can't return an error, so if reading fails the bytes value just
+/// ends early. [`ReadFrom::encode`] checks the encoded output stream and ends it with an error
+/// if reading fails. To embed the contents of a reader in a larger value, take its
+/// [`ReadFrom::status`] before encoding the larger value, and check the output of the larger
+/// value with [`ReadStatus::check`].
+pub struct ReadFrom<R> {
+    reader: R,
+    status: ReadStatus,
+}
+
+impl<R> ReadFrom<R> {
+    /// Construct a new [`ReadFrom`] value to encode the contents of the given `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            status: ReadStatus::default(),
+        }
+    }
+
+    /// Encode the contents of the reader with the given `encode` function, e.g.
+    /// `format::encode`, and return its output stream, which ends with an error if reading fails.
+    pub fn encode<F, S, E>(self, encode: F) -> Result<CheckRead<S>, E>
+    where
+        F: FnOnce(Self) -> Result<S, E>,
+    {
+        let status = self.status();
+        encode(self).map(|output| status.check(output))
+    }
+
+    /// Return a handle to check whether reading failed while this value was encoded.
+    pub fn status(&self) -> ReadStatus {
+        self.status.clone()
+    }
+
+    /// Return the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<'en, R: AsyncRead + Send + Unpin + 'en> IntoStream<'en> for ReadFrom<R> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let status = self.status;
+
+        // each chunk is split off of the same buffer, which is reused once its chunks are dropped
+        let state = Some((self.reader, BytesMut::new()));
+
+        let chunks = stream::unfold(state, move |state| {
+            let status = status.clone();
+
+            async move {
+                let (mut reader, mut buffer) = state?;
+                buffer.resize(BUF_SIZE, 0);
+
+                loop {
+                    match reader.read(&mut buffer).await {
+                        Ok(0) => break None,
+                        Ok(len) => {
+                            let chunk = buffer.split_to(len).freeze();
+                            break Some((chunk, Some((reader, buffer))));
+                        }
+                        Err(cause) if cause.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(cause) => {
+                            let _ = status.error.set(cause);
+                            break None;
+                        }
+                    }
+                }
+            }
+        });
+
+        encoder.encode_array_u8(Box::pin(chunks))
+    }
+}
+
+/// A handle to check whether a [`ReadFrom`] value failed to read its contents while encoding.
+#[derive(Clone, Default)]
+pub struct ReadStatus {
+    error: Arc<OnceLock<io::Error>>,
+}
+
+impl ReadStatus {
+    /// Return the error which ended the read, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.get()
+    }
+
+    /// End the given encoded `output` stream with an error as soon as reading fails.
+    pub fn check<S>(self, output: S) -> CheckRead<S> {
+        CheckRead {
+            output: Some(output),
+            status: self,
+        }
+    }
+}
+
+impl fmt::Debug for ReadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error() {
+            Some(cause) => write!(f, "(read failed: {cause})"),
+            None => f.write_str("(read ok)"),
+        }
+    }
+}
+
+/// An encoded output stream which ends with an error if reading a [`ReadFrom`] value fails.
+/// Returned by [`ReadStatus::check`].
+pub struct CheckRead<S> {
+    output: Option<S>,
+    status: ReadStatus,
+}
+
+impl<T, X, S> Stream for CheckRead<S>
+where
+    S: Stream<Item = Result<T, X>> + Unpin,
+    X: en::Error,
+{
+    type Item = Result<T, X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        let item = match self.output.as_mut() {
+            Some(output) => ready!(output.poll_next_unpin(cxt)),
+            None => return Poll::Ready(None),
+        };
+
+        if let Some(cause) = self.status.error() {
+            let error = X::custom(format_args!("failed to read a bytes value: {cause}"));
+            self.output = None;
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        if item.is_none() {
+            self.output = None;
+        }
+
+        Poll::Ready(item)
+    }
+}
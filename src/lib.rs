@@ -16,9 +16,9 @@
 //! Important differences between `destream` and `serde`:
 //!  - `destream` supports decoding from and encoding to a `futures::Stream` (obviously).
 //!  - `destream` does not (yet) support the `derive` macro, so you can't derive `FromStream` or
-//!     `ToStream`, and there is no built-in functionality for decoding/encoding a given `struct`.
+//!    `ToStream`, and there is no built-in functionality for decoding/encoding a given `struct`.
 //!  - `Decoder` assumes the static lifetime and only supports owned types, but `Encoder` uses a
-//!     specific lifetime `'en`. This is the opposite of `serde`.
+//!    specific lifetime `'en`. This is the opposite of `serde`.
 //!
 //! `destream` itself does not implement support for any specific serialization format.
 //! [`destream_json`] provides support for streaming JSON.
@@ -26,10 +26,35 @@
 //! [`destream_json`]: http://docs.rs/destream_json/
 //! [`serde`]: http://docs.rs/serde
 
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
 pub mod de;
+pub mod diff;
 pub mod en;
+pub mod io;
+pub mod os;
+pub mod patch;
+#[cfg(feature = "reference-binary")]
+pub mod reference;
+pub mod throttle;
+pub mod types;
+pub mod value;
+#[cfg(feature = "ws")]
+pub mod ws;
 
-pub use de::{ArrayAccess, Decoder, FromStream, IgnoredAny, MapAccess, SeqAccess, Visitor};
+pub use de::{
+    decode_from, try_decode, ArrayAccess, Decoder, FromStream, IgnoredAny, MapAccess, SeqAccess,
+    TextAccess, Visitor,
+};
+pub use diff::diff;
 pub use en::{
-    EncodeMap, EncodeSeq, EncodeTuple, Encoder, IntoStream, MapStream, SeqStream, ToStream,
+    encode_into, EncodeMap, EncodeSeq, EncodeTuple, Encoder, IntoStream, MapStream, SeqStream,
+    ToStream,
 };
+pub use types::OrderedEntries;
+pub use value::Value;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+}
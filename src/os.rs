@@ -0,0 +1,211 @@
+//! Support for snapshots of commonly-reported operating system data, such as the exit status
+//! and resource usage of a child process, or its environment.
+//!
+//! An [`OsString`] is encoded as a string if it's valid UTF-8, and otherwise as bytes
+//! (on Unix) or as a lossy UTF-8 string (elsewhere). So an environment snapshot can be encoded
+//! and decoded directly as a `Vec<(OsString, OsString)>`, e.g. from [`std::env::vars_os`].
+//! Since the decoder has to tell a string from bytes, an [`OsString`] is decoded with
+//! [`Decoder::decode_any`], which requires a self-describing format.
+
+use std::ffi::OsString;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::TryFutureExt;
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+struct OsStringVisitor;
+
+#[async_trait]
+impl Visitor for OsStringVisitor {
+    type Value = OsString;
+
+    fn expecting() -> &'static str {
+        "an OS string"
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    async fn visit_array_u8<A: ArrayAccess<u8>>(
+        self,
+        mut array: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+        let mut buffer = [0u8; 4_096];
+
+        loop {
+            let len = array.buffer(&mut buffer).await?;
+            if len == 0 {
+                break;
+            }
+
+            bytes.extend_from_slice(&buffer[..len]);
+        }
+
+        os_string_from_bytes(bytes)
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+
+        while let Some(byte) = seq.next_element(()).await? {
+            bytes.push(byte);
+        }
+
+        os_string_from_bytes(bytes)
+    }
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes<E: de::Error>(bytes: Vec<u8>) -> Result<OsString, E> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes<E: de::Error>(bytes: Vec<u8>) -> Result<OsString, E> {
+    String::from_utf8(bytes)
+        .map(OsString::from)
+        .map_err(|cause| de::Error::invalid_value(cause, "a UTF-8 string"))
+}
+
+#[async_trait]
+impl FromStream for OsString {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_any(OsStringVisitor).await
+    }
+}
+
+impl<'en> IntoStream<'en> for OsString {
+    #[cfg(unix)]
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        use std::os::unix::ffi::OsStringExt;
+
+        match self.into_string() {
+            Ok(string) => encoder.encode_str(&string),
+            Err(os_string) => encoder.encode_bytes(os_string.into_vec()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.to_string_lossy())
+    }
+}
+
+impl<'en> ToStream<'en> for OsString {
+    #[cfg(unix)]
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        use std::os::unix::ffi::OsStrExt;
+
+        match self.to_str() {
+            Some(string) => encoder.encode_str(string),
+            None => encoder.collect_bytes(self.as_bytes().iter().copied()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.to_string_lossy())
+    }
+}
+
+/// A snapshot of the [`ExitStatus`] of a process, encoded as a tuple `(code, signal)`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ExitStatusSnapshot {
+    /// The exit code of the process, if it exited normally.
+    pub code: Option<i32>,
+    /// The signal which terminated the process, if any (always `None` except on Unix).
+    pub signal: Option<i32>,
+}
+
+impl ExitStatusSnapshot {
+    /// Return `true` if the process exited successfully.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl From<ExitStatus> for ExitStatusSnapshot {
+    fn from(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            code: status.code(),
+            signal,
+        }
+    }
+}
+
+#[async_trait]
+impl FromStream for ExitStatusSnapshot {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        <(Option<i32>, Option<i32>)>::from_stream(context, decoder)
+            .map_ok(|(code, signal)| Self { code, signal })
+            .await
+    }
+}
+
+impl<'en> IntoStream<'en> for ExitStatusSnapshot {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        (self.code, self.signal).into_stream(encoder)
+    }
+}
+
+impl<'en> ToStream<'en> for ExitStatusSnapshot {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        (*self).into_stream(encoder)
+    }
+}
+
+/// A snapshot of the resources used by a process, e.g. as reported by `getrusage`,
+/// encoded as a tuple `(user_time, system_time, max_rss)`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ResourceUsage {
+    /// The CPU time spent in user mode.
+    pub user_time: Duration,
+    /// The CPU time spent in kernel mode.
+    pub system_time: Duration,
+    /// The maximum resident set size, in kilobytes.
+    pub max_rss: u64,
+}
+
+#[async_trait]
+impl FromStream for ResourceUsage {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        <(Duration, Duration, u64)>::from_stream(context, decoder)
+            .map_ok(|(user_time, system_time, max_rss)| Self {
+                user_time,
+                system_time,
+                max_rss,
+            })
+            .await
+    }
+}
+
+impl<'en> IntoStream<'en> for ResourceUsage {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        (self.user_time, self.system_time, self.max_rss).into_stream(encoder)
+    }
+}
+
+impl<'en> ToStream<'en> for ResourceUsage {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        (*self).into_stream(encoder)
+    }
+}
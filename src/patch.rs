@@ -0,0 +1,366 @@
+//! Apply a JSON merge patch to a self-describing stream while transcoding it to an encoder.
+//!
+//! Use [`transcode`] to apply a small patch to a large document with constant memory, e.g. to
+//! apply overrides to a configuration file as it's copied. Only the keys present in the patch
+//! are held in memory: the rest of the document is streamed from the decoder to the encoder.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, BoxFuture, Either, FutureExt};
+use futures::ready;
+use futures::sink::SinkExt;
+use futures::stream::{Stream, StreamExt};
+
+use crate::de::{
+    self, ArrayAccess, Decoder, FromStream, IgnoredAny, MapAccess, SeqAccess, TextAccess, Visitor,
+};
+use crate::en::{self, Encoder, IntoStream};
+use crate::value::{merge, Value, ValueVisitor};
+
+/// The number of entries, elements, or array chunks to buffer before pausing decoding.
+const NODE_BUFFER: usize = 32;
+
+/// The maximum number of values in each chunk of a streamed array.
+const BUF_SIZE: usize = 1_024;
+
+/// Decode a value with `decoder`, apply the given merge `patch` as if by [`merge`], and encode
+/// the result with the given `encode` function, e.g. `format::encode`, as it's decoded.
+///
+/// Returns the encoded output stream once the kind of the base value is known. Decoding the
+/// rest of the base value is driven by polling the output stream, which ends with an error if
+/// decoding fails.
+///
+/// Any part of the base value which the patch replaces or removes is skipped rather than
+/// decoded. Every other map, sequence, and typed array is streamed, so memory use does not
+/// depend on the size of the base value. Map keys, strings, and scalars are decoded in full.
+pub async fn transcode<'a, D, F, S, T, X>(
+    patch: Value,
+    decoder: &'a mut D,
+    encode: F,
+) -> Result<Transcode<'a, S, D::Error>, X>
+where
+    D: Decoder + 'a,
+    F: FnOnce(Patched) -> Result<S, X>,
+    S: Stream<Item = Result<T, X>> + Unpin,
+    X: en::Error,
+{
+    let (root, receiver) = oneshot::channel();
+    let decode = async move {
+        let Streamed = Streamed::from_stream((Slot::Root(root), Some(patch)), decoder).await?;
+        Ok(())
+    };
+
+    // decode until the kind of the root value is known, so that it can be passed to `encode`
+    let (root, decode) = match future::select(decode.boxed(), receiver).await {
+        Either::Left((Ok(()), receiver)) => (receiver.await, None),
+        Either::Left((Err(cause), _receiver)) => return Err(X::custom(cause)),
+        Either::Right((root, decode)) => (root, Some(decode)),
+    };
+
+    let root = root.map_err(|_| X::custom("the base value was not decoded"))?;
+    let output = encode(Patched(root))?;
+
+    Ok(Transcode {
+        decode,
+        output: Some(output),
+    })
+}
+
+/// The encoded output stream of [`transcode`], which drives decoding the base value.
+pub struct Transcode<'a, S, E> {
+    decode: Option<BoxFuture<'a, Result<(), E>>>,
+    output: Option<S>,
+}
+
+impl<'a, T, X, S, E> Stream for Transcode<'a, S, E>
+where
+    S: Stream<Item = Result<T, X>> + Unpin,
+    X: en::Error,
+    E: de::Error,
+{
+    type Item = Result<T, X>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Some(decode) = self.decode.as_mut() {
+            match decode.poll_unpin(cxt) {
+                Poll::Pending => {}
+                Poll::Ready(Ok(())) => self.decode = None,
+                Poll::Ready(Err(cause)) => {
+                    self.decode = None;
+                    self.output = None;
+                    return Poll::Ready(Some(Err(X::custom(cause))));
+                }
+            }
+        }
+
+        let output = match self.output.as_mut() {
+            Some(output) => output,
+            None => return Poll::Ready(None),
+        };
+
+        let item = ready!(output.poll_next_unpin(cxt));
+
+        if item.is_none() {
+            self.output = None;
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+/// A value decoded with a merge patch applied, which is encoded as it's decoded.
+///
+/// Passed to the `encode` function given to [`transcode`].
+pub struct Patched(Node);
+
+impl<'en> IntoStream<'en> for Patched {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.0.into_stream(encoder)
+    }
+}
+
+macro_rules! node {
+    ($($array:ident($elem:ty) => $encode_array:ident),+) => {
+        /// A patched value, or a channel which receives the contents of a value being decoded.
+        enum Node {
+            Value(Value),
+            Map(mpsc::Receiver<(Value, Node)>),
+            Seq(mpsc::Receiver<Node>),
+            $($array(mpsc::Receiver<Vec<$elem>>)),+
+        }
+
+        impl<'en> IntoStream<'en> for Node {
+            fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                match self {
+                    Self::Value(value) => value.into_stream(encoder),
+                    Self::Map(entries) => encoder.encode_map_stream(entries),
+                    Self::Seq(elements) => encoder.encode_seq_stream(elements),
+                    $(Self::$array(chunks) => encoder.$encode_array(chunks)),+
+                }
+            }
+        }
+    };
+}
+
+node!(
+    ArrayBool(bool) => encode_array_bool,
+    ArrayI8(i8) => encode_array_i8,
+    ArrayI16(i16) => encode_array_i16,
+    ArrayI32(i32) => encode_array_i32,
+    ArrayI64(i64) => encode_array_i64,
+    ArrayU8(u8) => encode_array_u8,
+    ArrayU16(u16) => encode_array_u16,
+    ArrayU32(u32) => encode_array_u32,
+    ArrayU64(u64) => encode_array_u64,
+    ArrayF32(f32) => encode_array_f32,
+    ArrayF64(f64) => encode_array_f64
+);
+
+/// Where to send a decoded [`Node`].
+enum Slot {
+    Root(oneshot::Sender<Node>),
+    Entry(mpsc::Sender<(Value, Node)>, Value),
+    Element(mpsc::Sender<Node>),
+}
+
+impl Slot {
+    async fn emit(self, node: Node) {
+        // if the receiver was dropped, nobody is listening for the encoded output
+        match self {
+            Self::Root(root) => {
+                let _ = root.send(node);
+            }
+            Self::Entry(mut entries, key) => {
+                let _ = entries.send((key, node)).await;
+            }
+            Self::Element(mut elements) => {
+                let _ = elements.send(node).await;
+            }
+        }
+    }
+}
+
+/// A value which has been decoded and sent to its [`Slot`].
+struct Streamed;
+
+#[async_trait]
+impl FromStream for Streamed {
+    type Context = (Slot, Option<Value>);
+
+    async fn from_stream<D: Decoder>(
+        (slot, patch): Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let patch = match patch {
+            // the patch replaces the base value entirely, so there's no need to decode it
+            Some(patch) if patch.as_map().is_none() => {
+                decoder.skip_value().await?;
+                slot.emit(Node::Value(patch)).await;
+                return Ok(Self);
+            }
+            patch => patch,
+        };
+
+        if let Some((slot, leaf)) = decoder.decode_any(PatchVisitor { slot, patch }).await? {
+            slot.emit(Node::Value(leaf)).await;
+        }
+
+        Ok(Self)
+    }
+}
+
+/// Decodes a value with a merge `patch` applied, if there is one, in which case it's a map.
+struct PatchVisitor {
+    slot: Slot,
+    patch: Option<Value>,
+}
+
+impl PatchVisitor {
+    // a leaf value is returned with its slot so that it can be emitted after the visitor
+    // returns, since the primitive visit methods are not async
+    fn leaf(self, value: Value) -> Option<(Slot, Value)> {
+        let value = match self.patch {
+            Some(patch) => merge(value, patch),
+            None => value,
+        };
+
+        Some((self.slot, value))
+    }
+}
+
+macro_rules! patch_visitor {
+    (
+        $($visit:ident($ty:ty) => $kind:ident),+;
+        $($visit_array:ident($elem:ty) => $array:ident),+
+    ) => {
+        #[async_trait]
+        impl Visitor for PatchVisitor {
+            type Value = Option<(Slot, Value)>;
+
+            fn expecting() -> &'static str {
+                "any value"
+            }
+
+            $(
+                fn $visit<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    ValueVisitor.$visit(v).map(|leaf| self.leaf(leaf))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    mut array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    if self.patch.is_some() {
+                        let IgnoredAny = IgnoredAny.$visit_array(array).await?;
+                        return Ok(self.leaf(Value::None));
+                    }
+
+                    let (mut chunks, receiver) = mpsc::channel(NODE_BUFFER);
+                    self.slot.emit(Node::$array(receiver)).await;
+
+                    loop {
+                        let mut chunk = vec![<$elem>::default(); BUF_SIZE];
+                        let len = array.buffer(&mut chunk).await?;
+                        if len == 0 {
+                            break;
+                        }
+
+                        chunk.truncate(len);
+                        let _ = chunks.send(chunk).await;
+                    }
+
+                    Ok(None)
+                }
+            )+
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(self.leaf(v.into()))
+            }
+
+            async fn visit_text_stream<A: TextAccess>(
+                self,
+                text: A,
+            ) -> Result<Self::Value, A::Error> {
+                let leaf = ValueVisitor.visit_text_stream(text).await?;
+                Ok(self.leaf(leaf))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(self.leaf(Value::Unit))
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(self.leaf(Value::None))
+            }
+
+            async fn visit_some<D: Decoder>(
+                self,
+                decoder: &mut D,
+            ) -> Result<Self::Value, D::Error> {
+                let Streamed = Streamed::from_stream((self.slot, self.patch), decoder).await?;
+                Ok(None)
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                // only the entries of the patch are buffered
+                let mut patch = match self.patch {
+                    Some(Value::Map(patch)) => {
+                        Arc::try_unwrap(patch).unwrap_or_else(|patch| (*patch).clone())
+                    }
+                    _ => Vec::new(),
+                };
+
+                let (mut entries, receiver) = mpsc::channel(NODE_BUFFER);
+                self.slot.emit(Node::Map(receiver)).await;
+
+                while let Some(key) = map.next_key::<Value>(()).await? {
+                    let patch = match patch.iter().position(|(k, _)| k == &key) {
+                        Some(i) => Some(patch.remove(i).1),
+                        None => None,
+                    };
+
+                    match patch {
+                        Some(patch) if patch.is_none() => map.skip_value().await?,
+                        patch => {
+                            let slot = Slot::Entry(entries.clone(), key);
+                            let Streamed = map.next_value((slot, patch)).await?;
+                        }
+                    }
+                }
+
+                for (key, patch) in patch {
+                    if !patch.is_none() {
+                        let _ = entries.send((key, Node::Value(merge(Value::None, patch)))).await;
+                    }
+                }
+
+                Ok(None)
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                if self.patch.is_some() {
+                    while let Some(IgnoredAny) = seq.next_element(()).await? {}
+                    return Ok(self.leaf(Value::None));
+                }
+
+                let (elements, receiver) = mpsc::channel(NODE_BUFFER);
+                self.slot.emit(Node::Seq(receiver)).await;
+
+                while let Some(Streamed) = seq
+                    .next_element((Slot::Element(elements.clone()), None))
+                    .await?
+                {}
+
+                Ok(None)
+            }
+        }
+    };
+}
+
+crate::de::visit_methods!(patch_visitor);
@@ -0,0 +1,380 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::stream::{Stream, StreamExt};
+
+use crate::de::{self, Error as _, FromStream, Visitor};
+
+use super::{tag, Element, Error};
+
+const FOLD_CHUNK_LEN: usize = 4_096;
+
+/// A [`de::Decoder`] for the reference binary format.
+pub struct Decoder<S> {
+    source: S,
+    buffer: BytesMut,
+}
+
+impl<S> Decoder<S> {
+    /// Construct a new [`Decoder`] which reads from the given `source` stream of chunks.
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            buffer: BytesMut::new(),
+        }
+    }
+}
+
+impl<S> Decoder<S>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+{
+    /// Buffer at least `len` bytes, or return an error if the source stream ends first.
+    async fn fill(&mut self, len: usize) -> Result<(), Error> {
+        while self.buffer.len() < len {
+            match self.source.next().await {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk?),
+                None => return Err(Error::custom("unexpected end of stream")),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn read(&mut self, len: usize) -> Result<Bytes, Error> {
+        self.fill(len).await?;
+        Ok(self.buffer.split_to(len).freeze())
+    }
+
+    async fn read_element<T: Element>(&mut self) -> Result<T, Error> {
+        let bytes = self.read(T::SIZE).await?;
+        Ok(T::read(&bytes))
+    }
+
+    async fn read_len(&mut self) -> Result<usize, Error> {
+        let len = self.read_element::<u64>().await?;
+        usize::try_from(len).map_err(Error::custom)
+    }
+
+    async fn peek_tag(&mut self) -> Result<u8, Error> {
+        self.fill(1).await?;
+        Ok(self.buffer[0])
+    }
+
+    /// Consume the end tag of a map or sequence, if it's next, and return `true` if it was.
+    async fn end_of_collection(&mut self) -> Result<bool, Error> {
+        if self.peek_tag().await? == tag::END {
+            self.buffer.advance(1);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Return an error if there is any data left to decode.
+    pub(super) async fn end(&mut self) -> Result<(), Error> {
+        while self.buffer.is_empty() {
+            match self.source.next().await {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk?),
+                None => return Ok(()),
+            }
+        }
+
+        Err(Error::custom(
+            "unexpected data after the end of the encoded value",
+        ))
+    }
+
+    async fn decode_value<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, Error> {
+        let tag = self.read_element::<u8>().await?;
+
+        match tag {
+            tag::NONE => visitor.visit_none(),
+            tag::SOME => visitor.visit_some(self).await,
+            tag::UNIT => visitor.visit_unit(),
+            tag::FALSE => visitor.visit_bool(false),
+            tag::TRUE => visitor.visit_bool(true),
+            tag::I8 => visitor.visit_i8(self.read_element().await?),
+            tag::I16 => visitor.visit_i16(self.read_element().await?),
+            tag::I32 => visitor.visit_i32(self.read_element().await?),
+            tag::I64 => visitor.visit_i64(self.read_element().await?),
+            tag::U8 => visitor.visit_u8(self.read_element().await?),
+            tag::U16 => visitor.visit_u16(self.read_element().await?),
+            tag::U32 => visitor.visit_u32(self.read_element().await?),
+            tag::U64 => visitor.visit_u64(self.read_element().await?),
+            tag::F32 => visitor.visit_f32(self.read_element().await?),
+            tag::F64 => visitor.visit_f64(self.read_element().await?),
+            tag::STRING => {
+                let len = self.read_len().await?;
+                let bytes = self.read(len).await?;
+                let string = String::from_utf8(bytes.into()).map_err(Error::custom)?;
+                visitor.visit_string(string)
+            }
+            tag::BYTES => {
+                let len = self.read_len().await?;
+                let bytes = self.read(len).await?;
+                visitor.visit_array_u8(BytesAccess { bytes }).await
+            }
+            tag::UUID => {
+                let bytes = self.read(16).await?;
+                visitor.visit_array_u8(BytesAccess { bytes }).await
+            }
+            tag::SEQ => {
+                let seq = SeqAccess {
+                    decoder: self,
+                    remaining: None,
+                };
+
+                visitor.visit_seq(seq).await
+            }
+            tag::TUPLE => {
+                let len = self.read_len().await?;
+                let seq = SeqAccess {
+                    decoder: self,
+                    remaining: Some(len),
+                };
+
+                visitor.visit_seq(seq).await
+            }
+            tag::MAP => visitor.visit_map(MapAccess { decoder: self }).await,
+            tag::ARRAY_BOOL => visitor.visit_array_bool(ArrayAccess::new(self)).await,
+            tag::ARRAY_I8 => visitor.visit_array_i8(ArrayAccess::new(self)).await,
+            tag::ARRAY_I16 => visitor.visit_array_i16(ArrayAccess::new(self)).await,
+            tag::ARRAY_I32 => visitor.visit_array_i32(ArrayAccess::new(self)).await,
+            tag::ARRAY_I64 => visitor.visit_array_i64(ArrayAccess::new(self)).await,
+            tag::ARRAY_U8 => visitor.visit_array_u8(ArrayAccess::new(self)).await,
+            tag::ARRAY_U16 => visitor.visit_array_u16(ArrayAccess::new(self)).await,
+            tag::ARRAY_U32 => visitor.visit_array_u32(ArrayAccess::new(self)).await,
+            tag::ARRAY_U64 => visitor.visit_array_u64(ArrayAccess::new(self)).await,
+            tag::ARRAY_F32 => visitor.visit_array_f32(ArrayAccess::new(self)).await,
+            tag::ARRAY_F64 => visitor.visit_array_f64(ArrayAccess::new(self)).await,
+            other => Err(Error::custom(format_args!("unknown tag {:#04x}", other))),
+        }
+    }
+}
+
+// every value is tagged with its type, so the type hints are ignored
+macro_rules! decode_self_describing {
+    ($($method:ident),+) => {
+        #[async_trait]
+        impl<S> de::Decoder for Decoder<S>
+        where
+            S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+        {
+            type Error = Error;
+
+            $(
+                async fn $method<V: Visitor>(&mut self, visitor: V) -> Result<V::Value, Self::Error> {
+                    self.decode_value(visitor).await
+                }
+            )+
+
+            async fn decode_tuple<V: Visitor>(
+                &mut self,
+                _len: usize,
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                self.decode_value(visitor).await
+            }
+        }
+    };
+}
+
+decode_self_describing!(
+    decode_any,
+    decode_bool,
+    decode_bytes,
+    decode_i8,
+    decode_i16,
+    decode_i32,
+    decode_i64,
+    decode_u8,
+    decode_u16,
+    decode_u32,
+    decode_u64,
+    decode_f32,
+    decode_f64,
+    decode_array_bool,
+    decode_array_i8,
+    decode_array_i16,
+    decode_array_i32,
+    decode_array_i64,
+    decode_array_u8,
+    decode_array_u16,
+    decode_array_u32,
+    decode_array_u64,
+    decode_array_f32,
+    decode_array_f64,
+    decode_map,
+    decode_option,
+    decode_seq,
+    decode_string,
+    decode_unit,
+    decode_uuid,
+    decode_ignored_any
+);
+
+/// Access to a sequence, or a tuple of known length.
+struct SeqAccess<'a, S> {
+    decoder: &'a mut Decoder<S>,
+    remaining: Option<usize>,
+}
+
+#[async_trait]
+impl<'a, S> de::SeqAccess for SeqAccess<'a, S>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+{
+    type Error = Error;
+
+    async fn next_element<T: FromStream>(
+        &mut self,
+        context: T::Context,
+    ) -> Result<Option<T>, Self::Error> {
+        match &mut self.remaining {
+            Some(0) => return Ok(None),
+            Some(remaining) => *remaining -= 1,
+            None if self.decoder.end_of_collection().await? => return Ok(None),
+            None => {}
+        }
+
+        T::from_stream(context, self.decoder).await.map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.remaining
+    }
+}
+
+struct MapAccess<'a, S> {
+    decoder: &'a mut Decoder<S>,
+}
+
+#[async_trait]
+impl<'a, S> de::MapAccess for MapAccess<'a, S>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+{
+    type Error = Error;
+
+    async fn next_key<K: FromStream>(
+        &mut self,
+        context: K::Context,
+    ) -> Result<Option<K>, Self::Error> {
+        if self.decoder.end_of_collection().await? {
+            Ok(None)
+        } else {
+            K::from_stream(context, self.decoder).await.map(Some)
+        }
+    }
+
+    async fn next_value<V: FromStream>(&mut self, context: V::Context) -> Result<V, Self::Error> {
+        V::from_stream(context, self.decoder).await
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Access to an array encoded as a series of length-prefixed chunks.
+struct ArrayAccess<'a, S, T> {
+    decoder: &'a mut Decoder<S>,
+    remaining: usize,
+    done: bool,
+    element: PhantomData<T>,
+}
+
+impl<'a, S, T> ArrayAccess<'a, S, T> {
+    fn new(decoder: &'a mut Decoder<S>) -> Self {
+        Self {
+            decoder,
+            remaining: 0,
+            done: false,
+            element: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<'a, S, T> de::ArrayAccess<T> for ArrayAccess<'a, S, T>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+    T: Element,
+{
+    type Error = Error;
+
+    async fn buffer(&mut self, buffer: &mut [T]) -> Result<usize, Self::Error> {
+        if self.done || buffer.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.remaining = self.decoder.read_len().await?;
+
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let len = Ord::min(buffer.len(), self.remaining);
+        let bytes = self.decoder.read(len * T::SIZE).await?;
+
+        for (target, element) in buffer.iter_mut().zip(bytes.chunks_exact(T::SIZE)) {
+            *target = T::read(element);
+        }
+
+        self.remaining -= len;
+        Ok(len)
+    }
+
+    async fn fold<B, F>(&mut self, init: B, mut f: F) -> Result<B, Self::Error>
+    where
+        T: Copy + Default + Send,
+        B: Send,
+        F: FnMut(B, T) -> B + Send,
+    {
+        let mut acc = init;
+
+        while !self.done {
+            if self.remaining == 0 {
+                self.remaining = self.decoder.read_len().await?;
+
+                if self.remaining == 0 {
+                    self.done = true;
+                    break;
+                }
+            }
+
+            // fold over the decoder's own buffer, one bounded read at a time
+            let len = Ord::min(self.remaining, FOLD_CHUNK_LEN);
+            let bytes = self.decoder.read(len * T::SIZE).await?;
+
+            for element in bytes.chunks_exact(T::SIZE) {
+                acc = f(acc, T::read(element));
+            }
+
+            self.remaining -= len;
+        }
+
+        Ok(acc)
+    }
+}
+
+/// Access to a byte string which has already been read in full.
+struct BytesAccess {
+    bytes: Bytes,
+}
+
+#[async_trait]
+impl de::ArrayAccess<u8> for BytesAccess {
+    type Error = Error;
+
+    async fn buffer(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = Ord::min(buffer.len(), self.bytes.len());
+        buffer[..len].copy_from_slice(&self.bytes.split_to(len));
+        Ok(len)
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt;
+use std::pin::Pin;
+
+use bytes::Bytes;
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use uuid::Uuid;
+
+use crate::en::{self, IntoStream, MemoCache};
+
+use super::{tag, Element, Error};
+
+/// A stream of chunks of data in the reference binary format.
+pub type Encoded<'en> = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send + 'en>>;
+
+fn chunk<'en>(bytes: Vec<u8>) -> Encoded<'en> {
+    Box::pin(stream::once(future::ready(Ok(Bytes::from(bytes)))))
+}
+
+fn tagged<'en>(tag: u8) -> Encoded<'en> {
+    chunk(vec![tag])
+}
+
+fn failed<'en>(cause: Error) -> Encoded<'en> {
+    Box::pin(stream::once(future::ready(Err(cause))))
+}
+
+fn concat<'en>(parts: Vec<Encoded<'en>>) -> Encoded<'en> {
+    Box::pin(stream::iter(parts).flatten())
+}
+
+/// Encode the given `value`, deferring any error to the returned stream.
+fn encode_item<'en, T: IntoStream<'en> + 'en>(encoder: Encoder, value: T) -> Encoded<'en> {
+    match value.into_stream(encoder) {
+        Ok(encoded) => encoded,
+        Err(cause) => failed(cause),
+    }
+}
+
+fn length_prefixed(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(9 + bytes.len());
+    encoded.push(tag);
+    (bytes.len() as u64).write(&mut encoded);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+fn encode_array<'en, E, T, S>(tag: u8, chunks: S) -> Encoded<'en>
+where
+    E: Element,
+    T: IntoIterator<Item = E> + Send + Unpin + 'en,
+    S: Stream<Item = T> + Send + Unpin + 'en,
+{
+    let chunks = chunks.filter_map(|chunk| {
+        let elements = chunk.into_iter().collect::<Vec<E>>();
+
+        // an empty chunk would terminate the array
+        let encoded = if elements.is_empty() {
+            None
+        } else {
+            let mut encoded = Vec::with_capacity(8 + (elements.len() * E::SIZE));
+            (elements.len() as u64).write(&mut encoded);
+
+            for element in elements {
+                element.write(&mut encoded);
+            }
+
+            Some(Ok(Bytes::from(encoded)))
+        };
+
+        future::ready(encoded)
+    });
+
+    let mut end = Vec::with_capacity(8);
+    0u64.write(&mut end);
+
+    concat(vec![tagged(tag), Box::pin(chunks), chunk(end)])
+}
+
+/// An [`en::Encoder`] for the reference binary format.
+///
+/// Every value nested within a value encoded by the same `Encoder` shares a [`MemoCache`],
+/// so a repeated [`Memoized`](en::Memoized) value is only encoded once.
+#[derive(Clone, Default)]
+pub struct Encoder {
+    memo: MemoCache<Bytes>,
+}
+
+impl Encoder {
+    /// Construct a new [`Encoder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Returned from `Encoder::encode_map`, `Encoder::encode_seq`, and `Encoder::encode_tuple`.
+pub struct EncodeParts<'en> {
+    encoder: Encoder,
+    parts: Vec<Encoded<'en>>,
+}
+
+impl<'en> EncodeParts<'en> {
+    fn new(encoder: Encoder, tag: u8, len: Option<usize>) -> Self {
+        let mut parts = Vec::with_capacity(len.map(|len| len + 2).unwrap_or(2));
+        parts.push(tagged(tag));
+        Self { encoder, parts }
+    }
+
+    fn tuple(encoder: Encoder, len: usize) -> Self {
+        let mut header = Vec::with_capacity(9);
+        header.push(tag::TUPLE);
+        (len as u64).write(&mut header);
+
+        let mut parts = Vec::with_capacity(len + 1);
+        parts.push(chunk(header));
+        Self { encoder, parts }
+    }
+
+    fn push<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Error> {
+        let encoded = value.into_stream(self.encoder.clone())?;
+        self.parts.push(encoded);
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Encoded<'en>, Error> {
+        self.parts.push(tagged(tag::END));
+        Ok(concat(self.parts))
+    }
+
+    fn end_tuple(self) -> Result<Encoded<'en>, Error> {
+        Ok(concat(self.parts))
+    }
+}
+
+impl<'en> en::EncodeMap<'en> for EncodeParts<'en> {
+    type Ok = Encoded<'en>;
+    type Error = Error;
+
+    fn encode_key<T: IntoStream<'en> + 'en>(&mut self, key: T) -> Result<(), Self::Error> {
+        self.push(key)
+    }
+
+    fn encode_value<T: IntoStream<'en> + 'en>(&mut self, value: T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.parts.reserve(additional * 2)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        EncodeParts::end(self)
+    }
+}
+
+impl<'en> en::EncodeSeq<'en> for EncodeParts<'en> {
+    type Ok = Encoded<'en>;
+    type Error = Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.parts.reserve(additional)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        EncodeParts::end(self)
+    }
+}
+
+impl<'en> en::EncodeTuple<'en> for EncodeParts<'en> {
+    type Ok = Encoded<'en>;
+    type Error = Error;
+
+    fn encode_element<V: IntoStream<'en> + 'en>(&mut self, value: V) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        EncodeParts::end_tuple(self)
+    }
+}
+
+macro_rules! encode_number {
+    ($($method:ident($ty:ty) => $tag:ident),+) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                let mut encoded = Vec::with_capacity(1 + <$ty as Element>::SIZE);
+                encoded.push(tag::$tag);
+                v.write(&mut encoded);
+                Ok(chunk(encoded))
+            }
+        )+
+    };
+}
+
+macro_rules! encode_array {
+    ($($method:ident($ty:ty) => $tag:ident),+) => {
+        $(
+            fn $method<T, S>(self, chunks: S) -> Result<Self::Ok, Self::Error>
+            where
+                T: IntoIterator<Item = $ty> + Send + Unpin + 'en,
+                S: Stream<Item = T> + Send + Unpin + 'en,
+                <T as IntoIterator>::IntoIter: Send + Unpin + 'en,
+            {
+                Ok(encode_array(tag::$tag, chunks))
+            }
+        )+
+    };
+}
+
+impl<'en> en::Encoder<'en> for Encoder {
+    type Ok = Encoded<'en>;
+    type Error = Error;
+    type EncodeMap = EncodeParts<'en>;
+    type EncodeSeq = EncodeParts<'en>;
+    type EncodeTuple = EncodeParts<'en>;
+
+    fn encode_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(tagged(if v { tag::TRUE } else { tag::FALSE }))
+    }
+
+    encode_number!(
+        encode_i8(i8) => I8,
+        encode_i16(i16) => I16,
+        encode_i32(i32) => I32,
+        encode_i64(i64) => I64,
+        encode_u8(u8) => U8,
+        encode_u16(u16) => U16,
+        encode_u32(u32) => U32,
+        encode_u64(u64) => U64,
+        encode_f32(f32) => F32,
+        encode_f64(f64) => F64
+    );
+
+    encode_array!(
+        encode_array_bool(bool) => ARRAY_BOOL,
+        encode_array_i8(i8) => ARRAY_I8,
+        encode_array_i16(i16) => ARRAY_I16,
+        encode_array_i32(i32) => ARRAY_I32,
+        encode_array_i64(i64) => ARRAY_I64,
+        encode_array_u8(u8) => ARRAY_U8,
+        encode_array_u16(u16) => ARRAY_U16,
+        encode_array_u32(u32) => ARRAY_U32,
+        encode_array_u64(u64) => ARRAY_U64,
+        encode_array_f32(f32) => ARRAY_F32,
+        encode_array_f64(f64) => ARRAY_F64
+    );
+
+    fn encode_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(chunk(length_prefixed(tag::STRING, v.as_bytes())))
+    }
+
+    fn encode_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(tagged(tag::NONE))
+    }
+
+    fn encode_some<T: IntoStream<'en> + 'en>(self, value: T) -> Result<Self::Ok, Self::Error> {
+        let value = value.into_stream(self)?;
+        Ok(concat(vec![tagged(tag::SOME), value]))
+    }
+
+    fn encode_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(tagged(tag::UNIT))
+    }
+
+    fn encode_bytes<B: Into<Bytes>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        Ok(chunk(length_prefixed(tag::BYTES, &bytes.into())))
+    }
+
+    fn encode_uuid(self, uuid: Uuid) -> Result<Self::Ok, Self::Error> {
+        let mut encoded = Vec::with_capacity(17);
+        encoded.push(tag::UUID);
+        encoded.extend_from_slice(uuid.as_bytes());
+        Ok(chunk(encoded))
+    }
+
+    fn encode_map(self, len: Option<usize>) -> Result<Self::EncodeMap, Self::Error> {
+        Ok(EncodeParts::new(self, tag::MAP, len.map(|len| len * 2)))
+    }
+
+    fn encode_map_stream<K, V, S>(self, map: S) -> Result<Self::Ok, Self::Error>
+    where
+        K: IntoStream<'en> + 'en,
+        V: IntoStream<'en> + 'en,
+        S: Stream<Item = (K, V)> + Send + Unpin + 'en,
+    {
+        let entries = map
+            .map(move |(key, value)| {
+                let key = encode_item(self.clone(), key);
+                let value = encode_item(self.clone(), value);
+                concat(vec![key, value])
+            })
+            .flatten();
+
+        Ok(concat(vec![
+            tagged(tag::MAP),
+            Box::pin(entries),
+            tagged(tag::END),
+        ]))
+    }
+
+    fn encode_seq(self, len: Option<usize>) -> Result<Self::EncodeSeq, Self::Error> {
+        Ok(EncodeParts::new(self, tag::SEQ, len))
+    }
+
+    fn encode_seq_stream<T, S>(self, seq: S) -> Result<Self::Ok, Self::Error>
+    where
+        T: IntoStream<'en> + 'en,
+        S: Stream<Item = T> + Send + Unpin + 'en,
+    {
+        let elements = seq
+            .map(move |element| encode_item(self.clone(), element))
+            .flatten();
+
+        Ok(concat(vec![
+            tagged(tag::SEQ),
+            Box::pin(elements),
+            tagged(tag::END),
+        ]))
+    }
+
+    fn encode_tuple(self, len: usize) -> Result<Self::EncodeTuple, Self::Error> {
+        Ok(EncodeParts::tuple(self, len))
+    }
+
+    fn collect_bytes<B: IntoIterator<Item = u8>>(self, bytes: B) -> Result<Self::Ok, Self::Error> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        self.encode_bytes(bytes)
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.encode_str(&value.to_string())
+    }
+
+    fn encode_memoized<T: IntoStream<'en> + 'en>(
+        self,
+        key: u64,
+        value: T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let memo = self.memo.clone();
+        memo.encode(key, move || value.into_stream(self))
+    }
+}
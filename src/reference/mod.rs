@@ -0,0 +1,180 @@
+//! A minimal reference binary format, to document the [`Decoder`](crate::de::Decoder) and
+//! [`Encoder`](crate::en::Encoder) contracts by example and to serve as a baseline for testing
+//! and benchmarks.
+//!
+//! **This format is not stable** and may change in any release. It is not intended for storage
+//! or for communication between processes which may run different versions of `destream`.
+//!
+//! Every value begins with a one-byte tag. Numbers are encoded in little-endian byte order, and
+//! strings, byte strings, and tuples are prefixed with their length as a `u64`. Maps and
+//! sequences are terminated by an end tag, and arrays of primitive numbers are encoded as a
+//! series of chunks, each prefixed with its length, terminated by an empty chunk, so that neither
+//! needs to know its length in advance.
+//!
+//! Enable this module with the `reference-binary` feature flag.
+//!
+//! ```
+//! # use futures::executor::block_on;
+//! use destream::reference;
+//!
+//! let value = vec![(1u64, "one".to_string()), (2u64, "two".to_string())];
+//! let encoded = reference::encode(value.clone()).unwrap();
+//! let decoded: Vec<(u64, String)> = block_on(reference::decode((), encoded)).unwrap();
+//! assert_eq!(decoded, value);
+//! ```
+
+use std::fmt;
+
+use bytes::Bytes;
+use futures::stream::{Stream, TryStreamExt};
+
+use crate::de::{self, FromStream};
+use crate::en::{self, IntoStream};
+
+mod decode;
+mod encode;
+
+pub use decode::Decoder;
+pub use encode::{Encoded, Encoder};
+
+mod tag {
+    pub const NONE: u8 = 0x00;
+    pub const SOME: u8 = 0x01;
+    pub const UNIT: u8 = 0x02;
+    pub const FALSE: u8 = 0x03;
+    pub const TRUE: u8 = 0x04;
+
+    pub const I8: u8 = 0x10;
+    pub const I16: u8 = 0x11;
+    pub const I32: u8 = 0x12;
+    pub const I64: u8 = 0x13;
+    pub const U8: u8 = 0x14;
+    pub const U16: u8 = 0x15;
+    pub const U32: u8 = 0x16;
+    pub const U64: u8 = 0x17;
+    pub const F32: u8 = 0x18;
+    pub const F64: u8 = 0x19;
+
+    pub const STRING: u8 = 0x20;
+    pub const BYTES: u8 = 0x21;
+    pub const UUID: u8 = 0x22;
+
+    pub const SEQ: u8 = 0x30;
+    pub const MAP: u8 = 0x31;
+    pub const TUPLE: u8 = 0x32;
+    pub const END: u8 = 0x3F;
+
+    pub const ARRAY_BOOL: u8 = 0x40;
+    pub const ARRAY_I8: u8 = 0x41;
+    pub const ARRAY_I16: u8 = 0x42;
+    pub const ARRAY_I32: u8 = 0x43;
+    pub const ARRAY_I64: u8 = 0x44;
+    pub const ARRAY_U8: u8 = 0x45;
+    pub const ARRAY_U16: u8 = 0x46;
+    pub const ARRAY_U32: u8 = 0x47;
+    pub const ARRAY_U64: u8 = 0x48;
+    pub const ARRAY_F32: u8 = 0x49;
+    pub const ARRAY_F64: u8 = 0x4A;
+}
+
+/// A primitive array element with a fixed-size little-endian encoding.
+trait Element: Copy + Default + Send + 'static {
+    const SIZE: usize;
+
+    fn write(self, buffer: &mut Vec<u8>);
+
+    fn read(bytes: &[u8]) -> Self;
+}
+
+impl Element for bool {
+    const SIZE: usize = 1;
+
+    fn write(self, buffer: &mut Vec<u8>) {
+        buffer.push(self as u8);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+macro_rules! element {
+    ($($ty:ty),+) => {
+        $(
+            impl Element for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn write(self, buffer: &mut Vec<u8>) {
+                    buffer.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(bytes: &[u8]) -> Self {
+                    let mut le = [0u8; std::mem::size_of::<$ty>()];
+                    le.copy_from_slice(bytes);
+                    <$ty>::from_le_bytes(le)
+                }
+            }
+        )+
+    };
+}
+
+element!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+/// An error encountered while encoding or decoding the reference binary format.
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            message: msg.to_string(),
+        }
+    }
+}
+
+impl en::Error for Error {
+    fn custom<I: fmt::Display>(info: I) -> Self {
+        Self {
+            message: info.to_string(),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Encode the given `value` as a stream of chunks in the reference binary format.
+pub fn encode<'en, T: IntoStream<'en> + 'en>(value: T) -> Result<Encoded<'en>, Error> {
+    value.into_stream(Encoder::new())
+}
+
+/// Decode a `T` from the given `source` stream of chunks in the reference binary format.
+///
+/// Returns an error if the `source` stream contains any data after the encoded `T`.
+pub async fn decode<T, S>(context: T::Context, source: S) -> Result<T, Error>
+where
+    T: FromStream,
+    S: Stream<Item = Result<Bytes, Error>> + Send + Unpin,
+{
+    let mut decoder = Decoder::new(source);
+    let value = T::from_stream(context, &mut decoder).await?;
+    decoder.end().await?;
+    Ok(value)
+}
+
+/// Encode the given `value` in the reference binary format and collect the result.
+pub async fn encode_to_vec<'en, T: IntoStream<'en> + 'en>(value: T) -> Result<Vec<u8>, Error> {
+    encode(value)?
+        .try_fold(Vec::new(), |mut encoded, chunk| {
+            encoded.extend_from_slice(&chunk);
+            futures::future::ready(Ok(encoded))
+        })
+        .await
+}
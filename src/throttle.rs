@@ -0,0 +1,133 @@
+//! Rate limiting for encoded and decoded streams, e.g. to share a server's encoding and decoding
+//! capacity fairly between connections.
+//!
+//! A [`Throttled`] stream wraps either the output stream of an [`Encoder`](crate::Encoder) or the
+//! input stream of a [`Decoder`](crate::Decoder), and limits the rate at which chunks are passed
+//! through to a given number of tokens per second, where a token is either one chunk or one byte.
+//! When the budget is exhausted, it waits on a timer provided by the application (e.g. a sleep
+//! future from its async runtime) before polling the underlying stream again, so that the task
+//! yields to the runtime in the meantime.
+//!
+//! Tokens accrue continuously up to a burst of one second's worth. A chunk is passed through as
+//! long as the budget is not overdrawn, so a chunk larger than the budget is never held back
+//! indefinitely; instead, the stream waits to pay off the debt before passing the next chunk.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::FutureExt;
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+
+/// A stream of chunks passed through at a limited rate. See the [module docs](self).
+pub struct Throttled<S, C, F, W> {
+    source: S,
+    cost: C,
+    sleep: F,
+    sleeping: Option<Pin<Box<W>>>,
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl<S, C, F, W> Throttled<S, C, F, W> {
+    fn new(source: S, cost: C, tokens_per_second: u64, sleep: F) -> Self {
+        let rate = Ord::max(tokens_per_second, 1) as f64;
+
+        Self {
+            source,
+            cost,
+            sleep,
+            sleeping: None,
+            rate,
+            tokens: rate,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Return the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = f64::min(self.tokens + (elapsed * self.rate), self.rate);
+        self.updated = now;
+    }
+}
+
+impl<S, F, W> Throttled<S, fn(&S::Item) -> u64, F, W>
+where
+    S: Stream,
+{
+    /// Pass through at most `per_second` chunks of the given `source` stream per second,
+    /// waiting on a timer constructed by calling `sleep` whenever the budget is exhausted.
+    pub fn chunks(source: S, per_second: u64, sleep: F) -> Self {
+        fn cost<T>(_chunk: &T) -> u64 {
+            1
+        }
+
+        Self::new(source, cost::<S::Item>, per_second, sleep)
+    }
+}
+
+impl<T, X, S, F, W> Throttled<S, fn(&Result<T, X>) -> u64, F, W>
+where
+    T: AsRef<[u8]>,
+    S: Stream<Item = Result<T, X>>,
+{
+    /// Pass through at most `per_second` bytes of the given `source` stream per second,
+    /// waiting on a timer constructed by calling `sleep` whenever the budget is exhausted.
+    pub fn bytes(source: S, per_second: u64, sleep: F) -> Self {
+        fn cost<T: AsRef<[u8]>, X>(chunk: &Result<T, X>) -> u64 {
+            match chunk {
+                Ok(chunk) => chunk.as_ref().len() as u64,
+                Err(_) => 0,
+            }
+        }
+
+        Self::new(source, cost::<T, X>, per_second, sleep)
+    }
+}
+
+impl<S, C, F, W> Stream for Throttled<S, C, F, W>
+where
+    S: Stream + Unpin,
+    C: Fn(&S::Item) -> u64 + Unpin,
+    F: FnMut(Duration) -> W + Unpin,
+    W: Future<Output = ()>,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cxt: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(sleeping) = self.sleeping.as_mut() {
+                ready!(sleeping.poll_unpin(cxt));
+                self.sleeping = None;
+            }
+
+            self.refill();
+
+            if self.tokens < 0. {
+                let wait = Duration::from_secs_f64(-self.tokens / self.rate);
+                let sleeping = (self.sleep)(wait);
+                self.sleeping = Some(Box::pin(sleeping));
+            } else {
+                break;
+            }
+        }
+
+        let item = ready!(self.source.poll_next_unpin(cxt));
+
+        if let Some(item) = &item {
+            let cost = (self.cost)(item) as f64;
+            self.tokens -= cost;
+        }
+
+        Poll::Ready(item)
+    }
+}
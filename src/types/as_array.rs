@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use async_trait::async_trait;
+use futures::{future, stream};
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+const BUF_SIZE: usize = 4_096;
+
+/// A slice, `Vec`, or array of primitive values which is encoded as a typed array
+/// (e.g. using [`Encoder::encode_array_f32`]) rather than element-by-element as a sequence.
+///
+/// Binary formats can encode and decode a typed array much more efficiently than a sequence.
+/// An `AsArray<Vec<P>>` is decoded using the matching `Decoder::decode_array_*` hint, and
+/// will also accept a sequence.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct AsArray<T>(pub T);
+
+impl<T> AsArray<T> {
+    /// Return the wrapped collection.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for AsArray<T> {
+    fn from(values: T) -> Self {
+        Self(values)
+    }
+}
+
+impl<T> Deref for AsArray<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for AsArray<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+struct AsArrayVisitor<P> {
+    marker: PhantomData<P>,
+}
+
+macro_rules! as_array {
+    ($($ty:ty => ($encode:ident, $decode:ident, $visit:ident)),+) => {
+        $(
+            impl<'a, 'en> IntoStream<'en> for AsArray<&'a [$ty]>
+            where
+                'a: 'en,
+            {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0.iter().copied())))
+                }
+            }
+
+            impl<'a, 'en> ToStream<'en> for AsArray<&'a [$ty]>
+            where
+                'a: 'en,
+            {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0.iter().copied())))
+                }
+            }
+
+            impl<'en> IntoStream<'en> for AsArray<Vec<$ty>> {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0)))
+                }
+            }
+
+            impl<'en> ToStream<'en> for AsArray<Vec<$ty>> {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0.iter().copied())))
+                }
+            }
+
+            impl<'en, const N: usize> IntoStream<'en> for AsArray<[$ty; N]> {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0)))
+                }
+            }
+
+            impl<'en, const N: usize> ToStream<'en> for AsArray<[$ty; N]> {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.$encode(stream::once(future::ready(self.0.iter().copied())))
+                }
+            }
+
+            #[async_trait]
+            impl Visitor for AsArrayVisitor<$ty> {
+                type Value = AsArray<Vec<$ty>>;
+
+                fn expecting() -> &'static str {
+                    concat!("an array of ", stringify!($ty))
+                }
+
+                async fn $visit<A: ArrayAccess<$ty>>(
+                    self,
+                    mut array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let mut values = Vec::new();
+                    let mut buffer = vec![<$ty>::default(); BUF_SIZE];
+
+                    loop {
+                        match array.buffer(&mut buffer).await? {
+                            0 => break,
+                            len => values.extend_from_slice(&buffer[..len]),
+                        }
+                    }
+
+                    Ok(AsArray(values))
+                }
+
+                async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let mut values = Vec::with_capacity(de::size_hint::cautious(seq.size_hint()));
+
+                    while let Some(value) = seq.next_element(()).await? {
+                        values.push(value);
+                    }
+
+                    Ok(AsArray(values))
+                }
+            }
+
+            #[async_trait]
+            impl FromStream for AsArray<Vec<$ty>> {
+                type Context = ();
+
+                async fn from_stream<D: Decoder>(
+                    _context: (),
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    let visitor = AsArrayVisitor::<$ty> { marker: PhantomData };
+                    decoder.$decode(visitor).await
+                }
+            }
+        )+
+    };
+}
+
+as_array!(
+    bool => (encode_array_bool, decode_array_bool, visit_array_bool),
+    i8 => (encode_array_i8, decode_array_i8, visit_array_i8),
+    i16 => (encode_array_i16, decode_array_i16, visit_array_i16),
+    i32 => (encode_array_i32, decode_array_i32, visit_array_i32),
+    i64 => (encode_array_i64, decode_array_i64, visit_array_i64),
+    u8 => (encode_array_u8, decode_array_u8, visit_array_u8),
+    u16 => (encode_array_u16, decode_array_u16, visit_array_u16),
+    u32 => (encode_array_u32, decode_array_u32, visit_array_u32),
+    u64 => (encode_array_u64, decode_array_u64, visit_array_u64),
+    f32 => (encode_array_f32, decode_array_f32, visit_array_f32),
+    f64 => (encode_array_f64, decode_array_f64, visit_array_f64)
+);
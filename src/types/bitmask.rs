@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use futures::{future, stream};
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+const BITS: usize = u64::BITS as usize;
+
+/// A set of up to 64 flags, encoded as an array of 64 `bool`s where element `i` is bit `i`.
+///
+/// A `Bitmask` is decoded using the [`Decoder::decode_array_bool`] hint, so that formats with
+/// packed boolean arrays can decode it without visiting each element. It will also accept a
+/// sequence of `bool`s. An array shorter than 64 elements leaves the remaining bits unset.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Bitmask(pub u64);
+
+impl Bitmask {
+    /// Return `true` if bit `i` is set.
+    pub fn get(&self, i: usize) -> bool {
+        i < BITS && self.0 & (1 << i) != 0
+    }
+
+    /// Set bit `i` to the given `value`.
+    ///
+    /// Panics if `i` is not less than 64.
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < BITS, "bit index {} out of range for a Bitmask", i);
+
+        if value {
+            self.0 |= 1 << i;
+        } else {
+            self.0 &= !(1 << i);
+        }
+    }
+
+    fn bits(self) -> impl Iterator<Item = bool> + Send + Unpin {
+        (0..BITS).map(move |i| self.get(i))
+    }
+}
+
+impl From<u64> for Bitmask {
+    fn from(bits: u64) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<Bitmask> for u64 {
+    fn from(mask: Bitmask) -> Self {
+        mask.0
+    }
+}
+
+struct BitmaskVisitor;
+
+#[async_trait]
+impl Visitor for BitmaskVisitor {
+    type Value = Bitmask;
+
+    fn expecting() -> &'static str {
+        "an array of at most 64 bools"
+    }
+
+    async fn visit_array_bool<A: ArrayAccess<bool>>(
+        self,
+        mut array: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut buffer = [false; BITS + 1];
+        let mut len = 0;
+
+        loop {
+            match array.buffer(&mut buffer[len..]).await? {
+                0 => break,
+                read => len += read,
+            }
+
+            if len > BITS {
+                return Err(de::Error::invalid_length(len, Self::expecting()));
+            }
+        }
+
+        let mut mask = Bitmask::default();
+        for (i, bit) in buffer[..len].iter().enumerate() {
+            mask.set(i, *bit);
+        }
+
+        Ok(mask)
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut mask = Bitmask::default();
+        let mut len = 0;
+
+        while let Some(bit) = seq.next_element(()).await? {
+            if len == BITS {
+                return Err(de::Error::invalid_length(len + 1, Self::expecting()));
+            }
+
+            mask.set(len, bit);
+            len += 1;
+        }
+
+        Ok(mask)
+    }
+}
+
+#[async_trait]
+impl FromStream for Bitmask {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_array_bool(BitmaskVisitor).await
+    }
+}
+
+impl<'en> IntoStream<'en> for Bitmask {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_array_bool(stream::once(future::ready(self.bits())))
+    }
+}
+
+impl<'en> ToStream<'en> for Bitmask {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        (*self).into_stream(encoder)
+    }
+}
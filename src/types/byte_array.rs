@@ -0,0 +1,135 @@
+use std::ops::{Deref, DerefMut};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+/// A fixed-size byte array, such as a hash, key, or nonce, which is encoded as a binary value
+/// rather than as a tuple of `u8`s, and decoded with an exact length check.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ByteArray<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> ByteArray<N> {
+    /// Return the wrapped array.
+    pub fn into_inner(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> Default for ByteArray<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for ByteArray<N> {
+    fn from(array: [u8; N]) -> Self {
+        Self(array)
+    }
+}
+
+impl<const N: usize> From<ByteArray<N>> for [u8; N] {
+    fn from(array: ByteArray<N>) -> Self {
+        array.0
+    }
+}
+
+impl<const N: usize> Deref for ByteArray<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for ByteArray<N> {
+    fn deref_mut(&mut self) -> &mut [u8; N] {
+        &mut self.0
+    }
+}
+
+struct ByteArrayVisitor<const N: usize>;
+
+#[async_trait]
+impl<const N: usize> Visitor for ByteArrayVisitor<N> {
+    type Value = ByteArray<N>;
+
+    fn expecting() -> &'static str {
+        "a fixed-size byte array"
+    }
+
+    async fn visit_array_u8<A: ArrayAccess<u8>>(
+        self,
+        mut array: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0u8; N];
+        let mut len = 0;
+
+        while len < N {
+            match array.buffer(&mut bytes[len..]).await? {
+                0 => return Err(de::Error::invalid_length(len, Self::expecting())),
+                read => len += read,
+            }
+        }
+
+        if array.buffer(&mut [0u8]).await? == 0 {
+            Ok(ByteArray(bytes))
+        } else {
+            Err(de::Error::invalid_length(N + 1, Self::expecting()))
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::engine::Engine;
+
+        let bytes = STANDARD
+            .decode(&v)
+            .map_err(|_cause| de::Error::invalid_value(&v, "a base64-encoded string"))?;
+
+        bytes
+            .try_into()
+            .map(ByteArray)
+            .map_err(|bytes: Vec<u8>| de::Error::invalid_length(bytes.len(), Self::expecting()))
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0u8; N];
+
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = match seq.next_element(()).await? {
+                Some(byte) => byte,
+                None => return Err(de::Error::invalid_length(i, Self::expecting())),
+            };
+        }
+
+        if seq.next_element::<u8>(()).await?.is_none() {
+            Ok(ByteArray(bytes))
+        } else {
+            Err(de::Error::invalid_length(N + 1, Self::expecting()))
+        }
+    }
+}
+
+#[async_trait]
+impl<const N: usize> FromStream for ByteArray<N> {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_bytes(ByteArrayVisitor).await
+    }
+}
+
+impl<'en, const N: usize> IntoStream<'en> for ByteArray<N> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_bytes(Bytes::copy_from_slice(&self.0))
+    }
+}
+
+impl<'en, const N: usize> ToStream<'en> for ByteArray<N> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_bytes(self.0.iter().copied())
+    }
+}
@@ -0,0 +1,457 @@
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::de::{self, Decoder, FromStream, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use crate::en::{EncodeMap, EncodeTuple, Encoder, IntoStream, ToStream};
+
+use super::AsArray;
+
+/// The element type of a [`Column`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ColumnKind {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+/// A single column of a [`Columns`] batch, encoded as a typed array.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Bool(Vec<bool>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// A primitive type which can be stored in a [`Column`].
+pub trait ColumnValue: Copy + Send + Sized {
+    /// The kind of [`Column`] which stores this type.
+    const KIND: ColumnKind;
+
+    /// Construct a new [`Column`] out of the given `values`.
+    fn into_column(values: Vec<Self>) -> Column;
+
+    /// Borrow the values of the given `column`, if it stores this type.
+    fn column_values(column: &Column) -> Option<&[Self]>;
+}
+
+macro_rules! column {
+    ($($variant:ident($ty:ty)),+) => {
+        impl Column {
+            /// The element type of this column.
+            pub fn kind(&self) -> ColumnKind {
+                match self {
+                    $(Self::$variant(_) => ColumnKind::$variant),+
+                }
+            }
+
+            /// The number of values in this column.
+            pub fn len(&self) -> usize {
+                match self {
+                    $(Self::$variant(values) => values.len()),+
+                }
+            }
+
+            /// Return `true` if this column is empty.
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+        }
+
+        impl fmt::Display for ColumnKind {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(Self::$variant => f.write_str(concat!("an array of ", stringify!($ty)))),+
+                }
+            }
+        }
+
+        $(
+            impl ColumnValue for $ty {
+                const KIND: ColumnKind = ColumnKind::$variant;
+
+                fn into_column(values: Vec<Self>) -> Column {
+                    Column::$variant(values)
+                }
+
+                fn column_values(column: &Column) -> Option<&[Self]> {
+                    match column {
+                        Column::$variant(values) => Some(values),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+
+        #[async_trait]
+        impl FromStream for Column {
+            type Context = ColumnKind;
+
+            async fn from_stream<D: Decoder>(
+                kind: ColumnKind,
+                decoder: &mut D,
+            ) -> Result<Self, D::Error> {
+                match kind {
+                    $(
+                        ColumnKind::$variant => {
+                            AsArray::<Vec<$ty>>::from_stream((), decoder)
+                                .await
+                                .map(|values| Self::$variant(values.into_inner()))
+                        }
+                    )+
+                }
+            }
+        }
+
+        impl<'en> ToStream<'en> for Column {
+            fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                match self {
+                    $(Self::$variant(values) => AsArray(values.as_slice()).into_stream(encoder)),+
+                }
+            }
+        }
+
+        impl<'en> IntoStream<'en> for Column {
+            fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                match self {
+                    $(Self::$variant(values) => AsArray(values).into_stream(encoder)),+
+                }
+            }
+        }
+    };
+}
+
+column!(
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64)
+);
+
+/// A batch of records stored column-major, i.e. as one typed array per field rather than one
+/// map per record, which is encoded as its row count followed by a map of field names to typed
+/// arrays.
+///
+/// A binary format can encode a typed array much more compactly and efficiently than a sequence
+/// of maps. Use [`Columns::builder`] to split a slice of row structs into columns, and
+/// [`Columns::rows`] to assemble the rows of a decoded batch. A batch is decoded using a schema
+/// of expected column names and kinds: a missing column, a column of the wrong kind, or a
+/// column whose length differs from the row count is an error, and an unexpected column is
+/// ignored. The row count is encoded explicitly so that a batch with no columns keeps its length.
+///
+/// Example:
+/// ```
+/// # use std::sync::Arc;
+/// # use destream::types::{ColumnKind, Columns};
+/// struct Point {
+///     id: u64,
+///     x: f32,
+/// }
+///
+/// let points = [Point { id: 1, x: 0.5 }, Point { id: 2, x: 1.5 }];
+///
+/// let columns = Columns::builder(&points)
+///     .column("id", |point| point.id)
+///     .column("x", |point| point.x)
+///     .build();
+///
+/// // decoded with the context `schema`
+/// let schema: Arc<[(String, ColumnKind)]> = Arc::from(vec![
+///     ("id".to_string(), ColumnKind::U64),
+///     ("x".to_string(), ColumnKind::F32),
+/// ]);
+/// # let _ = schema;
+///
+/// let points = columns
+///     .rows(|row| {
+///         Some(Point {
+///             id: row.get("id")?,
+///             x: row.get("x")?,
+///         })
+///     })
+///     .collect::<Option<Vec<Point>>>()
+///     .expect("points");
+///
+/// assert_eq!(points[1].id, 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Columns {
+    len: usize,
+    columns: Vec<(String, Column)>,
+}
+
+impl Columns {
+    /// Construct a [`ColumnsBuilder`] to split the given `rows` into columns.
+    pub fn builder<R>(rows: &[R]) -> ColumnsBuilder<'_, R> {
+        ColumnsBuilder {
+            rows,
+            columns: Vec::new(),
+        }
+    }
+
+    /// The number of rows in this batch.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return `true` if this batch has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Borrow the column with the given `name`, if present.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|(column, _)| column == name)
+            .map(|(_, column)| column)
+    }
+
+    /// Iterate over the names and values of the columns in this batch.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &Column)> {
+        self.columns
+            .iter()
+            .map(|(name, column)| (name.as_str(), column))
+    }
+
+    /// Construct an iterator over the rows of this batch by calling `row` with a [`Row`] view
+    /// of each one in order.
+    pub fn rows<'a, R, F>(&'a self, mut row: F) -> impl Iterator<Item = R> + 'a
+    where
+        F: FnMut(Row<'a>) -> R + 'a,
+    {
+        (0..self.len).map(move |index| {
+            row(Row {
+                columns: self,
+                index,
+            })
+        })
+    }
+}
+
+/// A builder to split a slice of rows into [`Columns`], one field at a time.
+pub struct ColumnsBuilder<'a, R> {
+    rows: &'a [R],
+    columns: Vec<(String, Column)>,
+}
+
+impl<'a, R> ColumnsBuilder<'a, R> {
+    /// Add a column with the given `name`, whose value in each row is given by `get`.
+    pub fn column<T, F>(mut self, name: impl Into<String>, get: F) -> Self
+    where
+        T: ColumnValue,
+        F: FnMut(&R) -> T,
+    {
+        let values = self.rows.iter().map(get).collect();
+        self.columns.push((name.into(), T::into_column(values)));
+        self
+    }
+
+    /// Construct a new [`Columns`] batch.
+    pub fn build(self) -> Columns {
+        Columns {
+            len: self.rows.len(),
+            columns: self.columns,
+        }
+    }
+}
+
+/// A view of a single row of a [`Columns`] batch.
+#[derive(Copy, Clone)]
+pub struct Row<'a> {
+    columns: &'a Columns,
+    index: usize,
+}
+
+impl<'a> Row<'a> {
+    /// The index of this row in its batch.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return the value of this row in the column with the given `name`, if there is such a
+    /// column of type `T`.
+    pub fn get<T: ColumnValue>(&self, name: &str) -> Option<T> {
+        let values = T::column_values(self.columns.column(name)?)?;
+        values.get(self.index).copied()
+    }
+}
+
+struct ColumnsVisitor {
+    schema: Arc<[(String, ColumnKind)]>,
+}
+
+#[async_trait]
+impl Visitor for ColumnsVisitor {
+    type Value = Columns;
+
+    fn expecting() -> &'static str {
+        "a row count followed by a map of column names to typed arrays"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let len: usize = seq
+            .next_element(())
+            .await?
+            .ok_or_else(|| de::Error::invalid_length(0, Self::expecting()))?;
+
+        let ColumnMap(columns) = seq
+            .next_element((self.schema, len))
+            .await?
+            .ok_or_else(|| de::Error::invalid_length(1, Self::expecting()))?;
+
+        Ok(Columns { len, columns })
+    }
+}
+
+struct ColumnMap<C>(C);
+
+struct ColumnMapVisitor {
+    schema: Arc<[(String, ColumnKind)]>,
+    len: usize,
+}
+
+#[async_trait]
+impl Visitor for ColumnMapVisitor {
+    type Value = ColumnMap<Vec<(String, Column)>>;
+
+    fn expecting() -> &'static str {
+        "a map of column names to typed arrays"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut columns: Vec<Option<Column>> = vec![None; self.schema.len()];
+
+        while let Some(name) = map.next_key::<String>(()).await? {
+            let index = match self.schema.iter().position(|(column, _)| *column == name) {
+                Some(index) => index,
+                None => {
+                    let IgnoredAny = map.next_value(()).await?;
+                    continue;
+                }
+            };
+
+            if columns[index].is_some() {
+                return Err(de::Error::custom(format_args!("duplicate column {name}")));
+            }
+
+            let (_, kind) = self.schema[index];
+            let column: Column = map
+                .next_value(kind)
+                .await
+                .map_err(|cause| de::Error::with_key(cause, &name))?;
+
+            if column.len() != self.len {
+                let cause: A::Error = de::Error::invalid_length(
+                    column.len(),
+                    format_args!("a column of length {}", self.len),
+                );
+
+                return Err(de::Error::with_key(cause, &name));
+            }
+
+            columns[index] = Some(column);
+        }
+
+        self.schema
+            .iter()
+            .zip(columns)
+            .map(|((name, _), column)| match column {
+                Some(column) => Ok((name.clone(), column)),
+                None => Err(de::Error::custom(format_args!("missing column {name}"))),
+            })
+            .collect::<Result<_, A::Error>>()
+            .map(ColumnMap)
+    }
+}
+
+#[async_trait]
+impl FromStream for ColumnMap<Vec<(String, Column)>> {
+    type Context = (Arc<[(String, ColumnKind)]>, usize);
+
+    async fn from_stream<D: Decoder>(
+        (schema, len): Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_map(ColumnMapVisitor { schema, len }).await
+    }
+}
+
+impl<'en> IntoStream<'en> for ColumnMap<&'en [(String, Column)]> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut map = encoder.encode_map(Some(self.0.len()))?;
+
+        for (name, column) in self.0 {
+            map.encode_key_str(name)?;
+            map.encode_value(column)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'en> IntoStream<'en> for ColumnMap<Vec<(String, Column)>> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut map = encoder.encode_map(Some(self.0.len()))?;
+
+        for (name, column) in self.0 {
+            map.encode_entry(name, column)?;
+        }
+
+        map.end()
+    }
+}
+
+#[async_trait]
+impl FromStream for Columns {
+    type Context = Arc<[(String, ColumnKind)]>;
+
+    async fn from_stream<D: Decoder>(
+        schema: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_seq(ColumnsVisitor { schema }).await
+    }
+}
+
+impl<'en> ToStream<'en> for Columns {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut seq = encoder.encode_tuple(2)?;
+        seq.encode_element(self.len)?;
+        seq.encode_element(ColumnMap(self.columns.as_slice()))?;
+        seq.end()
+    }
+}
+
+impl<'en> IntoStream<'en> for Columns {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut seq = encoder.encode_tuple(2)?;
+        seq.encode_element(self.len)?;
+        seq.encode_element(ColumnMap(self.columns))?;
+        seq.end()
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::{Deref, DerefMut};
+
+use async_trait::async_trait;
+use futures::{future, stream};
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+const BUF_SIZE: usize = 4_096;
+
+macro_rules! endian {
+    ($(#[$meta:meta])* $name:ident, $to_bytes:ident, $from_bytes:ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+        pub struct $name<T>(pub T);
+
+        impl<T> $name<T> {
+            /// Return the wrapped value.
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> From<T> for $name<T> {
+            fn from(value: T) -> Self {
+                Self(value)
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> DerefMut for $name<T> {
+            fn deref_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+
+        endian!(@impls $name, $to_bytes, $from_bytes; u16, u32, u64, i16, i32, i64, f32, f64);
+    };
+    (@impls $name:ident, $to_bytes:ident, $from_bytes:ident; $($ty:ty),+) => {
+        $(
+            impl<'en> IntoStream<'en> for $name<$ty> {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.encode_array_u8(stream::once(future::ready(self.0.$to_bytes())))
+                }
+            }
+
+            impl<'en> ToStream<'en> for $name<$ty> {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    encoder.encode_array_u8(stream::once(future::ready(self.0.$to_bytes())))
+                }
+            }
+
+            #[async_trait]
+            impl FromStream for $name<$ty> {
+                type Context = ();
+
+                async fn from_stream<D: Decoder>(
+                    _context: (),
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    let values: $name<Vec<$ty>> = $name::from_stream((), decoder).await?;
+
+                    match values.0.as_slice() {
+                        [value] => Ok($name(*value)),
+                        values => Err(de::Error::invalid_length(
+                            values.len(),
+                            concat!("a single ", stringify!($ty)),
+                        )),
+                    }
+                }
+            }
+
+            impl<'en> IntoStream<'en> for $name<Vec<$ty>> {
+                fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                    let bytes = self.0.into_iter().flat_map(<$ty>::$to_bytes);
+                    encoder.encode_array_u8(stream::once(future::ready(bytes)))
+                }
+            }
+
+            impl<'en> ToStream<'en> for $name<Vec<$ty>> {
+                fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                    let bytes = self.0.iter().copied().flat_map(<$ty>::$to_bytes);
+                    encoder.encode_array_u8(stream::once(future::ready(bytes)))
+                }
+            }
+
+            #[async_trait]
+            impl Visitor for EndianVisitor<$name<Vec<$ty>>> {
+                type Value = $name<Vec<$ty>>;
+
+                fn expecting() -> &'static str {
+                    concat!("an array of bytes encoding a sequence of ", stringify!($ty))
+                }
+
+                async fn visit_array_u8<A: ArrayAccess<u8>>(
+                    self,
+                    mut array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let mut bytes = Vec::new();
+                    let mut buffer = [0u8; BUF_SIZE];
+
+                    loop {
+                        match array.buffer(&mut buffer).await? {
+                            0 => break,
+                            len => bytes.extend_from_slice(&buffer[..len]),
+                        }
+                    }
+
+                    Self::decode(bytes)
+                }
+
+                async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let mut bytes = Vec::with_capacity(de::size_hint::cautious(seq.size_hint()));
+
+                    while let Some(byte) = seq.next_element(()).await? {
+                        bytes.push(byte);
+                    }
+
+                    Self::decode(bytes)
+                }
+            }
+
+            impl EndianVisitor<$name<Vec<$ty>>> {
+                fn decode<E: de::Error>(bytes: Vec<u8>) -> Result<$name<Vec<$ty>>, E> {
+                    const SIZE: usize = std::mem::size_of::<$ty>();
+
+                    if bytes.len() % SIZE != 0 {
+                        return Err(de::Error::invalid_length(bytes.len(), Self::expecting()));
+                    }
+
+                    let values = bytes
+                        .chunks_exact(SIZE)
+                        .map(|chunk| {
+                            let mut value = [0u8; SIZE];
+                            value.copy_from_slice(chunk);
+                            <$ty>::$from_bytes(value)
+                        })
+                        .collect();
+
+                    Ok($name(values))
+                }
+            }
+
+            #[async_trait]
+            impl FromStream for $name<Vec<$ty>> {
+                type Context = ();
+
+                async fn from_stream<D: Decoder>(
+                    _context: (),
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    let visitor = EndianVisitor::<Self> {
+                        marker: std::marker::PhantomData,
+                    };
+
+                    decoder.decode_array_u8(visitor).await
+                }
+            }
+        )+
+    };
+}
+
+struct EndianVisitor<T> {
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+endian!(
+    /// A number, or a `Vec` of numbers, which is encoded as an array of `u8`s in little-endian
+    /// byte order, e.g. to match the layout of an existing binary blob.
+    LittleEndian,
+    to_le_bytes,
+    from_le_bytes
+);
+
+endian!(
+    /// A number, or a `Vec` of numbers, which is encoded as an array of `u8`s in big-endian
+    /// byte order, e.g. to match the layout of an existing binary blob.
+    BigEndian,
+    to_be_bytes,
+    from_be_bytes
+);
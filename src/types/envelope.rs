@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+
+use crate::de::{Decoder, FromStream, Pair, PairVisitor};
+use crate::en::{EncodeMap, Encoder, IntoStream, ToStream};
+
+const META: &str = "meta";
+const BODY: &str = "body";
+
+/// A payload of type `T` preceded by metadata of type `M`, encoded as a map of the form
+/// `{"meta": <meta>, "body": <body>}`, e.g. one record in a stream of logs.
+///
+/// The metadata is always decoded completely before the body, and the body is decoded using the
+/// context of `T`, so a large body can be streamed (e.g. to a file) without buffering it.
+/// An `Envelope` can also be decoded from a tuple of the form `[<meta>, <body>]`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Envelope<M, T> {
+    pub meta: M,
+    pub body: T,
+}
+
+impl<M, T> Envelope<M, T> {
+    /// Construct a new [`Envelope`].
+    pub fn new(meta: M, body: T) -> Self {
+        Self { meta, body }
+    }
+
+    /// Return the metadata and body of this [`Envelope`].
+    pub fn into_inner(self) -> (M, T) {
+        (self.meta, self.body)
+    }
+}
+
+/// The body of an [`Envelope`], decoded with its metadata as part of its context.
+struct Body<M, T> {
+    envelope: Envelope<M, T>,
+}
+
+#[async_trait]
+impl<M, T> FromStream for Body<M, T>
+where
+    M: FromStream<Context = ()>,
+    T: FromStream,
+{
+    type Context = (M, T::Context);
+
+    async fn from_stream<D: Decoder>(
+        (meta, context): Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let body = T::from_stream(context, decoder).await?;
+        let envelope = Envelope { meta, body };
+        Ok(Self { envelope })
+    }
+}
+
+impl<M, T> Pair for Body<M, T>
+where
+    M: FromStream<Context = ()>,
+    T: FromStream,
+{
+    type First = M;
+    type Outer = T::Context;
+
+    const KEYS: [&'static str; 2] = [META, BODY];
+
+    fn expecting() -> &'static str {
+        "metadata followed by a body"
+    }
+}
+
+#[async_trait]
+impl<M, T> FromStream for Envelope<M, T>
+where
+    M: FromStream<Context = ()>,
+    T: FromStream,
+{
+    type Context = T::Context;
+
+    async fn from_stream<D: Decoder>(
+        context: Self::Context,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let Body { envelope } = decoder.decode_map(PairVisitor::new(context)).await?;
+        Ok(envelope)
+    }
+}
+
+impl<'en, M, T> IntoStream<'en> for Envelope<M, T>
+where
+    M: IntoStream<'en> + 'en,
+    T: IntoStream<'en> + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut map = encoder.encode_map(Some(2))?;
+        map.encode_entry(META, self.meta)?;
+        map.encode_entry(BODY, self.body)?;
+        map.end()
+    }
+}
+
+impl<'en, M, T> ToStream<'en> for Envelope<M, T>
+where
+    M: ToStream<'en> + 'en,
+    T: ToStream<'en> + 'en,
+{
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut map = encoder.encode_map(Some(2))?;
+        map.encode_entry(META, &self.meta)?;
+        map.encode_entry(BODY, &self.body)?;
+        map.end()
+    }
+}
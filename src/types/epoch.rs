@@ -0,0 +1,416 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+
+use crate::de::{self, Decoder, FromStream, Visitor};
+use crate::en::{self, Encoder, IntoStream, ToStream};
+
+const SECS_PER_DAY: i64 = 86_400;
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+const NANOS_PER_MILLI: u32 = 1_000_000;
+
+/// A point in time which can be converted to and from a Unix timestamp, for use with
+/// [`EpochSeconds`], [`EpochMillis`], and [`Rfc3339`].
+///
+/// A bare `SystemTime` is encoded as a tuple of whole seconds since the epoch (negative before
+/// the epoch) and nanoseconds, so use one of these wrappers to pin its representation on the
+/// wire. Implemented for `SystemTime`, for `chrono::DateTime<Utc>` with the `chrono` feature
+/// flag, and for `time::OffsetDateTime` with the `time` feature flag.
+pub trait Timestamp: Sized {
+    /// Return the number of whole seconds since the Unix epoch (rounded down) and the number
+    /// of nanoseconds since that second, or `None` if this time is out of range.
+    fn to_unix(&self) -> Option<(i64, u32)>;
+
+    /// Construct a new timestamp from a number of seconds since the Unix epoch and a number
+    /// of nanoseconds since that second, or return `None` if it's out of range.
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self>;
+}
+
+impl Timestamp for SystemTime {
+    fn to_unix(&self) -> Option<(i64, u32)> {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(since) => Some((i64::try_from(since.as_secs()).ok()?, since.subsec_nanos())),
+            Err(before) => {
+                let before = before.duration();
+                let secs = i64::try_from(before.as_secs()).ok()?;
+
+                match before.subsec_nanos() {
+                    0 => Some((-secs, 0)),
+                    nanos => Some((-secs - 1, NANOS_PER_SEC - nanos)),
+                }
+            }
+        }
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        let nanos = Duration::from_nanos(nanos.into());
+
+        if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))?
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+        }
+        .checked_add(nanos)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Timestamp for chrono::DateTime<chrono::Utc> {
+    fn to_unix(&self) -> Option<(i64, u32)> {
+        // chrono represents a leap second as a nanosecond value past the end of the second
+        let nanos = Ord::min(self.timestamp_subsec_nanos(), NANOS_PER_SEC - 1);
+        Some((self.timestamp(), nanos))
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        Self::from_timestamp(secs, nanos)
+    }
+}
+
+#[cfg(feature = "time")]
+impl Timestamp for time::OffsetDateTime {
+    fn to_unix(&self) -> Option<(i64, u32)> {
+        Some((self.unix_timestamp(), self.nanosecond()))
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        Self::from_unix_timestamp(secs)
+            .ok()?
+            .replace_nanosecond(nanos)
+            .ok()
+    }
+}
+
+macro_rules! timestamp_wrapper {
+    ($name:ident) => {
+        impl<T> $name<T> {
+            /// Return the wrapped timestamp.
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> From<T> for $name<T> {
+            fn from(time: T) -> Self {
+                Self(time)
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> DerefMut for $name<T> {
+            fn deref_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+
+        impl<'en, T: Timestamp> IntoStream<'en> for $name<T> {
+            fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+                self.encode(encoder)
+            }
+        }
+
+        impl<'en, T: Timestamp> ToStream<'en> for $name<T> {
+            fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+                self.encode(encoder)
+            }
+        }
+    };
+}
+
+/// A timestamp which is always encoded as an integer number of seconds since the Unix epoch,
+/// rounded down, e.g. for a protocol which mandates this representation.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EpochSeconds<T = SystemTime>(pub T);
+
+/// A timestamp which is always encoded as an integer number of milliseconds since the Unix
+/// epoch, rounded down, e.g. for a protocol which mandates this representation.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EpochMillis<T = SystemTime>(pub T);
+
+/// A timestamp which is always encoded as an RFC 3339 string in UTC, like
+/// `"2024-05-01T12:30:00.250Z"`, e.g. for a protocol which mandates this representation.
+///
+/// A fractional second is encoded with 3, 6, or 9 digits, and omitted if zero. When decoding,
+/// a time with a UTC offset like `"2024-05-01T14:30:00+02:00"` is converted to UTC.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Rfc3339<T = SystemTime>(pub T);
+
+timestamp_wrapper!(EpochSeconds);
+timestamp_wrapper!(EpochMillis);
+timestamp_wrapper!(Rfc3339);
+
+fn out_of_range<E: en::Error>() -> E {
+    E::custom("timestamp is out of range")
+}
+
+impl<T: Timestamp> EpochSeconds<T> {
+    fn from_count(secs: i64) -> Option<T> {
+        T::from_unix(secs, 0)
+    }
+}
+
+impl<T: Timestamp> EpochMillis<T> {
+    fn from_count(millis: i64) -> Option<T> {
+        let nanos = millis.rem_euclid(1_000) as u32 * NANOS_PER_MILLI;
+        T::from_unix(millis.div_euclid(1_000), nanos)
+    }
+}
+
+impl<T: Timestamp> EpochSeconds<T> {
+    fn encode<'en, E: Encoder<'en>>(&self, encoder: E) -> Result<E::Ok, E::Error> {
+        let (secs, _nanos) = self.0.to_unix().ok_or_else(out_of_range)?;
+        encoder.encode_i64(secs)
+    }
+}
+
+impl<T: Timestamp> EpochMillis<T> {
+    fn encode<'en, E: Encoder<'en>>(&self, encoder: E) -> Result<E::Ok, E::Error> {
+        let (secs, nanos) = self.0.to_unix().ok_or_else(out_of_range)?;
+
+        let millis = secs
+            .checked_mul(1_000)
+            .and_then(|millis| millis.checked_add((nanos / NANOS_PER_MILLI).into()))
+            .ok_or_else(out_of_range)?;
+
+        encoder.encode_i64(millis)
+    }
+}
+
+impl<T: Timestamp> Rfc3339<T> {
+    fn encode<'en, E: Encoder<'en>>(&self, encoder: E) -> Result<E::Ok, E::Error> {
+        let (secs, nanos) = self.0.to_unix().ok_or_else(out_of_range)?;
+        let formatted = format_rfc3339(secs, nanos).ok_or_else(out_of_range)?;
+        encoder.encode_str(&formatted)
+    }
+}
+
+struct EpochVisitor<W> {
+    marker: PhantomData<fn() -> W>,
+}
+
+macro_rules! decode_epoch {
+    ($name:ident, $expecting:literal) => {
+        impl<T: Timestamp> Visitor for EpochVisitor<$name<T>>
+        where
+            T: Send,
+        {
+            type Value = $name<T>;
+
+            fn expecting() -> &'static str {
+                $expecting
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                $name::<T>::from_count(v)
+                    .map($name)
+                    .ok_or_else(|| de::Error::invalid_value(v, Self::expecting()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                let count =
+                    i64::try_from(v).map_err(|_| de::Error::invalid_value(v, Self::expecting()))?;
+
+                self.visit_i64(count)
+            }
+        }
+
+        #[async_trait]
+        impl<T: Timestamp + Send> FromStream for $name<T> {
+            type Context = ();
+
+            async fn from_stream<D: Decoder>(
+                _context: (),
+                decoder: &mut D,
+            ) -> Result<Self, D::Error> {
+                let visitor = EpochVisitor::<Self> {
+                    marker: PhantomData,
+                };
+                decoder.decode_i64(visitor).await
+            }
+        }
+    };
+}
+
+decode_epoch!(EpochSeconds, "a number of seconds since the Unix epoch");
+decode_epoch!(EpochMillis, "a number of milliseconds since the Unix epoch");
+
+impl<T: Timestamp + Send> Visitor for EpochVisitor<Rfc3339<T>> {
+    type Value = Rfc3339<T>;
+
+    fn expecting() -> &'static str {
+        "an RFC 3339 timestamp like \"2024-05-01T12:30:00Z\""
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        parse_rfc3339(&v)
+            .and_then(|(secs, nanos)| T::from_unix(secs, nanos))
+            .map(Rfc3339)
+            .ok_or_else(|| de::Error::invalid_value(v, Self::expecting()))
+    }
+}
+
+#[async_trait]
+impl<T: Timestamp + Send> FromStream for Rfc3339<T> {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = EpochVisitor::<Self> {
+            marker: PhantomData,
+        };
+        decoder.decode_string(visitor).await
+    }
+}
+
+// the number of days since the Unix epoch of the given proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - (era * 400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = (year_of_era * 365) + (year_of_era / 4) - (year_of_era / 100) + day_of_year;
+    (era * 146_097) + day_of_era - 719_468
+}
+
+// the proleptic Gregorian date of the given number of days since the Unix epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - (era * 146_097);
+    let year_of_era =
+        (day_of_era - (day_of_era / 1_460) + (day_of_era / 36_524) - (day_of_era / 146_096)) / 365;
+    let day_of_year = day_of_era - ((365 * year_of_era) + (year_of_era / 4) - (year_of_era / 100));
+    let mp = ((5 * day_of_year) + 2) / 153;
+    let day = day_of_year - ((153 * mp) + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + (era * 400) + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn format_rfc3339(secs: i64, nanos: u32) -> Option<String> {
+    let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+
+    let time = secs.rem_euclid(SECS_PER_DAY);
+    let (hour, minute, second) = (time / 3_600, (time % 3_600) / 60, time % 60);
+
+    let mut formatted = format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}");
+
+    if nanos != 0 {
+        let fraction = format!("{nanos:09}");
+        let digits = match fraction.trim_end_matches('0').len() {
+            0..=3 => 3,
+            4..=6 => 6,
+            _ => 9,
+        };
+
+        formatted.push('.');
+        formatted.push_str(&fraction[..digits]);
+    }
+
+    formatted.push('Z');
+    Some(formatted)
+}
+
+fn parse_rfc3339(s: &str) -> Option<(i64, u32)> {
+    fn number(s: &str) -> Option<i64> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    let bytes = s.as_bytes();
+    if !s.is_ascii() || bytes.len() < 20 {
+        return None;
+    }
+
+    if bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+
+    if !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+
+    let year = number(&s[0..4])?;
+    let month = number(&s[5..7])?;
+    let day = number(&s[8..10])?;
+    let hour = number(&s[11..13])?;
+    let minute = number(&s[14..16])?;
+    let second = number(&s[17..19])?;
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    // a leap second is accepted, and falls on the first second of the next minute
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0;
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+
+        if len == 0 {
+            return None;
+        }
+
+        // digits past the ninth are truncated
+        for (i, digit) in fraction[..len].bytes().take(9).enumerate() {
+            nanos += u32::from(digit - b'0') * 10u32.pow(8 - i as u32);
+        }
+
+        rest = &fraction[len..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let hours = number(&rest[1..3])?;
+            let minutes = number(&rest[4..6])?;
+
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+
+            let offset = (hours * 3_600) + (minutes * 60);
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    let secs = (days * SECS_PER_DAY) + (hour * 3_600) + (minute * 60) + second - offset;
+    Some((secs, nanos))
+}
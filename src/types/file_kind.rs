@@ -0,0 +1,27 @@
+use std::fs::FileType;
+
+crate::str_enum! {
+    /// The kind of a filesystem entry, e.g. in a streamed directory listing.
+    ///
+    /// Encoded as one of the strings `"file"`, `"dir"`, `"symlink"`, or `"other"`.
+    pub enum FileKind {
+        File => "file",
+        Dir => "dir",
+        Symlink => "symlink",
+        Other => "other",
+    }
+}
+
+impl From<FileType> for FileKind {
+    fn from(file_type: FileType) -> Self {
+        if file_type.is_symlink() {
+            Self::Symlink
+        } else if file_type.is_dir() {
+            Self::Dir
+        } else if file_type.is_file() {
+            Self::File
+        } else {
+            Self::Other
+        }
+    }
+}
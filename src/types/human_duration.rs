@@ -0,0 +1,145 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::de::{self, Decoder, FromStream, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+const UNITS: [(&str, u128); 7] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// A [`Duration`] which is encoded as a human-readable string like `"1h30m"` or `"250ms"`.
+///
+/// A `HumanDuration` is decoded from a string of one or more integer components, each followed
+/// by one of the units `d`, `h`, `m`, `s`, `ms`, `us`, or `ns`, or from an integer number of
+/// seconds. It is encoded using the units `h` through `ns`, largest first, e.g. `"25h1m500ms"`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    /// Return the wrapped [`Duration`].
+    pub fn into_inner(self) -> Duration {
+        self.0
+    }
+
+    fn parse(s: &str) -> Option<Duration> {
+        let mut nanos: u128 = 0;
+        let mut rest = s.trim();
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+            let value: u128 = rest[..digits].parse().ok()?;
+            rest = &rest[digits..];
+
+            let units = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+
+            let (_, scale) = UNITS.iter().find(|(unit, _)| *unit == &rest[..units])?;
+            nanos = nanos.checked_add(value.checked_mul(*scale)?)?;
+            rest = rest[units..].trim_start();
+        }
+
+        let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+        Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl Deref for HumanDuration {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl DerefMut for HumanDuration {
+    fn deref_mut(&mut self) -> &mut Duration {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+
+        for (unit, scale) in &UNITS[1..] {
+            if nanos >= *scale {
+                write!(f, "{}{}", nanos / scale, unit)?;
+                nanos %= scale;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct HumanDurationVisitor;
+
+impl Visitor for HumanDurationVisitor {
+    type Value = HumanDuration;
+
+    fn expecting() -> &'static str {
+        "a duration like \"1h30m\" or \"250ms\", or a number of seconds"
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(HumanDuration(Duration::from_secs(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        HumanDuration::parse(&v)
+            .map(HumanDuration)
+            .ok_or_else(|| de::Error::invalid_value(v, Self::expecting()))
+    }
+}
+
+#[async_trait]
+impl FromStream for HumanDuration {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_string(HumanDurationVisitor).await
+    }
+}
+
+impl<'en> IntoStream<'en> for HumanDuration {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.to_string())
+    }
+}
+
+impl<'en> ToStream<'en> for HumanDuration {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.to_string())
+    }
+}
@@ -0,0 +1,96 @@
+/// Implement `FromStream`, `IntoStream`, and `ToStream` for one or more tuple newtypes,
+/// each of which is encoded and decoded exactly like the single value it wraps.
+///
+/// ```
+/// pub struct UserId(u64);
+/// pub struct Name(String);
+///
+/// destream::impl_transparent!(UserId(u64), Name(String));
+/// ```
+#[macro_export]
+macro_rules! impl_transparent {
+    ($($name:ident($inner:ty)),+ $(,)?) => {
+        $(
+            #[$crate::__private::async_trait]
+            impl $crate::de::FromStream for $name {
+                type Context = <$inner as $crate::de::FromStream>::Context;
+
+                async fn from_stream<D: $crate::de::Decoder>(
+                    context: Self::Context,
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    <$inner as $crate::de::FromStream>::from_stream(context, decoder)
+                        .await
+                        .map(Self)
+                }
+            }
+
+            impl<'en> $crate::en::IntoStream<'en> for $name {
+                fn into_stream<E: $crate::en::Encoder<'en>>(
+                    self,
+                    encoder: E,
+                ) -> Result<E::Ok, E::Error> {
+                    $crate::en::IntoStream::into_stream(self.0, encoder)
+                }
+            }
+
+            impl<'en> $crate::en::ToStream<'en> for $name {
+                fn to_stream<E: $crate::en::Encoder<'en>>(
+                    &'en self,
+                    encoder: E,
+                ) -> Result<E::Ok, E::Error> {
+                    $crate::en::ToStream::to_stream(&self.0, encoder)
+                }
+            }
+        )+
+    };
+}
+
+/// Implement `FromStream`, `IntoStream`, and `ToStream` for a tuple newtype of a fixed-size
+/// byte array, such as a hash or key, which is encoded as a binary value like a
+/// [`ByteArray`](crate::types::ByteArray).
+///
+/// ```
+/// pub struct MyHash([u8; 32]);
+///
+/// destream::impl_fixed_bytes!(MyHash, 32);
+/// ```
+#[macro_export]
+macro_rules! impl_fixed_bytes {
+    ($name:ident, $len:expr) => {
+        #[$crate::__private::async_trait]
+        impl $crate::de::FromStream for $name {
+            type Context = ();
+
+            async fn from_stream<D: $crate::de::Decoder>(
+                context: (),
+                decoder: &mut D,
+            ) -> Result<Self, D::Error> {
+                <$crate::types::ByteArray<$len> as $crate::de::FromStream>::from_stream(
+                    context, decoder,
+                )
+                .await
+                .map(|array| Self(array.into_inner()))
+            }
+        }
+
+        impl<'en> $crate::en::IntoStream<'en> for $name {
+            fn into_stream<E: $crate::en::Encoder<'en>>(
+                self,
+                encoder: E,
+            ) -> Result<E::Ok, E::Error> {
+                let array: $crate::types::ByteArray<$len> = self.0.into();
+                $crate::en::IntoStream::into_stream(array, encoder)
+            }
+        }
+
+        impl<'en> $crate::en::ToStream<'en> for $name {
+            fn to_stream<E: $crate::en::Encoder<'en>>(
+                &'en self,
+                encoder: E,
+            ) -> Result<E::Ok, E::Error> {
+                $crate::en::Encoder::collect_bytes(encoder, self.0.iter().copied())
+            }
+        }
+    };
+}
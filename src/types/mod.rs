@@ -0,0 +1,37 @@
+//! Wrapper types which control how a value is encoded and decoded.
+//!
+//! Enable `LittleEndian` and `BigEndian` using the `endian` feature flag. Enable [`Timestamp`]
+//! support for `chrono::DateTime<Utc>` and `time::OffsetDateTime` using the `chrono` and `time`
+//! feature flags.
+
+mod as_array;
+mod bitmask;
+mod byte_array;
+mod columns;
+#[cfg(feature = "endian")]
+mod endian;
+mod envelope;
+mod epoch;
+mod file_kind;
+mod human_duration;
+mod macros;
+mod ordered;
+mod repr;
+mod shared;
+mod str_enum;
+mod unique;
+
+pub use as_array::AsArray;
+pub use bitmask::Bitmask;
+pub use byte_array::ByteArray;
+pub use columns::{Column, ColumnKind, ColumnValue, Columns, ColumnsBuilder, Row};
+#[cfg(feature = "endian")]
+pub use endian::{BigEndian, LittleEndian};
+pub use envelope::Envelope;
+pub use epoch::{EpochMillis, EpochSeconds, Rfc3339, Timestamp};
+pub use file_kind::FileKind;
+pub use human_duration::HumanDuration;
+pub use ordered::OrderedEntries;
+pub use repr::{AsBytes, AsMap, AsSeq, AsString, StringKeys};
+pub use shared::{ArcPool, RefId, Shared, WeakRef};
+pub use unique::{DuplicatePolicy, UniqueCollection, UniqueSeq};
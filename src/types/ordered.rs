@@ -0,0 +1,127 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use async_trait::async_trait;
+
+use crate::de::{size_hint, Decoder, FromStream, MapAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+/// The entries of a map, in the order in which they were encoded.
+///
+/// Unlike `HashMap` or `BTreeMap`, this preserves the order of the entries on the wire,
+/// e.g. to verify a signature computed over a canonical document. Duplicate keys are retained.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct OrderedEntries<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> OrderedEntries<K, V> {
+    /// Return the wrapped entries.
+    pub fn into_inner(self) -> Vec<(K, V)> {
+        self.0
+    }
+}
+
+impl<K, V> Default for OrderedEntries<K, V> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<K, V> Deref for OrderedEntries<K, V> {
+    type Target = Vec<(K, V)>;
+
+    fn deref(&self) -> &Vec<(K, V)> {
+        &self.0
+    }
+}
+
+impl<K, V> DerefMut for OrderedEntries<K, V> {
+    fn deref_mut(&mut self) -> &mut Vec<(K, V)> {
+        &mut self.0
+    }
+}
+
+impl<K, V> From<Vec<(K, V)>> for OrderedEntries<K, V> {
+    fn from(entries: Vec<(K, V)>) -> Self {
+        Self(entries)
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for OrderedEntries<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<K, V> IntoIterator for OrderedEntries<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+struct OrderedEntriesVisitor<K, V> {
+    marker: PhantomData<(K, V)>,
+}
+
+#[async_trait]
+impl<K, V> Visitor for OrderedEntriesVisitor<K, V>
+where
+    K: FromStream<Context = ()>,
+    V: FromStream<Context = ()>,
+{
+    type Value = OrderedEntries<K, V>;
+
+    fn expecting() -> &'static str {
+        "a map"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(size_hint::cautious(map.size_hint()));
+
+        while let Some(key) = map.next_key(()).await? {
+            let value = map.next_value(()).await?;
+            entries.push((key, value));
+        }
+
+        Ok(OrderedEntries(entries))
+    }
+}
+
+#[async_trait]
+impl<K, V> FromStream for OrderedEntries<K, V>
+where
+    K: FromStream<Context = ()>,
+    V: FromStream<Context = ()>,
+{
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = OrderedEntriesVisitor {
+            marker: PhantomData,
+        };
+
+        decoder.decode_map(visitor).await
+    }
+}
+
+impl<'en, K, V> IntoStream<'en> for OrderedEntries<K, V>
+where
+    K: IntoStream<'en> + 'en,
+    V: IntoStream<'en> + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(self.0)
+    }
+}
+
+impl<'en, K, V> ToStream<'en> for OrderedEntries<K, V>
+where
+    K: ToStream<'en> + 'en,
+    V: ToStream<'en> + 'en,
+{
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
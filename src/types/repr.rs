@@ -0,0 +1,346 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::de::{self, Decoder, FromStream, MapAccess, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+macro_rules! newtype {
+    ($($(#[$meta:meta])* $name:ident),+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+            pub struct $name<T>(pub T);
+
+            impl<T> $name<T> {
+                /// Return the wrapped value.
+                pub fn into_inner(self) -> T {
+                    self.0
+                }
+            }
+
+            impl<T> From<T> for $name<T> {
+                fn from(value: T) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl<T> Deref for $name<T> {
+                type Target = T;
+
+                fn deref(&self) -> &T {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $name<T> {
+                fn deref_mut(&mut self) -> &mut T {
+                    &mut self.0
+                }
+            }
+        )+
+    };
+}
+
+newtype!(
+    /// A collection of key-value pairs which is encoded and decoded as a map,
+    /// e.g. `AsMap<Vec<(K, V)>>`.
+    AsMap,
+    /// A collection which is encoded and decoded as a sequence of its items,
+    /// e.g. `AsSeq<HashMap<K, V>>` as a sequence of `(K, V)` tuples.
+    AsSeq,
+    /// A value which is encoded as a string using its [`fmt::Display`] implementation,
+    /// and decoded from a string using its [`FromStr`] implementation, e.g. `AsString<Uuid>`.
+    AsString,
+    /// A value which is encoded and decoded as binary, e.g. `AsBytes<Vec<u8>>`.
+    AsBytes,
+    /// A map whose keys are encoded as strings using their [`fmt::Display`] implementation,
+    /// and decoded from strings using their [`FromStr`] implementation,
+    /// e.g. `StringKeys<HashMap<u64, V>>` for formats which only support string keys.
+    StringKeys
+);
+
+struct MapVisitor<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+#[async_trait]
+impl<T, K, V> Visitor for MapVisitor<AsMap<T>>
+where
+    T: FromIterator<(K, V)> + IntoIterator<Item = (K, V)> + Send,
+    K: FromStream<Context = ()>,
+    V: FromStream<Context = ()>,
+{
+    type Value = AsMap<T>;
+
+    fn expecting() -> &'static str {
+        "a map"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(de::size_hint::cautious(map.size_hint()));
+
+        while let Some(key) = map.next_key(()).await? {
+            let value = map.next_value(()).await?;
+            entries.push((key, value));
+        }
+
+        Ok(AsMap(entries.into_iter().collect()))
+    }
+}
+
+#[async_trait]
+impl<T, K, V> FromStream for AsMap<T>
+where
+    T: FromIterator<(K, V)> + IntoIterator<Item = (K, V)> + Send,
+    K: FromStream<Context = ()>,
+    V: FromStream<Context = ()>,
+{
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = MapVisitor::<Self> {
+            marker: PhantomData,
+        };
+
+        decoder.decode_map(visitor).await
+    }
+}
+
+impl<'en, T, K, V> IntoStream<'en> for AsMap<T>
+where
+    T: IntoIterator<Item = (K, V)>,
+    K: IntoStream<'en> + 'en,
+    V: IntoStream<'en> + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(self.0)
+    }
+}
+
+impl<'en, T: 'en, K, V> ToStream<'en> for AsMap<T>
+where
+    &'en T: IntoIterator<Item = (K, V)>,
+    K: IntoStream<'en> + 'en,
+    V: IntoStream<'en> + 'en,
+{
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(&self.0)
+    }
+}
+
+struct SeqVisitor<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+#[async_trait]
+impl<T> Visitor for SeqVisitor<AsSeq<T>>
+where
+    T: FromIterator<T::Item> + IntoIterator + Send,
+    T::Item: FromStream<Context = ()>,
+{
+    type Value = AsSeq<T>;
+
+    fn expecting() -> &'static str {
+        "a sequence"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::with_capacity(de::size_hint::cautious(seq.size_hint()));
+
+        while let Some(item) = seq.next_element(()).await? {
+            items.push(item);
+        }
+
+        Ok(AsSeq(items.into_iter().collect()))
+    }
+}
+
+#[async_trait]
+impl<T> FromStream for AsSeq<T>
+where
+    T: FromIterator<T::Item> + IntoIterator + Send,
+    T::Item: FromStream<Context = ()>,
+{
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = SeqVisitor::<Self> {
+            marker: PhantomData,
+        };
+
+        decoder.decode_seq(visitor).await
+    }
+}
+
+impl<'en, T> IntoStream<'en> for AsSeq<T>
+where
+    T: IntoIterator,
+    T::Item: IntoStream<'en> + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_seq(self.0)
+    }
+}
+
+impl<'en, T: 'en> ToStream<'en> for AsSeq<T>
+where
+    &'en T: IntoIterator,
+    <&'en T as IntoIterator>::Item: IntoStream<'en> + 'en,
+{
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_seq(&self.0)
+    }
+}
+
+struct StringVisitor<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Visitor for StringVisitor<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = AsString<T>;
+
+    fn expecting() -> &'static str {
+        "a string"
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        v.parse()
+            .map(AsString)
+            .map_err(|cause| de::Error::invalid_value(cause, Self::expecting()))
+    }
+}
+
+#[async_trait]
+impl<T> FromStream for AsString<T>
+where
+    T: FromStr + Send,
+    T::Err: fmt::Display,
+{
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = StringVisitor::<T> {
+            marker: PhantomData,
+        };
+
+        decoder.decode_string(visitor).await
+    }
+}
+
+impl<'en, T: fmt::Display> IntoStream<'en> for AsString<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.0.to_string())
+    }
+}
+
+impl<'en, T: fmt::Display> ToStream<'en> for AsString<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_str(&self.0.to_string())
+    }
+}
+
+#[async_trait]
+impl<T: From<Bytes> + Send> FromStream for AsBytes<T> {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let bytes = Bytes::from_stream((), decoder).await?;
+        Ok(Self(bytes.into()))
+    }
+}
+
+impl<'en, T: AsRef<[u8]>> IntoStream<'en> for AsBytes<T> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.encode_bytes(Bytes::copy_from_slice(self.0.as_ref()))
+    }
+}
+
+impl<'en, T: AsRef<[u8]>> ToStream<'en> for AsBytes<T> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_bytes(self.0.as_ref().iter().copied())
+    }
+}
+
+#[async_trait]
+impl<M, K, V> Visitor for MapVisitor<StringKeys<M>>
+where
+    M: FromIterator<(K, V)> + IntoIterator<Item = (K, V)> + Send,
+    K: FromStr + Send,
+    K::Err: fmt::Display,
+    V: FromStream<Context = ()>,
+{
+    type Value = StringKeys<M>;
+
+    fn expecting() -> &'static str {
+        "a map with string keys"
+    }
+
+    async fn visit_map<A: MapAccess>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(de::size_hint::cautious(map.size_hint()));
+
+        while let Some(AsString(key)) = map.next_key(()).await? {
+            let value = map.next_value(()).await?;
+            entries.push((key, value));
+        }
+
+        Ok(StringKeys(entries.into_iter().collect()))
+    }
+}
+
+#[async_trait]
+impl<M, K, V> FromStream for StringKeys<M>
+where
+    M: FromIterator<(K, V)> + IntoIterator<Item = (K, V)> + Send,
+    K: FromStr + Send,
+    K::Err: fmt::Display,
+    V: FromStream<Context = ()>,
+{
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        let visitor = MapVisitor::<Self> {
+            marker: PhantomData,
+        };
+
+        decoder.decode_map(visitor).await
+    }
+}
+
+impl<'en, M, K, V> IntoStream<'en> for StringKeys<M>
+where
+    M: IntoIterator<Item = (K, V)>,
+    K: fmt::Display + 'en,
+    V: IntoStream<'en> + 'en,
+{
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(
+            self.0
+                .into_iter()
+                .map(|(key, value)| (AsString(key), value)),
+        )
+    }
+}
+
+impl<'en, M: 'en, K, V> ToStream<'en> for StringKeys<M>
+where
+    &'en M: IntoIterator<Item = (K, V)>,
+    K: fmt::Display + 'en,
+    V: IntoStream<'en> + 'en,
+{
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        encoder.collect_map(
+            (&self.0)
+                .into_iter()
+                .map(|(key, value)| (AsString(key), value)),
+        )
+    }
+}
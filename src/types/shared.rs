@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, Weak};
+
+use async_trait::async_trait;
+
+use crate::de::{self, Decoder, FromStreamWith, SeqAccess, Visitor};
+use crate::en::{self, EncodeTuple, EncodeWith, Encoder, IntoStreamWith};
+
+/// The identifier of a node in an [`ArcPool`], assigned in the order in which nodes are encoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct RefId(pub u64);
+
+impl fmt::Display for RefId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+enum Slot<T> {
+    Pending(Vec<Arc<OnceLock<Weak<T>>>>),
+    Ready(Arc<T>),
+}
+
+/// A pool of shared nodes of type `T`, used as the encoding and decoding context of
+/// [`Shared`] and [`WeakRef`] to encode a directed acyclic graph, or a tree with back-references,
+/// without duplicating any node.
+///
+/// The first time a node is encoded it's assigned a new [`RefId`] and encoded as a tuple
+/// `[id, node]`; every subsequent reference to it is encoded as just its id. When decoding,
+/// each id is resolved to the same `Arc` it was first decoded as.
+///
+/// A strong reference cycle can't be constructed out of `Arc`s, so [`Shared`] nodes must form
+/// a DAG, but a [`WeakRef`] may refer back to a node which is still being decoded, such as the
+/// parent of the current node. A pool should only be used to encode or decode a single value.
+pub struct ArcPool<T> {
+    encoded: Mutex<HashMap<usize, RefId>>,
+    decoded: Vec<Slot<T>>,
+}
+
+impl<T> ArcPool<T> {
+    /// Construct a new, empty [`ArcPool`].
+    pub fn new() -> Self {
+        Self {
+            encoded: Mutex::new(HashMap::new()),
+            decoded: Vec::new(),
+        }
+    }
+
+    /// Return the id assigned to the given `node` while encoding, if it has been encoded.
+    pub fn id_of(&self, node: &Arc<T>) -> Option<RefId> {
+        self.lock().get(&address(node)).copied()
+    }
+
+    /// Return the node decoded with the given `id`, if it has been decoded completely.
+    pub fn get(&self, id: RefId) -> Option<&Arc<T>> {
+        match self.decoded.get(usize::try_from(id.0).ok()?) {
+            Some(Slot::Ready(node)) => Some(node),
+            _ => None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<usize, RefId>> {
+        match self.encoded.lock() {
+            Ok(encoded) => encoded,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Return the id of the given `node`, and `true` if it was newly assigned.
+    fn register(&self, node: &Arc<T>) -> (RefId, bool) {
+        let mut encoded = self.lock();
+        let next = RefId(encoded.len() as u64);
+        let id = *encoded.entry(address(node)).or_insert(next);
+        (id, id == next)
+    }
+
+    fn begin<E: de::Error>(&mut self, id: u64) -> Result<usize, E> {
+        if id == self.decoded.len() as u64 {
+            self.decoded.push(Slot::Pending(Vec::new()));
+            Ok(self.decoded.len() - 1)
+        } else {
+            Err(de::Error::invalid_value(
+                RefId(id),
+                format_args!("the next node id {}", RefId(self.decoded.len() as u64)),
+            ))
+        }
+    }
+
+    fn complete(&mut self, index: usize, node: Arc<T>) {
+        let slot = std::mem::replace(&mut self.decoded[index], Slot::Ready(node.clone()));
+
+        if let Slot::Pending(refs) = slot {
+            for target in refs {
+                let _ = target.set(Arc::downgrade(&node));
+            }
+        }
+    }
+
+    fn resolve<E: de::Error>(&self, id: u64) -> Result<Arc<T>, E> {
+        match self.slot(id)? {
+            Slot::Ready(node) => Ok(node.clone()),
+            Slot::Pending(_) => Err(de::Error::custom(format_args!(
+                "node {} contains a strong reference to itself",
+                RefId(id)
+            ))),
+        }
+    }
+
+    fn resolve_weak<E: de::Error>(&mut self, id: u64) -> Result<WeakRef<T>, E> {
+        self.slot(id)?;
+
+        let index = id as usize;
+        match &mut self.decoded[index] {
+            Slot::Ready(node) => Ok(WeakRef::new(node)),
+            Slot::Pending(refs) => {
+                let target = Arc::new(OnceLock::new());
+                refs.push(target.clone());
+                Ok(WeakRef { target })
+            }
+        }
+    }
+
+    fn slot<E: de::Error>(&self, id: u64) -> Result<&Slot<T>, E> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|index| self.decoded.get(index))
+            .ok_or_else(|| {
+                de::Error::invalid_value(RefId(id), "the id of a node which has been decoded")
+            })
+    }
+}
+
+impl<T> Default for ArcPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn address<T>(node: &Arc<T>) -> usize {
+    Arc::as_ptr(node) as usize
+}
+
+/// A node in a graph which may be referenced more than once, encoded and decoded using an
+/// [`ArcPool`] so that it's only encoded once.
+///
+/// `T` itself must implement [`FromStreamWith<ArcPool<T>>`](FromStreamWith) and, for a borrow,
+/// [`IntoStreamWith<&ArcPool<T>>`](IntoStreamWith), in order to pass the pool on to its children.
+#[derive(Debug)]
+pub struct Shared<T>(pub Arc<T>);
+
+impl<T> Shared<T> {
+    /// Construct a new [`Shared`] node.
+    pub fn new(node: T) -> Self {
+        Self(Arc::new(node))
+    }
+
+    /// Return the underlying `Arc`.
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = Arc<T>;
+
+    fn deref(&self) -> &Arc<T> {
+        &self.0
+    }
+}
+
+impl<T> From<Arc<T>> for Shared<T> {
+    fn from(node: Arc<T>) -> Self {
+        Self(node)
+    }
+}
+
+struct SharedVisitor<'a, T> {
+    pool: &'a mut ArcPool<T>,
+}
+
+#[async_trait]
+impl<'a, T> Visitor for SharedVisitor<'a, T>
+where
+    T: FromStreamWith<ArcPool<T>> + Sync,
+{
+    type Value = Shared<T>;
+
+    fn expecting() -> &'static str {
+        "a node id, or a tuple of a new node id and a node"
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        let id = u64::try_from(v).map_err(|_| E::invalid_value(v, Self::expecting()))?;
+        self.visit_u64(id)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.pool.resolve(v).map(Shared)
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let id = seq.expect_next::<u64>(()).await?;
+        let index = self.pool.begin(id)?;
+
+        let node = seq
+            .next_element_with::<T, ArcPool<T>>(self.pool)
+            .await?
+            .ok_or_else(|| de::Error::invalid_length(1, Self::expecting()))?;
+
+        let node = Arc::new(node);
+        self.pool.complete(index, node.clone());
+        Ok(Shared(node))
+    }
+}
+
+#[async_trait]
+impl<T> FromStreamWith<ArcPool<T>> for Shared<T>
+where
+    T: FromStreamWith<ArcPool<T>> + Sync,
+{
+    async fn from_stream_with<D: Decoder>(
+        pool: &mut ArcPool<T>,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_any(SharedVisitor { pool }).await
+    }
+}
+
+impl<'en, T> IntoStreamWith<'en, &'en ArcPool<T>> for &'en Shared<T>
+where
+    T: 'en,
+    &'en T: IntoStreamWith<'en, &'en ArcPool<T>>,
+{
+    fn into_stream_with<E: Encoder<'en>>(
+        self,
+        pool: &'en ArcPool<T>,
+        encoder: E,
+    ) -> Result<E::Ok, E::Error> {
+        match pool.register(&self.0) {
+            (id, false) => encoder.encode_u64(id.0),
+            (id, true) => {
+                let mut tuple = encoder.encode_tuple(2)?;
+                tuple.encode_element(id.0)?;
+                tuple.encode_element(EncodeWith::new(&*self.0, pool))?;
+                tuple.end()
+            }
+        }
+    }
+}
+
+/// A weak reference to a [`Shared`] node, encoded as the id of a node in an [`ArcPool`],
+/// e.g. a reference from a child node back to its parent.
+///
+/// The node must already have been encoded (or be in the process of being encoded), and a
+/// `WeakRef` to a node which has been dropped is encoded as `None`. A `WeakRef` to a node which
+/// is still being decoded can only be upgraded once that node has been decoded completely.
+pub struct WeakRef<T> {
+    target: Arc<OnceLock<Weak<T>>>,
+}
+
+impl<T> WeakRef<T> {
+    /// Construct a new [`WeakRef`] to the given `node`.
+    pub fn new(node: &Arc<T>) -> Self {
+        Self {
+            target: Arc::new(OnceLock::from(Arc::downgrade(node))),
+        }
+    }
+
+    /// Return the referenced node, if it still exists.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        self.target.get().and_then(Weak::upgrade)
+    }
+}
+
+impl<T> Clone for WeakRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target.clone(),
+        }
+    }
+}
+
+impl<T> Default for WeakRef<T> {
+    fn default() -> Self {
+        Self {
+            target: Arc::new(OnceLock::from(Weak::new())),
+        }
+    }
+}
+
+impl<T> fmt::Debug for WeakRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(WeakRef)")
+    }
+}
+
+struct WeakRefVisitor<'a, T> {
+    pool: &'a mut ArcPool<T>,
+}
+
+#[async_trait]
+impl<'a, T: Send + Sync> Visitor for WeakRefVisitor<'a, T> {
+    type Value = WeakRef<T>;
+
+    fn expecting() -> &'static str {
+        "an optional node id"
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        let id = u64::try_from(v).map_err(|_| E::invalid_value(v, Self::expecting()))?;
+        self.visit_u64(id)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.pool.resolve_weak(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(WeakRef::default())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(WeakRef::default())
+    }
+
+    async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+        decoder.decode_any(self).await
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync> FromStreamWith<ArcPool<T>> for WeakRef<T> {
+    async fn from_stream_with<D: Decoder>(
+        pool: &mut ArcPool<T>,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        decoder.decode_any(WeakRefVisitor { pool }).await
+    }
+}
+
+impl<'en, T> IntoStreamWith<'en, &'en ArcPool<T>> for &'en WeakRef<T> {
+    fn into_stream_with<E: Encoder<'en>>(
+        self,
+        pool: &'en ArcPool<T>,
+        encoder: E,
+    ) -> Result<E::Ok, E::Error> {
+        let node = match self.upgrade() {
+            Some(node) => node,
+            None => return encoder.encode_none(),
+        };
+
+        match pool.id_of(&node) {
+            Some(id) => encoder.encode_u64(id.0),
+            None => Err(en::Error::custom(
+                "a weak reference must refer to a node which has already been encoded",
+            )),
+        }
+    }
+}
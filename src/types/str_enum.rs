@@ -0,0 +1,102 @@
+/// Define a unit-only enum which is encoded and decoded as one of a fixed set of strings.
+///
+/// The enum implements `FromStream`, `IntoStream`, `ToStream`, and `Display`, and provides an
+/// `as_str` method and a `VARIANTS` constant listing its encoded names. Decoding an unknown
+/// string returns an error which lists the expected names.
+///
+/// ```
+/// destream::str_enum! {
+///     /// The status of a job.
+///     pub enum Status {
+///         Pending => "pending",
+///         Running => "running",
+///         Done => "done",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! str_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $encoded:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant),+
+        }
+
+        impl $name {
+            /// The encoded names of each variant, in order.
+            pub const VARIANTS: &'static [&'static str] = &[$($encoded),+];
+
+            /// Return the encoded name of this variant.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $encoded),+
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        const _: () = {
+            struct StrEnumVisitor;
+
+            impl $crate::de::Visitor for StrEnumVisitor {
+                type Value = $name;
+
+                fn expecting() -> &'static str {
+                    concat!("a variant of ", stringify!($name))
+                }
+
+                fn visit_string<E: $crate::de::Error>(self, v: String) -> Result<$name, E> {
+                    match v.as_str() {
+                        $($encoded => Ok($name::$variant),)+
+                        _ => Err($crate::de::Error::custom(format_args!(
+                            "unknown variant {:?}, expected one of {:?}",
+                            v,
+                            $name::VARIANTS,
+                        ))),
+                    }
+                }
+            }
+
+            #[$crate::__private::async_trait]
+            impl $crate::de::FromStream for $name {
+                type Context = ();
+
+                async fn from_stream<D: $crate::de::Decoder>(
+                    _context: (),
+                    decoder: &mut D,
+                ) -> Result<Self, D::Error> {
+                    decoder.decode_string(StrEnumVisitor).await
+                }
+            }
+
+            impl<'en> $crate::en::IntoStream<'en> for $name {
+                fn into_stream<E: $crate::en::Encoder<'en>>(
+                    self,
+                    encoder: E,
+                ) -> Result<E::Ok, E::Error> {
+                    encoder.encode_str(self.as_str())
+                }
+            }
+
+            impl<'en> $crate::en::ToStream<'en> for $name {
+                fn to_stream<E: $crate::en::Encoder<'en>>(
+                    &'en self,
+                    encoder: E,
+                ) -> Result<E::Ok, E::Error> {
+                    encoder.encode_str(self.as_str())
+                }
+            }
+        };
+    };
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use async_trait::async_trait;
+
+use crate::de::{self, Decoder, FromStream, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+/// A collection of unique elements which a [`UniqueSeq`] can decode into.
+pub trait UniqueCollection: Default + Send {
+    /// The type of element in this collection.
+    type Item;
+
+    /// Insert the given `item`, and return `false` if an equal item was already present.
+    fn insert_unique(&mut self, item: Self::Item) -> bool;
+}
+
+impl<T: Ord + Send> UniqueCollection for BTreeSet<T> {
+    type Item = T;
+
+    fn insert_unique(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+}
+
+impl<T, S> UniqueCollection for HashSet<T, S>
+where
+    T: Eq + Hash + Send,
+    S: BuildHasher + Default + Send,
+{
+    type Item = T;
+
+    fn insert_unique(&mut self, item: T) -> bool {
+        self.insert(item)
+    }
+}
+
+/// What a [`UniqueSeq`] should do when it decodes an element equal to one it already contains.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum DuplicatePolicy {
+    /// Return an error.
+    #[default]
+    Error,
+    /// Keep the first element and drop the duplicate, like a plain `BTreeSet` or `HashSet`.
+    Ignore,
+    /// Keep the first element and drop the duplicate, but count it
+    /// (see [`UniqueSeq::duplicates`]).
+    Count,
+}
+
+/// A set, such as a `BTreeSet` or `HashSet`, decoded from a sequence which is expected not to
+/// contain any duplicate elements.
+///
+/// Decoding a set directly silently drops duplicate elements. A `UniqueSeq` instead applies the
+/// [`DuplicatePolicy`] given as its decoding context, which by default is to return an error,
+/// since a duplicate element in a supposedly unique collection often indicates corrupt input.
+///
+/// A `UniqueSeq` is encoded exactly like the set it wraps.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UniqueSeq<S> {
+    set: S,
+    duplicates: usize,
+}
+
+impl<S> UniqueSeq<S> {
+    /// Return the number of duplicate elements which were dropped while decoding
+    /// with [`DuplicatePolicy::Count`], or zero otherwise.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Return the decoded set.
+    pub fn into_inner(self) -> S {
+        self.set
+    }
+}
+
+impl<S> Deref for UniqueSeq<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.set
+    }
+}
+
+impl<S> From<S> for UniqueSeq<S> {
+    fn from(set: S) -> Self {
+        Self { set, duplicates: 0 }
+    }
+}
+
+struct UniqueSeqVisitor<S> {
+    policy: DuplicatePolicy,
+    marker: PhantomData<fn() -> S>,
+}
+
+#[async_trait]
+impl<S> Visitor for UniqueSeqVisitor<S>
+where
+    S: UniqueCollection,
+    S::Item: FromStream<Context = ()>,
+{
+    type Value = UniqueSeq<S>;
+
+    fn expecting() -> &'static str {
+        "a sequence of unique elements"
+    }
+
+    async fn visit_seq<A: SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut set = S::default();
+        let mut duplicates = 0;
+        let mut index = 0;
+
+        while let Some(item) = seq.next_element(()).await? {
+            if !set.insert_unique(item) {
+                match self.policy {
+                    DuplicatePolicy::Error => {
+                        return Err(de::Error::invalid_value(
+                            format_args!("a duplicate element at index {}", index),
+                            Self::expecting(),
+                        ))
+                    }
+                    DuplicatePolicy::Ignore => {}
+                    DuplicatePolicy::Count => duplicates += 1,
+                }
+            }
+
+            index += 1;
+        }
+
+        Ok(UniqueSeq { set, duplicates })
+    }
+}
+
+#[async_trait]
+impl<S> FromStream for UniqueSeq<S>
+where
+    S: UniqueCollection,
+    S::Item: FromStream<Context = ()>,
+{
+    type Context = DuplicatePolicy;
+
+    async fn from_stream<D: Decoder>(
+        policy: DuplicatePolicy,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let visitor = UniqueSeqVisitor {
+            policy,
+            marker: PhantomData,
+        };
+
+        decoder.decode_seq(visitor).await
+    }
+}
+
+impl<'en, S: IntoStream<'en>> IntoStream<'en> for UniqueSeq<S> {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.set.into_stream(encoder)
+    }
+}
+
+impl<'en, S: ToStream<'en>> ToStream<'en> for UniqueSeq<S> {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        self.set.to_stream(encoder)
+    }
+}
@@ -0,0 +1,381 @@
+//! A dynamically-typed [`Value`] which can hold any self-describing stream.
+//!
+//! Strings, byte strings, sequences, and maps are reference-counted, so cloning a [`Value`]
+//! (or re-encoding it with [`IntoStream`]) never copies their contents. Use the `make_*_mut`
+//! methods to edit a [`Value`] in place, which clones only the parts of the tree being edited.
+//!
+//! Use [`merge`] to apply a JSON merge patch to a [`Value`], or
+//! [`patch::transcode`](crate::patch::transcode) to apply one to a stream while transcoding it.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::de::{self, ArrayAccess, Decoder, FromStream, MapAccess, SeqAccess, Visitor};
+use crate::en::{Encoder, IntoStream, ToStream};
+
+const BUF_SIZE: usize = 1_024;
+
+/// A dynamically-typed value, decoded from any self-describing stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    None,
+    Unit,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    String(Arc<String>),
+    Bytes(Bytes),
+    Seq(Arc<Vec<Value>>),
+    Map(Arc<Vec<(Value, Value)>>),
+}
+
+impl Value {
+    /// Return `true` if this is [`Value::None`].
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Borrow this value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Borrow the elements of this value, if it is a sequence.
+    pub fn as_seq(&self) -> Option<&[Value]> {
+        match self {
+            Self::Seq(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    /// Borrow the entries of this value, if it is a map.
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Look up the value of the entry with the given string `key`, if this value is a map.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    /// Mutably borrow this value as a string, if it is one, cloning it first if it is shared.
+    pub fn make_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::String(string) => Some(Arc::make_mut(string)),
+            _ => None,
+        }
+    }
+
+    /// Mutably borrow the elements of this value, if it is a sequence,
+    /// cloning the sequence (but not its elements) first if it is shared.
+    pub fn make_seq_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Self::Seq(seq) => Some(Arc::make_mut(seq)),
+            _ => None,
+        }
+    }
+
+    /// Mutably borrow the entries of this value, if it is a map,
+    /// cloning the map (but not its entries) first if it is shared.
+    pub fn make_map_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match self {
+            Self::Map(map) => Some(Arc::make_mut(map)),
+            _ => None,
+        }
+    }
+
+    /// Mutably borrow the value of the entry with the given string `key`, if this value is a map,
+    /// cloning the map (but not its entries) first if it is shared.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.make_map_mut()?
+            .iter_mut()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, value)| value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::I64(i)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(u: u64) -> Self {
+        Self::U64(u)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Self::F64(f)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(Arc::new(s))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(Arc::new(s.to_string()))
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(bytes: Bytes) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(seq: Vec<Value>) -> Self {
+        Self::Seq(Arc::new(seq))
+    }
+}
+
+impl From<Vec<(Value, Value)>> for Value {
+    fn from(map: Vec<(Value, Value)>) -> Self {
+        Self::Map(Arc::new(map))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map(Into::into).unwrap_or(Self::None)
+    }
+}
+
+pub(crate) struct ValueVisitor;
+
+macro_rules! value_visitor {
+    (
+        $($visit:ident($ty:ty) => $variant:ident),+;
+        $($visit_array:ident($elem:ty) => $elem_variant:ident),+
+    ) => {
+        #[async_trait]
+        impl Visitor for ValueVisitor {
+            type Value = Value;
+
+            fn expecting() -> &'static str {
+                "any value"
+            }
+
+            $(
+                fn $visit<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    Ok(Value::$variant(v.into()))
+                }
+            )+
+
+            $(
+                async fn $visit_array<A: ArrayAccess<$elem>>(
+                    self,
+                    mut array: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let mut seq: Vec<Value> = Vec::new();
+                    let mut buf = [<$elem>::default(); BUF_SIZE];
+
+                    loop {
+                        let len = array.buffer(&mut buf).await?;
+                        if len == 0 {
+                            break;
+                        }
+
+                        seq.extend(buf[..len].iter().map(|v| Value::$elem_variant((*v).into())));
+                    }
+
+                    Ok(seq.into())
+                }
+            )+
+
+            async fn visit_array_u8<A: ArrayAccess<u8>>(
+                self,
+                mut array: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::new();
+                let mut buf = [0u8; BUF_SIZE];
+
+                loop {
+                    let len = array.buffer(&mut buf).await?;
+                    if len == 0 {
+                        break;
+                    }
+
+                    bytes.extend_from_slice(&buf[..len]);
+                }
+
+                Ok(Value::Bytes(bytes.into()))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(v.into())
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(Value::Unit)
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(Value::None)
+            }
+
+            async fn visit_some<D: Decoder>(self, decoder: &mut D) -> Result<Self::Value, D::Error> {
+                Value::from_stream((), decoder).await
+            }
+
+            async fn visit_map<A: MapAccess>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut map: Vec<(Value, Value)> =
+                    Vec::with_capacity(de::size_hint::cautious(access.size_hint()));
+
+                while let Some(key) = access.next_key(()).await? {
+                    let value = access.next_value(()).await?;
+                    map.push((key, value));
+                }
+
+                Ok(map.into())
+            }
+
+            async fn visit_seq<A: SeqAccess>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut seq: Vec<Value> =
+                    Vec::with_capacity(de::size_hint::cautious(access.size_hint()));
+
+                while let Some(element) = access.next_element(()).await? {
+                    seq.push(element);
+                }
+
+                Ok(seq.into())
+            }
+        }
+    };
+}
+
+value_visitor!(
+    visit_bool(bool) => Bool,
+    visit_i8(i8) => I64,
+    visit_i16(i16) => I64,
+    visit_i32(i32) => I64,
+    visit_i64(i64) => I64,
+    visit_u8(u8) => U64,
+    visit_u16(u16) => U64,
+    visit_u32(u32) => U64,
+    visit_u64(u64) => U64,
+    visit_f32(f32) => F64,
+    visit_f64(f64) => F64;
+    visit_array_bool(bool) => Bool,
+    visit_array_i8(i8) => I64,
+    visit_array_i16(i16) => I64,
+    visit_array_i32(i32) => I64,
+    visit_array_i64(i64) => I64,
+    visit_array_u16(u16) => U64,
+    visit_array_u32(u32) => U64,
+    visit_array_u64(u64) => U64,
+    visit_array_f32(f32) => F64,
+    visit_array_f64(f64) => F64
+);
+
+#[async_trait]
+impl FromStream for Value {
+    type Context = ();
+
+    async fn from_stream<D: Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_any(ValueVisitor).await
+    }
+}
+
+/// Apply a JSON merge `patch` ([RFC 7386](https://www.rfc-editor.org/rfc/rfc7386)) to `base`.
+///
+/// If `patch` is a map, each of its entries is merged recursively into the matching entry of
+/// `base` (or into an empty map, if `base` is not a map), and a [`Value::None`] entry removes
+/// the matching entry. Otherwise `patch` replaces `base`.
+///
+/// Only the maps along the patched paths are cloned, and only if they are shared.
+pub fn merge(base: Value, patch: Value) -> Value {
+    let patch = match patch {
+        Value::Map(patch) => Arc::try_unwrap(patch).unwrap_or_else(|patch| (*patch).clone()),
+        patch => return patch,
+    };
+
+    let mut base = match base {
+        Value::Map(base) => base,
+        _ => Arc::new(Vec::with_capacity(patch.len())),
+    };
+
+    let entries = Arc::make_mut(&mut base);
+
+    for (key, patch) in patch {
+        let position = entries.iter().position(|(k, _)| k == &key);
+
+        match position {
+            Some(i) if patch.is_none() => {
+                entries.remove(i);
+            }
+            Some(i) => {
+                let value = std::mem::replace(&mut entries[i].1, Value::None);
+                entries[i].1 = merge(value, patch);
+            }
+            None if patch.is_none() => {}
+            None => entries.push((key, merge(Value::None, patch))),
+        }
+    }
+
+    Value::Map(base)
+}
+
+impl<'en> IntoStream<'en> for Value {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        match self {
+            Self::None => encoder.encode_none(),
+            Self::Unit => encoder.encode_unit(),
+            Self::Bool(b) => encoder.encode_bool(b),
+            Self::I64(i) => encoder.encode_i64(i),
+            Self::U64(u) => encoder.encode_u64(u),
+            Self::F64(f) => encoder.encode_f64(f),
+            Self::String(s) => encoder.encode_str(&s),
+            Self::Bytes(bytes) => encoder.encode_bytes(bytes),
+            // a shared sequence or map is cloned shallowly, so its leaves are not copied
+            Self::Seq(seq) => {
+                encoder.collect_seq(Arc::try_unwrap(seq).unwrap_or_else(|seq| (*seq).clone()))
+            }
+            Self::Map(map) => {
+                encoder.collect_map(Arc::try_unwrap(map).unwrap_or_else(|map| (*map).clone()))
+            }
+        }
+    }
+}
+
+impl<'en> ToStream<'en> for Value {
+    fn to_stream<E: Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        match self {
+            Self::None => encoder.encode_none(),
+            Self::Unit => encoder.encode_unit(),
+            Self::Bool(b) => encoder.encode_bool(*b),
+            Self::I64(i) => encoder.encode_i64(*i),
+            Self::U64(u) => encoder.encode_u64(*u),
+            Self::F64(f) => encoder.encode_f64(*f),
+            Self::String(s) => encoder.encode_str(s),
+            Self::Bytes(bytes) => encoder.encode_bytes(bytes.clone()),
+            Self::Seq(seq) => encoder.collect_seq(seq.iter()),
+            Self::Map(map) => encoder.collect_map(map.iter().map(|(k, v)| (k, v))),
+        }
+    }
+}
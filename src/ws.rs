@@ -0,0 +1,121 @@
+//! Adapters between destream formats and a message-based transport such as a WebSocket, which
+//! treat each message as a standalone encoded document.
+//!
+//! A format only has to provide a function to decode a value from a stream of chunks, or to encode
+//! a value as a stream of chunks, e.g. `|payload| format::decode((), payload)` and
+//! `format::encode`. The adapters in this module handle the conversion between messages and
+//! chunks, in both directions.
+
+use std::fmt;
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{self, Future};
+use futures::sink::{Sink, SinkExt};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+
+use crate::{de, en};
+
+/// A message of a transport which carries one encoded document per message.
+pub trait Frame: Sized {
+    /// Return the payload of this frame, or `None` if it's a control frame with no document.
+    fn into_payload(self) -> Option<Bytes>;
+
+    /// Construct a new frame to carry the given `payload`.
+    fn from_payload(payload: Bytes) -> Self;
+}
+
+impl Frame for Bytes {
+    fn into_payload(self) -> Option<Bytes> {
+        Some(self)
+    }
+
+    fn from_payload(payload: Bytes) -> Self {
+        payload
+    }
+}
+
+impl Frame for tungstenite::Message {
+    fn into_payload(self) -> Option<Bytes> {
+        match self {
+            Self::Binary(payload) => Some(payload),
+            Self::Text(payload) => Some(payload.into()),
+            Self::Ping(_) | Self::Pong(_) | Self::Close(_) | Self::Frame(_) => None,
+        }
+    }
+
+    fn from_payload(payload: Bytes) -> Self {
+        Self::Binary(payload)
+    }
+}
+
+/// The source stream of the payload of a single frame, decoded by [`decode_frames`].
+pub type Payload<E> = stream::Once<future::Ready<Result<Bytes, E>>>;
+
+/// Decode each frame of the given `frames` stream as a standalone document with the given
+/// `decode` function, e.g. `|payload| format::decode((), payload)`.
+///
+/// Control frames are skipped. A transport error is converted into a decoding error.
+pub fn decode_frames<M, X, S, T, E, F, Fut>(
+    frames: S,
+    mut decode: F,
+) -> impl Stream<Item = Result<T, E>>
+where
+    M: Frame,
+    X: fmt::Display,
+    S: Stream<Item = Result<M, X>>,
+    E: de::Error,
+    F: FnMut(Payload<E>) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    frames
+        .map_err(E::custom)
+        .try_filter_map(|frame| future::ready(Ok(frame.into_payload())))
+        .and_then(move |payload| decode(stream::once(future::ready(Ok(payload)))))
+}
+
+/// Encode each value of the given `values` stream as a standalone document with the given
+/// `encode` function, e.g. `format::encode`, and return a stream of frames which each carry
+/// one document.
+pub fn encode_frames<M, S, E, F, C>(values: S, mut encode: F) -> impl Stream<Item = Result<M, E>>
+where
+    M: Frame,
+    S: Stream,
+    F: FnMut(S::Item) -> Result<C, E>,
+    C: Stream<Item = Result<Bytes, E>>,
+{
+    values.then(move |value| {
+        let chunks = encode(value);
+
+        async move {
+            let payload = chunks?
+                .try_fold(BytesMut::new(), |mut payload, chunk| {
+                    payload.extend_from_slice(&chunk);
+                    future::ready(Ok(payload))
+                })
+                .await?;
+
+            Ok(M::from_payload(payload.freeze()))
+        }
+    })
+}
+
+/// Encode each value of the given `values` stream as a standalone document with the given
+/// `encode` function and send it to the given `sink` of frames, e.g. the outgoing half of a
+/// WebSocket, then flush the `sink`.
+///
+/// A transport error is converted into an encoding error.
+pub async fn send_frames<M, Si, S, E, F, C>(sink: &mut Si, values: S, encode: F) -> Result<(), E>
+where
+    M: Frame,
+    Si: Sink<M> + Unpin,
+    Si::Error: fmt::Display,
+    S: Stream,
+    E: en::Error,
+    F: FnMut(S::Item) -> Result<C, E>,
+    C: Stream<Item = Result<Bytes, E>>,
+{
+    let frames = encode_frames(values, encode);
+    futures::pin_mut!(frames);
+
+    sink.sink_map_err(E::custom).send_all(&mut frames).await
+}